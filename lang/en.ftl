@@ -1,7 +1,7 @@
 # Title buttons
 reset = Reset
-load-json = Load from JSON
-export-json = Export to JSON
+load-file = Load from a file
+export = Export
 load-example = Load an example
 help = Help
 github = Project on GitHub
@@ -12,13 +12,45 @@ add-node = Add node
 set-observations = Set observations
 compute-beliefs = Compute beliefs
 mutual-info = Mutual information
+model-info = Model information
+health = Model health
+text-editor = Text editor
+learn-from-data = Learn from data
 
 # Loading messages
 choose-file-lo-load = Choose a file to load
 invalid-json = The provided input is not valid JSON
-err-cycle = The input graph cannot be loaded as it contains a cycle.
-err-nodenotfound = The input graph cannot be loaded as it contains references to non-existing nodes.
-err-edges = The input graph cannot be loaded as it contains duplicate edges.
+invalid-xml = The provided input is not valid XML
+invalid-yaml = The provided input is not valid YAML
+err-version = This file was saved in format version {$version}, which this version of the app cannot read.
+err-syntax = Line {$line}: expected {$expected}, but found {$found}.
+syntax-name = a name
+syntax-number = a number
+syntax-variable = a declared variable
+syntax-value-of = a value of "{$node}"
+syntax-end = the end of the file
+export-format = Choose the format to export the model to:
+err-invalid = This model cannot be loaded:
+load-report = Some problems were found in this file:
+load-anyway = Load anyway, dropping the invalid data
+val-error = Error
+val-warning = Warning
+val-issue = {$severity}: node "{$node}", field "{$field}": {$problem}
+val-duplicate-id = the identifier "{$id}" is used by several nodes
+val-unknown-parent = the parent "{$parent}" does not exist
+val-cycle = the edge from "{$parent}" would create a cycle
+val-duplicate-edge = the edge from "{$parent}" is listed several times
+val-no-values = the node has no values
+val-duplicate-value = the value "{$value}" is listed several times
+val-observation = the observation {$observation} is out of range, the node has {$values} values
+val-credencies-length = {$expected} credencies were expected, but {$found} were found
+val-negative-credency = the credency {$value} is not a positive number, it will be treated as 0
+val-descriptions-count = {$expected} row explanations were expected, but {$found} were found
+val-sample-sizes-count = {$expected} sample sizes were expected, but {$found} were found, the table is read as certain
+val-invalid-rules = the rules do not match the values of the node and of its parents
+val-unsupported = {$construct} is not supported and was ignored
+val-unknown-name = "{$name}" is neither a value of the node or of its parents, nor a parent
+val-missing-credency = no credency is given for "{$row}"
 loading-help = Help content is loading...
 
 # Generic words
@@ -36,13 +68,40 @@ write-desc = Write a description for this node...
 row-desc = Description for this row...
 parent-values = Parent values
 explanation = Explanation
+sample-size = Sample size
+sample-size-help = How many cases the row is worth, making its credencies uncertain; leave empty when they are known exactly
 save-credencies = Save credencies
 default-node-name = Node #{$id}
 duplicate-node = Duplicate this node
 remove-node = Delete this node
+absorb-node = Marginalize out this node
+absorb-node-help = Delete this node, its children inheriting its parents so that the probabilities of the other nodes are unchanged
 press-enter = Press Enter to submit
 obs-for-node = Observation:
 add-parent = Add a parent...
+default-row = Default
+any-value = (any)
+add-rule = Add a rule
+use-rules = Use rules
+use-full-table = Use the full table
+rules-help = Rules are tried in order, the first one matching the parent values applies. The default row applies when no rule matches.
+move-left = Move left
+move-right = Move right
+reverse-edge = Reverse this edge, keeping the same joint distribution
+export-table-csv = Export the table as CSV
+import-table-csv = Import a table from CSV...
+table-csv-errors = The table was left unchanged because of these problems:
+csv-cell = Line {$line}, column {$column}: {$problem}
+csv-line = Line {$line}: {$problem}
+csv-unknown-column = "{$found}" is neither a parent, a value of the node nor the explanation
+csv-missing-column = no column is given for "{$name}"
+csv-value-of = "{$found}" is not a value of "{$parent}"
+csv-number = "{$found}" is not a positive number
+csv-duplicate-row = these parent values were already given on line {$line}
+csv-missing-row = no row is given for {$values}
+paste-errors = The cells were not pasted because of these problems:
+paste-cell = Row "{$row}", value "{$value}": {$problem}
+paste-too-large = The pasted cells do not fit in the table: at most {$rows} rows and {$columns} columns can be pasted from this cell
 
 # Result messages
 node = Node "{$name}":
@@ -57,3 +116,102 @@ target-node = Target node:
 mi-no-value = Mutual information cannot be computed if no node is unobserved.
 with-node = With node "{$name}":
 probabilities = Probabilities
+belief-interval = (90% interval: {$low}% – {$high}%)
+
+# Model information
+model-title = Title:
+model-question = Question being investigated:
+model-author = Author:
+model-license = License:
+model-created = Created on:
+
+# Health report
+health-results = Health report of the model:
+lint-nothing = No problem was found in this model.
+prior = Prior
+lint-no-values = has no values, so inference cannot be done
+lint-unset-credencies = its credencies were never set, all its values are considered equally likely
+lint-zero-row = all credencies are 0 when {$row}
+lint-near-deterministic = "{$value}" has a probability of {$probability} when {$row}, which makes inference brittle
+lint-orphan = is not connected to any other node
+lint-duplicate-label = has the same name as another node
+lint-unexplained-rows = {$count} rows have no explanation: {$rows}
+
+# Text editor
+text-editor-help = Edit the model as text, the graph is updated as soon as the text is valid. See the help for the syntax.
+text-editor-problems = The model was updated, but some problems were found:
+
+# Learning from data
+data-help = Load a CSV file of cases: each column is matched with the node of the same name, and each cell with one of its values. Empty cells, "?" and "NA" are missing values.
+choose-dataset = Choose a CSV file...
+dataset-empty = The file is empty.
+dataset-summary = The dataset has {$rows} rows and {$columns} columns.
+dataset-unknown-columns = These columns match no node and are ignored: {$columns}
+dataset-unknown-cells = {$count} cells are not a value of their node, and are read as missing:
+dataset-skipped-nodes = These nodes, or one of their parents, have no column, so their table can only be learned by expectation-maximization:
+learn-parameters = Learn the tables
+learn-parameters-help = The tables of the nodes are replaced by the frequencies of their values for each combination of values of their parents. The rows where the node or one of its parents is missing are left out of its table. Bayesian estimation adds a pseudo-count to every cell, so that combinations absent from the data are not impossible.
+maximum-likelihood = Maximum likelihood
+bayesian-estimation = Bayesian estimation
+pseudo-count = Pseudo-count:
+parameters-learned = The tables of {$count} nodes were learned.
+learned-from = learned from {$count} rows
+nodes-to-learn = Nodes to learn, uncheck the ones whose table was given by experts to keep it:
+em = Expectation-maximization
+em-help = Expectation-maximization also learns from the rows with missing cells, and the tables of the nodes that have no column at all. It alternates computing the probabilities of the missing values with the current tables, and learning the tables from them, until the likelihood of the data stops increasing.
+em-no-values = All the nodes need values to run expectation-maximization.
+run-em = Run expectation-maximization
+run-em-again = Run again
+em-likelihood = The log-likelihood of the data went from {$first} to {$last}.
+em-converged = Converged after {$iterations} iterations.
+em-not-converged = Did not converge after {$iterations} iterations, you can run it again to continue.
+em-impossible-rows = {$count} rows are impossible according to the model and were left out.
+learn-structure = Learn a structure
+learn-structure-help = Replace the model by a new one with a node for each column, whose edges are chosen to fit the data, then learn its tables. The search adds, removes or reverses one edge at a time while this improves the score, each node having at most {$max} parents. Tabu search goes on past the first structure that no move improves, and keeps the best one found.
+score-bic = BIC score
+score-bdeu = BDeu score
+equivalent-sample-size = Equivalent sample size:
+hill-climbing = Hill-climbing
+tabu-search = Tabu search
+edge-constraints = Edges that the structure must have, or must not have:
+require-parent = require a parent
+forbid-parent = forbid a parent
+required-edges = Required edges:
+forbidden-edges = Forbidden edges:
+structure-cycle = The required edge from {$parent} to {$child} makes a cycle with the other required edges.
+structure-learned = The structure was learned in {$moves} moves, with a score of {$score}.
+sample-cases = Sample cases
+sample-cases-help = Draw random cases from the model and download them as a CSV file, to practice learning or to test it. Forward sampling ignores the observations, rejection sampling only keeps the cases that agree with them, and likelihood weighting sets the observed nodes and gives each case a weight in the last column.
+sample-no-values = All the nodes need values to sample cases.
+sample-count = Number of cases:
+forward-sampling = Forward sampling
+rejection-sampling = Rejection sampling
+likelihood-weighting = Likelihood weighting
+download-cases = Download the cases
+sample-weight = weight
+sample-rejected = {$kept} cases were kept out of {$drawn} drawn.
+sample-gave-up = The observations are too unlikely: only {$kept} cases were kept out of {$drawn} drawn.
+batch-inference = Inference on each row
+batch-inference-help = Compute the probabilities of the target nodes for each row of the dataset, given the observations of the other cells of the row, and download them as a CSV file after the columns of the dataset.
+batch-no-values = All the nodes need values to compute probabilities.
+target-nodes = Target nodes:
+download-posteriors = Download the probabilities
+posteriors-computed = The probabilities were computed, and downloaded.
+posteriors-impossible-rows = {$count} rows are impossible according to the model, their probabilities were left empty.
+evaluate-classifier = Evaluate the predictions
+evaluate-classifier-help = Predict a target node in each row of the dataset from the other cells of the row, with the same inference as the beliefs page, and compare the predictions with the value of the target in the row.
+choose-target = choose the target
+evaluate = Evaluate
+evaluation-cases = {$count} rows were evaluated.
+evaluation-unlabelled = {$count} rows have no value for the target and were left out.
+evaluation-impossible = {$count} rows are impossible according to the model and were left out.
+accuracy = Accuracy: {$value}%
+log-loss = Log-loss: {$value}
+brier-score = Brier score: {$value}
+actual-predicted = Actual \ predicted
+reliability-diagram = Reliability diagram: the average predicted probability against how often the value actually came, for groups of similar predictions. The closer to the diagonal, the better calibrated the model.
+build-classifier = Build a classifier
+build-classifier-help = Replace the model by a classifier predicting the chosen column from the others, then learn its tables. Naive Bayes links the class to every other node, which are then independent given the class. Tree-augmented naive Bayes also links the other nodes by the tree that best fits the data, each having at most one parent besides the class.
+choose-class = choose the class
+naive-bayes = Naive Bayes
+tree-augmented-naive-bayes = Tree-augmented naive Bayes
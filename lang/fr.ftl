@@ -1,7 +1,7 @@
 # Title buttons
 reset = Remise à zéro
-load-json = Charger depuis du JSON
-export-json = Exporter en JSON
+load-file = Charger depuis un fichier
+export = Exporter
 load-example = Charger un example
 help = Aide
 github = Projet sur GitHub
@@ -12,13 +12,45 @@ add-node = Ajouter un nœud
 set-observations = Fixer les observations
 compute-beliefs = Calculer les croyances
 mutual-info = Information mutuelle
+model-info = Informations du modèle
+health = Santé du modèle
+text-editor = Éditeur de texte
+learn-from-data = Apprendre des données
 
 # Loading messages
 choose-file-lo-load = Choisissez un fichier à charger
 invalid-json = Le text entré n'est pas du JSON valide
-err-cycle = Le graphe n'a pas pu être chargé car il contient un cycle.
-err-nodenotfound = Le graphe n'a pas pu être chargé car il contient des références à des nœuds inexistants.
-err-edges = Le graphe n'a pas pu être chargé car il contient des arrêtes en double.
+invalid-xml = Le texte entré n'est pas du XML valide
+invalid-yaml = Le texte entré n'est pas du YAML valide
+err-version = Ce fichier a été enregistré au format de version {$version}, que cette version de l'application ne peut pas lire.
+err-syntax = Ligne {$line} : {$expected} était attendu, mais {$found} a été trouvé.
+syntax-name = un nom
+syntax-number = un nombre
+syntax-variable = une variable déclarée
+syntax-value-of = une valeur de « {$node} »
+syntax-end = la fin du fichier
+export-format = Choisissez le format dans lequel exporter le modèle :
+err-invalid = Ce modèle ne peut pas être chargé :
+load-report = Des problèmes ont été trouvés dans ce fichier :
+load-anyway = Charger malgré tout, en ignorant les données invalides
+val-error = Erreur
+val-warning = Avertissement
+val-issue = {$severity} : nœud « {$node} », champ « {$field} » : {$problem}
+val-duplicate-id = l'identifiant « {$id} » est utilisé par plusieurs nœuds
+val-unknown-parent = le parent « {$parent} » n'existe pas
+val-cycle = l'arc depuis « {$parent} » créerait un cycle
+val-duplicate-edge = l'arc depuis « {$parent} » est présent plusieurs fois
+val-no-values = le nœud n'a pas de valeur
+val-duplicate-value = la valeur « {$value} » est présente plusieurs fois
+val-observation = l'observation {$observation} est hors limites, le nœud a {$values} valeurs
+val-credencies-length = {$expected} crédences étaient attendues, mais {$found} ont été trouvées
+val-negative-credency = la crédence {$value} n'est pas un nombre positif, elle sera traitée comme 0
+val-descriptions-count = {$expected} explications de lignes étaient attendues, mais {$found} ont été trouvées
+val-sample-sizes-count = {$expected} tailles d'échantillon étaient attendues, mais {$found} ont été trouvées, la table est lue comme certaine
+val-invalid-rules = les règles ne correspondent pas aux valeurs du nœud et de ses parents
+val-unsupported = {$construct} n'est pas pris en charge et a été ignoré
+val-unknown-name = « {$name} » n'est ni une valeur du nœud ou de ses parents, ni un parent
+val-missing-credency = aucune crédence n'est donnée pour « {$row} »
 loading-help = Le contenu de l'aide est en chargement...
 
 # Generic words
@@ -36,13 +68,40 @@ write-desc = Écrivez une description pour ce nœud...
 row-desc = Description pour cette ligne...
 parent-values = Valeurs parentes
 explanation = Explication
+sample-size = Taille d'échantillon
+sample-size-help = Combien de cas vaut la ligne, rendant ses crédences incertaines ; laissez vide quand elles sont connues exactement
 save-credencies = Enregistrer les crédences
 default-node-name = Nœud #{$id}
 duplicate-node = Dupliquer ce nœud
 remove-node = Supprimer ce nœud
+absorb-node = Marginaliser ce nœud
+absorb-node-help = Supprimer ce nœud, ses enfants héritant de ses parents pour que les probabilités des autres nœuds soient inchangées
 press-enter = Appuyez sur Entrée pour valider
 obs-for-node = Observation :
 add-parent = Ajouter un parent...
+default-row = Par défaut
+any-value = (toutes)
+add-rule = Ajouter une règle
+use-rules = Utiliser des règles
+use-full-table = Utiliser la table complète
+rules-help = Les règles sont essayées dans l'ordre, la première correspondant aux valeurs des parents s'applique. La ligne par défaut s'applique si aucune règle ne correspond.
+move-left = Déplacer à gauche
+move-right = Déplacer à droite
+reverse-edge = Inverser cet arc en conservant la même distribution jointe
+export-table-csv = Exporter la table en CSV
+import-table-csv = Importer une table depuis un CSV...
+table-csv-errors = La table n'a pas été modifiée à cause de ces problèmes :
+csv-cell = Ligne {$line}, colonne {$column} : {$problem}
+csv-line = Ligne {$line} : {$problem}
+csv-unknown-column = « {$found} » n'est ni un parent, ni une valeur du nœud, ni l'explication
+csv-missing-column = aucune colonne n'est donnée pour « {$name} »
+csv-value-of = « {$found} » n'est pas une valeur de « {$parent} »
+csv-number = « {$found} » n'est pas un nombre positif
+csv-duplicate-row = ces valeurs des parents ont déjà été données ligne {$line}
+csv-missing-row = aucune ligne n'est donnée pour {$values}
+paste-errors = Les cellules n'ont pas été collées à cause de ces problèmes :
+paste-cell = Ligne « {$row} », valeur « {$value} » : {$problem}
+paste-too-large = Les cellules collées ne tiennent pas dans la table : au plus {$rows} lignes et {$columns} colonnes peuvent être collées depuis cette cellule
 
 # Result messages
 node = Nœud « {$name} » :
@@ -57,3 +116,102 @@ target-node = Nœud cible :
 mi-no-value = L'information mutuelle ne peut pas être calculée si tous les nœuds sont observés.
 with-node = Avec le nœud « {$name} » :
 probabilities = Probabilitiés
+belief-interval = (intervalle à 90 % : {$low} % – {$high} %)
+
+# Model information
+model-title = Titre :
+model-question = Question étudiée :
+model-author = Auteur :
+model-license = Licence :
+model-created = Créé le :
+
+# Health report
+health-results = Rapport de santé du modèle :
+lint-nothing = Aucun problème n'a été trouvé dans ce modèle.
+prior = A priori
+lint-no-values = n'a pas de valeur, l'inférence ne peut donc pas être effectuée
+lint-unset-credencies = ses crédences n'ont jamais été définies, toutes ses valeurs sont considérées comme équiprobables
+lint-zero-row = toutes les crédences sont à 0 quand {$row}
+lint-near-deterministic = « {$value} » a une probabilité de {$probability} quand {$row}, ce qui rend l'inférence fragile
+lint-orphan = n'est relié à aucun autre nœud
+lint-duplicate-label = a le même nom qu'un autre nœud
+lint-unexplained-rows = {$count} lignes n'ont pas d'explication : {$rows}
+
+# Éditeur de texte
+text-editor-help = Modifiez le modèle sous forme de texte, le graphe est mis à jour dès que le texte est valide. Consultez l'aide pour la syntaxe.
+text-editor-problems = Le modèle a été mis à jour, mais des problèmes ont été trouvés :
+
+# Apprentissage à partir de données
+data-help = Chargez un fichier CSV de cas : chaque colonne est associée au nœud du même nom, et chaque cellule à l'une de ses valeurs. Les cellules vides, « ? » et « NA » sont des valeurs manquantes.
+choose-dataset = Choisir un fichier CSV...
+dataset-empty = Le fichier est vide.
+dataset-summary = Les données ont {$rows} lignes et {$columns} colonnes.
+dataset-unknown-columns = Ces colonnes ne correspondent à aucun nœud et sont ignorées : {$columns}
+dataset-unknown-cells = {$count} cellules ne sont pas une valeur de leur nœud, et sont lues comme manquantes :
+dataset-skipped-nodes = Ces nœuds, ou l'un de leurs parents, n'ont pas de colonne, leur table ne peut donc être apprise que par espérance-maximisation :
+learn-parameters = Apprendre les tables
+learn-parameters-help = Les tables des nœuds sont remplacées par les fréquences de leurs valeurs pour chaque combinaison de valeurs de leurs parents. Les lignes où le nœud ou l'un de ses parents est manquant sont ignorées pour sa table. L'estimation bayésienne ajoute un pseudo-compte à chaque cellule, afin que les combinaisons absentes des données ne soient pas impossibles.
+maximum-likelihood = Maximum de vraisemblance
+bayesian-estimation = Estimation bayésienne
+pseudo-count = Pseudo-compte :
+parameters-learned = Les tables de {$count} nœuds ont été apprises.
+learned-from = appris à partir de {$count} lignes
+nodes-to-learn = Nœuds à apprendre, décochez ceux dont la table a été donnée par des experts pour la conserver :
+em = Espérance-maximisation
+em-help = L'algorithme espérance-maximisation apprend aussi des lignes avec des cellules manquantes, et les tables des nœuds qui n'ont aucune colonne. Il alterne le calcul des probabilités des valeurs manquantes avec les tables actuelles, et l'apprentissage des tables à partir de celles-ci, jusqu'à ce que la vraisemblance des données cesse d'augmenter.
+em-no-values = Tous les nœuds doivent avoir des valeurs pour lancer l'espérance-maximisation.
+run-em = Lancer l'espérance-maximisation
+run-em-again = Relancer
+em-likelihood = La log-vraisemblance des données est passée de {$first} à {$last}.
+em-converged = A convergé après {$iterations} itérations.
+em-not-converged = N'a pas convergé après {$iterations} itérations, vous pouvez le relancer pour continuer.
+em-impossible-rows = {$count} lignes sont impossibles selon le modèle et ont été ignorées.
+learn-structure = Apprendre une structure
+learn-structure-help = Remplace le modèle par un nouveau modèle avec un nœud par colonne, dont les arcs sont choisis pour correspondre aux données, puis apprend ses tables. La recherche ajoute, retire ou inverse un arc à la fois tant que cela améliore le score, chaque nœud ayant au plus {$max} parents. La recherche tabou continue au-delà de la première structure qu'aucun mouvement n'améliore, et garde la meilleure trouvée.
+score-bic = Score BIC
+score-bdeu = Score BDeu
+equivalent-sample-size = Taille d'échantillon équivalente :
+hill-climbing = Montée de colline
+tabu-search = Recherche tabou
+edge-constraints = Arcs que la structure doit avoir, ou ne doit pas avoir :
+require-parent = imposer un parent
+forbid-parent = interdire un parent
+required-edges = Arcs imposés :
+forbidden-edges = Arcs interdits :
+structure-cycle = L'arc imposé de {$parent} vers {$child} forme un cycle avec les autres arcs imposés.
+structure-learned = La structure a été apprise en {$moves} mouvements, avec un score de {$score}.
+sample-cases = Échantillonner des cas
+sample-cases-help = Tire des cas au hasard selon le modèle et les télécharge dans un fichier CSV, pour s'exercer à l'apprentissage ou pour le tester. L'échantillonnage direct ignore les observations, l'échantillonnage par rejet ne garde que les cas qui s'accordent avec elles, et la pondération par la vraisemblance fixe les nœuds observés et donne à chaque cas un poids dans la dernière colonne.
+sample-no-values = Tous les nœuds doivent avoir des valeurs pour échantillonner des cas.
+sample-count = Nombre de cas :
+forward-sampling = Échantillonnage direct
+rejection-sampling = Échantillonnage par rejet
+likelihood-weighting = Pondération par la vraisemblance
+download-cases = Télécharger les cas
+sample-weight = poids
+sample-rejected = {$kept} cas ont été gardés sur {$drawn} tirés.
+sample-gave-up = Les observations sont trop improbables : seuls {$kept} cas ont été gardés sur {$drawn} tirés.
+batch-inference = Inférence sur chaque ligne
+batch-inference-help = Calcule les probabilités des nœuds cibles pour chaque ligne du jeu de données, étant données les observations des autres cellules de la ligne, et les télécharge dans un fichier CSV à la suite des colonnes du jeu de données.
+batch-no-values = Tous les nœuds doivent avoir des valeurs pour calculer des probabilités.
+target-nodes = Nœuds cibles :
+download-posteriors = Télécharger les probabilités
+posteriors-computed = Les probabilités ont été calculées, et téléchargées.
+posteriors-impossible-rows = {$count} lignes sont impossibles selon le modèle, leurs probabilités ont été laissées vides.
+evaluate-classifier = Évaluer les prédictions
+evaluate-classifier-help = Prédit un nœud cible dans chaque ligne du jeu de données à partir des autres cellules de la ligne, avec la même inférence que la page des croyances, et compare les prédictions à la valeur de la cible dans la ligne.
+choose-target = choisir la cible
+evaluate = Évaluer
+evaluation-cases = {$count} lignes ont été évaluées.
+evaluation-unlabelled = {$count} lignes n'ont pas de valeur pour la cible et ont été ignorées.
+evaluation-impossible = {$count} lignes sont impossibles selon le modèle et ont été ignorées.
+accuracy = Exactitude : {$value} %
+log-loss = Perte logarithmique : {$value}
+brier-score = Score de Brier : {$value}
+actual-predicted = Réel \ prédit
+reliability-diagram = Diagramme de fiabilité : la probabilité prédite moyenne comparée à la fréquence à laquelle la valeur est réellement apparue, pour des groupes de prédictions semblables. Plus les points sont proches de la diagonale, mieux le modèle est calibré.
+build-classifier = Construire un classifieur
+build-classifier-help = Remplace le modèle par un classifieur prédisant la colonne choisie à partir des autres, puis apprend ses tables. Le Bayes naïf relie la classe à tous les autres nœuds, qui sont alors indépendants étant donnée la classe. Le Bayes naïf augmenté d'un arbre relie aussi les autres nœuds par l'arbre qui correspond le mieux aux données, chacun ayant au plus un parent en plus de la classe.
+choose-class = choisir la classe
+naive-bayes = Bayes naïf
+tree-augmented-naive-bayes = Bayes naïf augmenté d'un arbre
@@ -0,0 +1,140 @@
+use crate::dataset::{Cases, Dataset};
+use crate::elimination;
+use crate::formats::csv::write_record;
+use crate::graph::Dag;
+
+/*
+ * Inference over many cases at once
+ *
+ * Each row of a dataset gives the observations of a case. The posterior of each
+ * target node is computed given the other observations of the row, leaving out
+ * the cell of the target itself, so that a dataset whose targets are known can
+ * also be used to check the predictions of the model.
+ */
+
+/// The posterior of each target in each row, given the other cells of the row.
+/// It is `None` when the observations of the row are impossible according to
+/// the model. All the nodes must have values.
+pub fn posteriors(dag: &Dag, cases: &Cases, targets: &[usize]) -> Vec<Vec<Option<Vec<f64>>>> {
+    let tables = elimination::tables(dag);
+    cases
+        .rows
+        .iter()
+        .map(|row| {
+            targets
+                .iter()
+                .map(|&target| {
+                    let mut row = row.clone();
+                    row[target] = None;
+                    let joint = elimination::joint(dag, &tables, &row, &[target]);
+                    let total = joint.total();
+                    if total > 0.0 {
+                        Some(joint.values.iter().map(|&p| p / total).collect())
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The rows of the dataset followed by the posteriors of the targets, the cells
+/// of the impossible rows being left empty
+pub fn write_posteriors(
+    dag: &Dag,
+    dataset: &Dataset,
+    targets: &[usize],
+    posteriors: &[Vec<Option<Vec<f64>>>],
+) -> String {
+    let mut header = dataset.header.clone();
+    for &target in targets {
+        let node = dag.get(target).unwrap();
+        header.extend(
+            node.values
+                .iter()
+                .map(|value| format!("P({} = {})", node.label, value)),
+        );
+    }
+    let mut out = write_record(&header, ',');
+    for ((_, record), posteriors) in dataset.rows.iter().zip(posteriors) {
+        let mut record = record.clone();
+        record.resize(dataset.header.len(), String::new());
+        for (&target, posterior) in targets.iter().zip(posteriors) {
+            let nvalues = dag.get(target).unwrap().values.len();
+            match posterior {
+                // the tables are in single precision, so are the posteriors
+                Some(posterior) => record.extend(posterior.iter().map(|&p| (p as f32).to_string())),
+                None => record.extend(vec![String::new(); nvalues]),
+            }
+        }
+        out.push_str(&write_record(&record, ','));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    // P(b = t) = 0.3 * 0.9 + 0.7 * 0.2 = 0.41
+    const MODEL: &str = "node a {\n    values t, f\n}\n\
+                         node b {\n    values t, f\n}\n\
+                         a -> b\n\
+                         table a { 0.3, 0.7 }\n\
+                         table b {\n    (t): 0.9, 0.1\n    (f): 0.2, 0.8\n}\n";
+
+    fn close(posterior: &Option<Vec<f64>>, expected: &[f64]) -> bool {
+        let posterior = posterior.as_ref().unwrap();
+        posterior.len() == expected.len()
+            && posterior
+                .iter()
+                .zip(expected)
+                .all(|(p, e)| (p - e).abs() < 1e-6)
+    }
+
+    #[test]
+    fn targets_are_left_out_of_their_own_row() {
+        let (dag, _) = text::read(MODEL).unwrap();
+        let dataset = Dataset::parse("a,b\nt,t\n?,f\nf,?\n").unwrap();
+        let cases = dataset.match_nodes(&dag);
+        let posteriors = posteriors(&dag, &cases, &[0, 1]);
+        assert_eq!(posteriors.len(), 3);
+        // the known value of the target is not used
+        assert!(close(&posteriors[0][0], &[0.27 / 0.41, 0.14 / 0.41]));
+        assert!(close(&posteriors[0][1], &[0.9, 0.1]));
+        assert!(close(&posteriors[1][0], &[0.03 / 0.59, 0.56 / 0.59]));
+        assert!(close(&posteriors[1][1], &[0.41, 0.59]));
+        assert!(close(&posteriors[2][1], &[0.2, 0.8]));
+
+        // the same as the joint with the cell of the target left out
+        let tables = elimination::tables(&dag);
+        for (row, posteriors) in cases.rows.iter().zip(&posteriors) {
+            for (target, posterior) in posteriors.iter().enumerate() {
+                let mut row = row.clone();
+                row[target] = None;
+                let factor = elimination::joint(&dag, &tables, &row, &[target]);
+                let expected: Vec<f64> =
+                    factor.values.iter().map(|&p| p / factor.total()).collect();
+                assert!(close(posterior, &expected));
+            }
+        }
+    }
+
+    #[test]
+    fn impossible_rows() {
+        let (mut dag, _) = text::read(MODEL).unwrap();
+        dag.set_credencies(0, ndarray::arr1(&[1.0f32, 0.0]).into_dyn())
+            .unwrap();
+        let dataset = Dataset::parse("a,b\nf,t\n").unwrap();
+        let posteriors = posteriors(&dag, &dataset.match_nodes(&dag), &[0, 1]);
+        // a = f cannot happen, but is left out when a is the target
+        assert!(close(&posteriors[0][0], &[1.0, 0.0]));
+        assert_eq!(posteriors[0][1], None);
+        assert_eq!(
+            write_posteriors(&dag, &dataset, &[0, 1], &posteriors),
+            "a,b,P(a = t),P(a = f),P(b = t),P(b = f)\nf,t,1,0,,\n"
+        );
+    }
+}
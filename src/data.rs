@@ -0,0 +1,663 @@
+use web_sys::{Event, HtmlInputElement, HtmlSelectElement};
+use yew::{
+    html,
+    html::{Scope, TargetCast},
+    Html,
+};
+
+use crate::{
+    dataset::Dataset,
+    evaluation::Evaluation,
+    graph::Dag,
+    lang,
+    learning::{learnable_nodes, Estimator},
+    model::{BayesOMatic, Msg},
+    sampling::{Sampling, MAX_CASES},
+    structure::{Classifier, Score, Search, MAX_PARENTS},
+    Page,
+};
+
+/// Problems in the cells of a dataset are only listed up to this count
+const MAX_LISTED_CELLS: usize = 10;
+
+/// The size of the drawing of the likelihood curve, margins included
+const CURVE_WIDTH: f64 = 400.0;
+const CURVE_HEIGHT: f64 = 150.0;
+const CURVE_MARGIN: f64 = 5.0;
+/// The size of the reliability diagram, which is square
+const DIAGRAM_SIZE: f64 = 200.0;
+
+impl BayesOMatic {
+    fn make_dataset_input(&self, link: &Scope<Self>) -> Html {
+        html! {
+            <div class="file block">
+            <label class="file-label">
+            <input type="file" class="file-input" accept=".csv,.tsv,.txt" onchange={ link.callback_future(|evt: Event| async move {
+                let fileinput = evt.target_dyn_into::<HtmlInputElement>().unwrap();
+                let file = fileinput.files()?.get(0)?;
+                let text = wasm_bindgen_futures::JsFuture::from(file.text()).await.ok()?.as_string()?;
+                fileinput.set_value("");
+                Some(Msg::LoadDataset(text))
+            })} />
+            <span class="file-cta">
+                <span class="file-icon">
+                    <i class="fas fa-upload"></i>
+                </span>
+                <span class="file-label">{ lang!(self.lang, "choose-dataset") }</span>
+            </span>
+            </label>
+            </div>
+        }
+    }
+
+    fn make_estimator_select(&self, link: &Scope<Self>) -> Html {
+        let pseudo_count = match self.estimator {
+            Estimator::Bayesian(pseudo_count) => pseudo_count,
+            Estimator::MaximumLikelihood => 1.0,
+        };
+        html! {
+            <div class="field is-grouped">
+                <div class="control select">
+                    <select onchange={ link.callback(move |e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                        Some(select) if select.value() == "ml" => Msg::SetEstimator(Estimator::MaximumLikelihood),
+                        Some(_) => Msg::SetEstimator(Estimator::Bayesian(pseudo_count)),
+                        None => Msg::Ignore,
+                    })}>
+                        <option value="ml" selected={ self.estimator == Estimator::MaximumLikelihood }>
+                            { lang!(self.lang, "maximum-likelihood") }
+                        </option>
+                        <option value="bayesian" selected={ self.estimator != Estimator::MaximumLikelihood }>
+                            { lang!(self.lang, "bayesian-estimation") }
+                        </option>
+                    </select>
+                </div>
+                { if let Estimator::Bayesian(pseudo_count) = self.estimator { html! {
+                    <div class="control">
+                        <label class="label">{ lang!(self.lang, "pseudo-count") }</label>
+                        <input class="input" size=4 value={ pseudo_count.to_string() }
+                               onchange={ link.callback(|e: Event| {
+                                   let input = e.target_dyn_into::<HtmlInputElement>().unwrap();
+                                   match crate::formats::csv::parse_number(&input.value()) {
+                                       Some(pseudo_count) if pseudo_count >= 0.0 => {
+                                           Msg::SetEstimator(Estimator::Bayesian(pseudo_count))
+                                       }
+                                       _ => Msg::Ignore,
+                                   }
+                               })} />
+                    </div>
+                }} else { html! {} }}
+            </div>
+        }
+    }
+
+    fn make_frozen_nodes(&self, link: &Scope<Self>) -> Html {
+        html! {
+            <div class="block">
+                <p>{ lang!(self.lang, "nodes-to-learn") }</p>
+                <ul class="blocky">
+                { for self.dag.iter_nodes().map(|(nodeid, node)| html! {
+                    <li>
+                        <label class="checkbox">
+                            <input type="checkbox" checked={ !self.frozen.contains(&nodeid) }
+                                   onchange={ link.callback(move |_| Msg::ToggleFrozen(nodeid)) } />
+                            { format!(" {}", node.label) }
+                        </label>
+                    </li>
+                })}
+                </ul>
+            </div>
+        }
+    }
+
+    /// The log-likelihood of the data along the iterations
+    fn make_likelihood_curve(&self, log_likelihoods: &[f64]) -> Html {
+        let min = log_likelihoods
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min);
+        let max = log_likelihoods
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let range = if max > min { max - min } else { 1.0 };
+        let steps = (log_likelihoods.len() - 1).max(1) as f64;
+        let points = log_likelihoods
+            .iter()
+            .enumerate()
+            .map(|(i, &ll)| {
+                let x = CURVE_MARGIN + i as f64 / steps * (CURVE_WIDTH - 2.0 * CURVE_MARGIN);
+                let y = CURVE_HEIGHT
+                    - CURVE_MARGIN
+                    - (ll - min) / range * (CURVE_HEIGHT - 2.0 * CURVE_MARGIN);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        html! {
+            <svg width={ CURVE_WIDTH.to_string() } height={ CURVE_HEIGHT.to_string() }
+                 viewBox={ format!("0 0 {} {}", CURVE_WIDTH, CURVE_HEIGHT) }>
+                <rect x="0" y="0" width={ CURVE_WIDTH.to_string() } height={ CURVE_HEIGHT.to_string() }
+                      fill="none" stroke="lightgray" />
+                <polyline points={ points } fill="none" stroke="steelblue" stroke-width="2" />
+            </svg>
+        }
+    }
+
+    fn make_em_section(&self, link: &Scope<Self>) -> Html {
+        let report = match self.em_report {
+            Some(ref report) => report,
+            None => return html! {},
+        };
+        let (first, last) = match (
+            report.log_likelihoods.first(),
+            report.log_likelihoods.last(),
+        ) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return html! {},
+        };
+        let iterations = report.log_likelihoods.len();
+        html! {
+            <div class="block">
+                { self.make_likelihood_curve(&report.log_likelihoods) }
+                <p>{ lang!(self.lang, "em-likelihood", first = format!("{:.2}", first), last = format!("{:.2}", last)) }</p>
+                <p>{ if report.converged {
+                    lang!(self.lang, "em-converged", iterations = iterations)
+                } else {
+                    lang!(self.lang, "em-not-converged", iterations = iterations)
+                }}</p>
+                { if report.impossible_rows > 0 { html! {
+                    <p class="error">{ lang!(self.lang, "em-impossible-rows", count = report.impossible_rows) }</p>
+                }} else { html! {} }}
+                <a href="#" class="button" onclick={ link.callback(|_| Msg::RunEm) }>{ lang!(self.lang, "run-em-again") }</a>
+            </div>
+        }
+    }
+
+    fn make_batch_section(&self, link: &Scope<Self>) -> Html {
+        let has_targets = self
+            .dag
+            .iter_nodes()
+            .any(|(i, _)| self.targets.contains(&i));
+        html! {
+            <div class="block">
+                <h3>{ lang!(self.lang, "batch-inference") }</h3>
+                <p>{ lang!(self.lang, "batch-inference-help") }</p>
+                { if self.dag.iter_nodes().any(|(_, node)| node.values.is_empty()) { html! {
+                    <p class="error">{ lang!(self.lang, "batch-no-values") }</p>
+                }} else { html! {
+                    <div>
+                    <p>{ lang!(self.lang, "target-nodes") }</p>
+                    <ul class="blocky">
+                    { for self.dag.iter_nodes().map(|(nodeid, node)| html! {
+                        <li>
+                            <label class="checkbox">
+                                <input type="checkbox" checked={ self.targets.contains(&nodeid) }
+                                       onchange={ link.callback(move |_| Msg::ToggleTarget(nodeid)) } />
+                                { format!(" {}", node.label) }
+                            </label>
+                        </li>
+                    })}
+                    </ul>
+                    { match self.batch_report {
+                        Some(0) => html! {
+                            <p>{ lang!(self.lang, "posteriors-computed") }</p>
+                        },
+                        Some(count) => html! {
+                            <p class="error">{ lang!(self.lang, "posteriors-impossible-rows", count = count) }</p>
+                        },
+                        None => html! {},
+                    }}
+                    { if has_targets { html! {
+                        <a href="#" class="button" onclick={ link.callback(|_| Msg::DownloadPosteriors) }>{ lang!(self.lang, "download-posteriors") }</a>
+                    }} else { html! {} }}
+                    </div>
+                }}}
+            </div>
+        }
+    }
+
+    /// The predicted probabilities against how often the values came, the
+    /// diagonal being a perfectly calibrated model
+    fn make_reliability_diagram(&self, evaluation: &Evaluation) -> Html {
+        let inner = DIAGRAM_SIZE - 2.0 * CURVE_MARGIN;
+        let point = |p: f64, q: f64| {
+            (
+                CURVE_MARGIN + p * inner,
+                DIAGRAM_SIZE - CURVE_MARGIN - q * inner,
+            )
+        };
+        let bins: Vec<(f64, f64)> = evaluation
+            .calibration
+            .iter()
+            .filter(|bin| bin.count > 0)
+            .map(|bin| point(bin.predicted, bin.observed))
+            .collect();
+        let points = bins
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (x0, y0) = point(0.0, 0.0);
+        let (x1, y1) = point(1.0, 1.0);
+        html! {
+            <svg width={ DIAGRAM_SIZE.to_string() } height={ DIAGRAM_SIZE.to_string() }
+                 viewBox={ format!("0 0 {} {}", DIAGRAM_SIZE, DIAGRAM_SIZE) }>
+                <rect x="0" y="0" width={ DIAGRAM_SIZE.to_string() } height={ DIAGRAM_SIZE.to_string() }
+                      fill="none" stroke="lightgray" />
+                <line x1={ x0.to_string() } y1={ y0.to_string() } x2={ x1.to_string() } y2={ y1.to_string() }
+                      stroke="lightgray" stroke-dasharray="4" />
+                <polyline points={ points } fill="none" stroke="steelblue" stroke-width="2" />
+                { for bins.iter().map(|(x, y)| html! {
+                    <circle cx={ format!("{:.1}", x) } cy={ format!("{:.1}", y) } r="3" fill="steelblue" />
+                })}
+            </svg>
+        }
+    }
+
+    fn make_confusion_matrix(&self, target: usize, evaluation: &Evaluation) -> Html {
+        let values = &self.dag.get(target).unwrap().values;
+        html! {
+            <table class="table is-narrow">
+                <thead>
+                    <tr>
+                        <th>{ lang!(self.lang, "actual-predicted") }</th>
+                        { for values.iter().map(|value| html! { <th>{ value }</th> }) }
+                    </tr>
+                </thead>
+                <tbody>
+                { for values.iter().zip(&evaluation.confusion).map(|(value, row)| html! {
+                    <tr>
+                        <th>{ value }</th>
+                        { for row.iter().map(|count| html! { <td>{ count.to_string() }</td> }) }
+                    </tr>
+                })}
+                </tbody>
+            </table>
+        }
+    }
+
+    fn make_evaluation_section(&self, link: &Scope<Self>) -> Html {
+        let target = self
+            .evaluation_target
+            .filter(|&target| self.dag.get(target).is_some());
+        let results = match (target, &self.evaluation) {
+            (Some(target), Some(evaluation)) => html! {
+                <div>
+                    <p>{ lang!(self.lang, "evaluation-cases", count = evaluation.cases) }</p>
+                    { if evaluation.unlabelled > 0 { html! {
+                        <p>{ lang!(self.lang, "evaluation-unlabelled", count = evaluation.unlabelled) }</p>
+                    }} else { html! {} }}
+                    { if evaluation.impossible > 0 { html! {
+                        <p class="error">{ lang!(self.lang, "evaluation-impossible", count = evaluation.impossible) }</p>
+                    }} else { html! {} }}
+                    { if evaluation.cases > 0 { html! {
+                        <div>
+                        <ul>
+                            <li>{ lang!(self.lang, "accuracy", value = format!("{:.1}", 100.0 * evaluation.accuracy)) }</li>
+                            <li>{ lang!(self.lang, "log-loss", value = format!("{:.4}", evaluation.log_loss)) }</li>
+                            <li>{ lang!(self.lang, "brier-score", value = format!("{:.4}", evaluation.brier)) }</li>
+                        </ul>
+                        { self.make_confusion_matrix(target, evaluation) }
+                        <p>{ lang!(self.lang, "reliability-diagram") }</p>
+                        { self.make_reliability_diagram(evaluation) }
+                        </div>
+                    }} else { html! {} }}
+                </div>
+            },
+            _ => html! {},
+        };
+        html! {
+            <div class="block">
+                <h3>{ lang!(self.lang, "evaluate-classifier") }</h3>
+                <p>{ lang!(self.lang, "evaluate-classifier-help") }</p>
+                { if self.dag.iter_nodes().any(|(_, node)| node.values.is_empty()) { html! {
+                    <p class="error">{ lang!(self.lang, "batch-no-values") }</p>
+                }} else { html! {
+                    <div class="field is-grouped">
+                        <div class="control select">
+                            <select onchange={ link.callback(|e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                                Some(select) => Msg::SetEvaluationTarget(select.value().parse().ok()),
+                                None => Msg::Ignore,
+                            })}>
+                                <option value="" selected={ target.is_none() }>{ format!("({})", lang!(self.lang, "choose-target")) }</option>
+                                { for self.dag.iter_nodes().map(|(nodeid, node)| html! {
+                                    <option value={ nodeid.to_string() } selected={ target == Some(nodeid) }>{ &node.label }</option>
+                                })}
+                            </select>
+                        </div>
+                        { if target.is_some() { html! {
+                            <div class="control">
+                                <a href="#" class="button" onclick={ link.callback(|_| Msg::Evaluate) }>{ lang!(self.lang, "evaluate") }</a>
+                            </div>
+                        }} else { html! {} }}
+                    </div>
+                }}}
+                { results }
+            </div>
+        }
+    }
+
+    fn make_score_select(&self, link: &Scope<Self>) -> Html {
+        let ess = match self.score {
+            Score::Bdeu(ess) => ess,
+            Score::Bic => 1.0,
+        };
+        html! {
+            <div class="field is-grouped">
+                <div class="control select">
+                    <select onchange={ link.callback(move |e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                        Some(select) if select.value() == "bic" => Msg::SetScore(Score::Bic),
+                        Some(_) => Msg::SetScore(Score::Bdeu(ess)),
+                        None => Msg::Ignore,
+                    })}>
+                        <option value="bic" selected={ self.score == Score::Bic }>{ lang!(self.lang, "score-bic") }</option>
+                        <option value="bdeu" selected={ self.score != Score::Bic }>{ lang!(self.lang, "score-bdeu") }</option>
+                    </select>
+                </div>
+                { if let Score::Bdeu(ess) = self.score { html! {
+                    <div class="control">
+                        <label class="label">{ lang!(self.lang, "equivalent-sample-size") }</label>
+                        <input class="input" size=4 value={ ess.to_string() }
+                               onchange={ link.callback(|e: Event| {
+                                   let input = e.target_dyn_into::<HtmlInputElement>().unwrap();
+                                   match crate::formats::csv::parse_number(&input.value()) {
+                                       Some(ess) if ess > 0.0 => Msg::SetScore(Score::Bdeu(f64::from(ess))),
+                                       _ => Msg::Ignore,
+                                   }
+                               })} />
+                    </div>
+                }} else { html! {} }}
+                <div class="control select">
+                    <select onchange={ link.callback(|e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                        Some(select) if select.value() == "hill-climbing" => Msg::SetSearch(Search::HillClimbing),
+                        Some(_) => Msg::SetSearch(Search::Tabu),
+                        None => Msg::Ignore,
+                    })}>
+                        <option value="hill-climbing" selected={ self.search == Search::HillClimbing }>
+                            { lang!(self.lang, "hill-climbing") }
+                        </option>
+                        <option value="tabu" selected={ self.search == Search::Tabu }>
+                            { lang!(self.lang, "tabu-search") }
+                        </option>
+                    </select>
+                </div>
+            </div>
+        }
+    }
+
+    /// The edges required or forbidden between the columns of the dataset, which
+    /// are the nodes of `columns`
+    fn make_constraints_edit(&self, columns: &Dag, link: &Scope<Self>) -> Html {
+        let label = |n: usize| &columns.get(n).unwrap().label;
+        let constraint = |&(parent, child): &(usize, usize)| {
+            html! {
+                <li>
+                    { format!("{} → {}", label(parent), label(child)) }
+                    <a href="#" class="delete-button"
+                       onclick={ link.callback(move |_| Msg::RemoveConstraint { parent, child }) }>{ "×" }</a>
+                </li>
+            }
+        };
+        let selector = |child: usize, required: bool| {
+            let prompt = if required {
+                lang!(self.lang, "require-parent")
+            } else {
+                lang!(self.lang, "forbid-parent")
+            };
+            html! {
+                <select onchange={ link.callback(move |e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                    Some(select) => match select.value().parse() {
+                        Ok(parent) => Msg::AddConstraint { parent, child, required },
+                        Err(_) => Msg::Ignore,
+                    },
+                    None => Msg::Ignore,
+                })}>
+                    <option selected=true value="">{ format!("({})", prompt) }</option>
+                    { for columns.iter_nodes().filter(|&(i, _)| i != child).map(|(i, node)| html! {
+                        <option value={ i.to_string() } selected=false>{ &node.label }</option>
+                    })}
+                </select>
+            }
+        };
+        html! {
+            <div class="block">
+                <p>{ lang!(self.lang, "edge-constraints") }</p>
+                <ul class="blocky">
+                { for columns.iter_nodes().map(|(i, node)| html! {
+                    <li>
+                        { format!("{} ", node.label) }
+                        { selector(i, true) }
+                        { selector(i, false) }
+                    </li>
+                })}
+                </ul>
+                { if self.constraints.required.is_empty() { html! {} } else { html! {
+                    <div>
+                    <p>{ lang!(self.lang, "required-edges") }</p>
+                    <ul>{ for self.constraints.required.iter().map(constraint) }</ul>
+                    </div>
+                }}}
+                { if self.constraints.forbidden.is_empty() { html! {} } else { html! {
+                    <div>
+                    <p>{ lang!(self.lang, "forbidden-edges") }</p>
+                    <ul>{ for self.constraints.forbidden.iter().map(constraint) }</ul>
+                    </div>
+                }}}
+            </div>
+        }
+    }
+
+    fn make_structure_section(&self, dataset: &Dataset, link: &Scope<Self>) -> Html {
+        let (columns, _) = dataset.to_dag();
+        let result = if let Some((parent, child)) = self.structure_error {
+            html! {
+                <p class="error">{ lang!(self.lang, "structure-cycle",
+                    parent = &columns.get(parent).unwrap().label[..],
+                    child = &columns.get(child).unwrap().label[..]) }</p>
+            }
+        } else if let Some(ref report) = self.structure_report {
+            html! {
+                <p>{ lang!(self.lang, "structure-learned",
+                    moves = report.moves,
+                    score = format!("{:.2}", report.score)) }</p>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <div class="block">
+                <h3>{ lang!(self.lang, "learn-structure") }</h3>
+                <p>{ lang!(self.lang, "learn-structure-help", max = MAX_PARENTS) }</p>
+                { self.make_score_select(link) }
+                { self.make_constraints_edit(&columns, link) }
+                { result }
+                <a href="#" class="button" onclick={ link.callback(|_| Msg::LearnStructure) }>{ lang!(self.lang, "learn-structure") }</a>
+            </div>
+        }
+    }
+
+    fn make_sampling_section(&self, link: &Scope<Self>) -> Html {
+        let report = match self.sample_report {
+            Some((kept, drawn)) if kept < self.sample_count => html! {
+                <p class="error">{ lang!(self.lang, "sample-gave-up", kept = kept, drawn = drawn) }</p>
+            },
+            Some((kept, drawn)) if drawn > kept => html! {
+                <p>{ lang!(self.lang, "sample-rejected", kept = kept, drawn = drawn) }</p>
+            },
+            _ => html! {},
+        };
+        html! {
+            <div class="block">
+                <h3>{ lang!(self.lang, "sample-cases") }</h3>
+                <p>{ lang!(self.lang, "sample-cases-help") }</p>
+                { if self.dag.iter_nodes().any(|(_, node)| node.values.is_empty()) { html! {
+                    <p class="error">{ lang!(self.lang, "sample-no-values") }</p>
+                }} else { html! {
+                    <div class="field is-grouped">
+                        <div class="control">
+                            <label class="label">{ lang!(self.lang, "sample-count") }</label>
+                            <input class="input" size=6 value={ self.sample_count.to_string() }
+                                   onchange={ link.callback(|e: Event| {
+                                       let input = e.target_dyn_into::<HtmlInputElement>().unwrap();
+                                       match input.value().trim().parse() {
+                                           Ok(count) if count > 0 && count <= MAX_CASES => Msg::SetSampleCount(count),
+                                           _ => Msg::Ignore,
+                                       }
+                                   })} />
+                        </div>
+                        <div class="control select">
+                            <select onchange={ link.callback(|e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                                Some(select) => match &select.value()[..] {
+                                    "rejection" => Msg::SetSampling(Sampling::Rejection),
+                                    "likelihood-weighting" => Msg::SetSampling(Sampling::LikelihoodWeighting),
+                                    _ => Msg::SetSampling(Sampling::Forward),
+                                },
+                                None => Msg::Ignore,
+                            })}>
+                                <option value="forward" selected={ self.sampling == Sampling::Forward }>
+                                    { lang!(self.lang, "forward-sampling") }
+                                </option>
+                                <option value="rejection" selected={ self.sampling == Sampling::Rejection }>
+                                    { lang!(self.lang, "rejection-sampling") }
+                                </option>
+                                <option value="likelihood-weighting" selected={ self.sampling == Sampling::LikelihoodWeighting }>
+                                    { lang!(self.lang, "likelihood-weighting") }
+                                </option>
+                            </select>
+                        </div>
+                        <div class="control">
+                            <a href="#" class="button" onclick={ link.callback(|_| Msg::SampleCases) }>{ lang!(self.lang, "download-cases") }</a>
+                        </div>
+                    </div>
+                }}}
+                { report }
+            </div>
+        }
+    }
+
+    fn make_classifier_section(&self, dataset: &Dataset, link: &Scope<Self>) -> Html {
+        let (columns, _) = dataset.to_dag();
+        let class = self
+            .class_node
+            .filter(|&class| columns.get(class).is_some());
+        html! {
+            <div class="block">
+                <h3>{ lang!(self.lang, "build-classifier") }</h3>
+                <p>{ lang!(self.lang, "build-classifier-help") }</p>
+                <div class="field is-grouped">
+                    <div class="control select">
+                        <select onchange={ link.callback(|e: Event| match e.target_dyn_into::<HtmlSelectElement>() {
+                            Some(select) => Msg::SetClassNode(select.value().parse().ok()),
+                            None => Msg::Ignore,
+                        })}>
+                            <option value="" selected={ class.is_none() }>{ format!("({})", lang!(self.lang, "choose-class")) }</option>
+                            { for columns.iter_nodes().map(|(nodeid, node)| html! {
+                                <option value={ nodeid.to_string() } selected={ class == Some(nodeid) }>{ &node.label }</option>
+                            })}
+                        </select>
+                    </div>
+                    { if class.is_some() { html! {
+                        <>
+                        <div class="control">
+                            <a href="#" class="button" onclick={ link.callback(|_| Msg::BuildClassifier(Classifier::NaiveBayes)) }>
+                                { lang!(self.lang, "naive-bayes") }
+                            </a>
+                        </div>
+                        <div class="control">
+                            <a href="#" class="button" onclick={ link.callback(|_| Msg::BuildClassifier(Classifier::TreeAugmented)) }>
+                                { lang!(self.lang, "tree-augmented-naive-bayes") }
+                            </a>
+                        </div>
+                        </>
+                    }} else { html! {} }}
+                </div>
+            </div>
+        }
+    }
+
+    pub fn make_data_tab(&self, link: &Scope<Self>) -> Html {
+        let dataset = match self.dataset {
+            Some(ref dataset) => dataset,
+            None => {
+                return html! {
+                    <div id="node-editor" class="box content">
+                        <p>{ lang!(self.lang, "data-help") }</p>
+                        { if self.dataset_empty { html! {
+                            <p class="error">{ lang!(self.lang, "dataset-empty") }</p>
+                        }} else { html! {} }}
+                        { self.make_dataset_input(link) }
+                        { self.make_sampling_section(link) }
+                    </div>
+                };
+            }
+        };
+        let cases = dataset.match_nodes(&self.dag);
+        let learnable = learnable_nodes(&self.dag, &cases);
+        let skipped: Vec<_> = self
+            .dag
+            .iter_nodes()
+            .filter(|(i, _)| !learnable.contains(i))
+            .collect();
+        let skipped_list = if skipped.is_empty() {
+            html! {}
+        } else {
+            html! {
+                <div>
+                <p>{ lang!(self.lang, "dataset-skipped-nodes") }</p>
+                <ul>
+                { for skipped.iter().map(|&(nodeid, node)| html! {
+                    <li>
+                        <a href="#" onclick={ link.callback(move |_| Msg::MoveToPage(Page::NodeEdit(nodeid))) }>
+                            { &node.label }
+                        </a>
+                    </li>
+                })}
+                </ul>
+                </div>
+            }
+        };
+        html! {
+            <div id="node-editor" class="box content">
+                <p>{ lang!(self.lang, "data-help") }</p>
+                { self.make_dataset_input(link) }
+                <p>{ lang!(self.lang, "dataset-summary", rows = dataset.rows.len(), columns = dataset.header.len()) }</p>
+                { if cases.unknown_columns.is_empty() { html! {} } else { html! {
+                    <p>{ lang!(self.lang, "dataset-unknown-columns", columns = cases.unknown_columns.join(", ")) }</p>
+                }}}
+                { if cases.unknown_cells.is_empty() { html! {} } else { html! {
+                    <div>
+                    <p>{ lang!(self.lang, "dataset-unknown-cells", count = cases.unknown_cells.len()) }</p>
+                    <ul>
+                    { for cases.unknown_cells.iter().take(MAX_LISTED_CELLS).map(|error| html! {
+                        <li>{ self.describe_cell_error(error) }</li>
+                    })}
+                    </ul>
+                    </div>
+                }}}
+                { self.make_frozen_nodes(link) }
+                { self.make_estimator_select(link) }
+                { if let Some(count) = self.learned { html! {
+                    <p>{ lang!(self.lang, "parameters-learned", count = count) }</p>
+                }} else { html! {} }}
+                <h3>{ lang!(self.lang, "learn-parameters") }</h3>
+                <p>{ lang!(self.lang, "learn-parameters-help") }</p>
+                { skipped_list }
+                <a href="#" class="button" onclick={ link.callback(|_| Msg::LearnParameters) }>{ lang!(self.lang, "learn-parameters") }</a>
+                <h3>{ lang!(self.lang, "em") }</h3>
+                <p>{ lang!(self.lang, "em-help") }</p>
+                { if self.dag.iter_nodes().any(|(_, node)| node.values.is_empty()) { html! {
+                    <p class="error">{ lang!(self.lang, "em-no-values") }</p>
+                }} else if self.em_report.is_some() {
+                    self.make_em_section(link)
+                } else { html! {
+                    <a href="#" class="button" onclick={ link.callback(|_| Msg::RunEm) }>{ lang!(self.lang, "run-em") }</a>
+                }}}
+                { self.make_batch_section(link) }
+                { self.make_evaluation_section(link) }
+                { self.make_structure_section(dataset, link) }
+                { self.make_classifier_section(dataset, link) }
+                { self.make_sampling_section(link) }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::formats::csv::{self, CellError, CellProblem};
+use crate::graph::Dag;
+
+/*
+ * Case data loaded from a CSV file
+ *
+ * Each column is matched with the node of the same label, or failing that of the
+ * same identifier, and each cell with one of the values of its node, ignoring
+ * case and surrounding spaces. Empty cells, as well as "?", "NA" and "N/A", are
+ * missing values.
+ */
+
+const MISSING: &[&str] = &["", "?", "na", "n/a"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dataset {
+    pub header: Vec<String>,
+    // the cells of each row, with the line it starts on in the file
+    pub rows: Vec<(usize, Vec<String>)>,
+}
+
+/// A dataset matched with the nodes of a model
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cases {
+    // the column of each node, indexed like the nodes of the model
+    pub columns: Vec<Option<usize>>,
+    // the value of each node in each row, indexed like the nodes of the model,
+    // `None` when missing
+    pub rows: Vec<Vec<Option<usize>>>,
+    // the columns matching no node
+    pub unknown_columns: Vec<String>,
+    // the cells that are not a value of their node, read as missing
+    pub unknown_cells: Vec<CellError>,
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+pub fn is_missing(cell: &str) -> bool {
+    MISSING.contains(&&cell.trim().to_lowercase()[..])
+}
+
+impl Dataset {
+    /// Read a dataset, `None` if the file is empty
+    pub fn parse(text: &str) -> Option<Dataset> {
+        let mut records = csv::parse(text, csv::separator(text)).into_iter();
+        let (_, header) = records.next()?;
+        Some(Dataset {
+            header,
+            rows: records.collect(),
+        })
+    }
+
+    /// A model without edges having a node for each column with values, whose
+    /// values are the ones found in the column in their order of appearance.
+    /// Also gives the node of each column.
+    pub fn to_dag(&self) -> (Dag, Vec<Option<usize>>) {
+        let mut dag = Dag::new();
+        let mut nodes = Vec::with_capacity(self.header.len());
+        for (c, name) in self.header.iter().enumerate() {
+            let mut values: Vec<&str> = Vec::new();
+            for (_, record) in &self.rows {
+                let cell = record.get(c).map(|c| c.trim()).unwrap_or("");
+                if !is_missing(cell) && !values.iter().any(|v| same(v, cell)) {
+                    values.push(cell);
+                }
+            }
+            if values.is_empty() {
+                nodes.push(None);
+                continue;
+            }
+            let node = dag.insert_node();
+            dag.set_label(node, name.trim().into());
+            for value in values {
+                dag.add_value(node, value.into());
+            }
+            nodes.push(Some(node));
+        }
+        (dag, nodes)
+    }
+
+    pub fn match_nodes(&self, dag: &Dag) -> Cases {
+        let ids = dag.node_ids();
+        let mut columns = vec![None; ids.len()];
+        let mut unknown_columns = Vec::new();
+        for (c, name) in self.header.iter().enumerate() {
+            let node = dag
+                .iter_nodes()
+                .find(|&(i, node)| columns[i].is_none() && same(&node.label, name))
+                .or_else(|| {
+                    dag.iter_nodes().find(|&(i, _)| {
+                        columns[i].is_none() && matches!(ids[i], Some(ref id) if same(id, name))
+                    })
+                });
+            match node {
+                Some((i, _)) => columns[i] = Some(c),
+                None => unknown_columns.push(name.clone()),
+            }
+        }
+
+        let mut unknown_cells = Vec::new();
+        let mut rows = Vec::with_capacity(self.rows.len());
+        for (line, record) in &self.rows {
+            let mut row = vec![None; ids.len()];
+            for (i, node) in dag.iter_nodes() {
+                let column = match columns[i] {
+                    Some(column) => column,
+                    None => continue,
+                };
+                let cell = record.get(column).map(|c| &c[..]).unwrap_or("");
+                if is_missing(cell) {
+                    continue;
+                }
+                row[i] = node.values.iter().position(|v| same(v, cell));
+                if row[i].is_none() {
+                    unknown_cells.push(CellError {
+                        line: Some(*line),
+                        column: Some(column + 1),
+                        found: cell.trim().into(),
+                        problem: CellProblem::ValueOf(node.label.clone()),
+                    });
+                }
+            }
+            rows.push(row);
+        }
+
+        Cases {
+            columns,
+            rows,
+            unknown_columns,
+            unknown_cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    #[test]
+    fn missing_cells() {
+        for cell in &["", " ", "?", "NA", "n/a", " N/A "] {
+            assert!(is_missing(cell), "{:?}", cell);
+        }
+        assert!(!is_missing("no"));
+    }
+
+    #[test]
+    fn matching_nodes() {
+        let (dag, _) = text::read(
+            "node rain { label \"Rain\" values Yes, No }\nnode wet { label \"Wet grass\" values y, n }",
+        )
+        .unwrap();
+        // the columns match the label then the identifier, ignoring case and spaces
+        let dataset =
+            Dataset::parse(" RAIN ,Wet,extra\nyes,N/A,1\n no ,?,2\nmaybe,Y,3\n,na,4\n").unwrap();
+        let cases = dataset.match_nodes(&dag);
+        assert_eq!(cases.columns, vec![Some(0), Some(1)]);
+        assert_eq!(cases.unknown_columns, vec!["extra".to_string()]);
+        assert_eq!(
+            cases.rows,
+            vec![
+                vec![Some(0), None],
+                vec![Some(1), None],
+                vec![None, Some(0)],
+                vec![None, None],
+            ]
+        );
+        assert_eq!(
+            cases.unknown_cells,
+            vec![CellError {
+                line: Some(4),
+                column: Some(1),
+                found: "maybe".into(),
+                problem: CellProblem::ValueOf("Rain".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn dag_from_columns() {
+        let dataset = Dataset::parse("a;b;c\nx;;1\ny;?;1\nX;;2\n").unwrap();
+        let (dag, nodes) = dataset.to_dag();
+        assert_eq!(nodes, vec![Some(0), None, Some(1)]);
+        assert_eq!(dag.get(0).unwrap().values, vec!["x", "y"]);
+        assert_eq!(dag.get(1).unwrap().values, vec!["1", "2"]);
+    }
+}
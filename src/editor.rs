@@ -2,18 +2,21 @@ use itertools::Itertools;
 use ndarray::{ArrayD, IxDyn};
 use wasm_bindgen::JsCast;
 use web_sys::{
-    window, Event, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement, InputEvent,
-    KeyboardEvent,
+    window, ClipboardEvent, Event, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement,
+    InputEvent, KeyboardEvent,
 };
 use yew::{
     html,
     html::{Scope, TargetCast},
-    Html,
+    Callback, Html,
 };
 
 use crate::{
+    formats::csv::{self, CellError, CellProblem},
+    graph::{DeserError, Metadata, Node},
     lang,
     model::{BayesOMatic, Msg},
+    rules::{parent_configurations, CptRule, RuleTable},
 };
 
 pub fn fetch_input_and_clear(name: &str) -> String {
@@ -31,11 +34,57 @@ pub fn fetch_input_and_clear(name: &str) -> String {
     value
 }
 
-fn extract_credencies(shape: &[usize], parents: &[usize]) -> (ArrayD<f32>, Vec<String>) {
+/// The name of the input of a credency, the rows of the table following the order
+/// of `parent_configurations`
+fn credency_input_name(shape: &[usize], parents: &[usize], row: usize, value: usize) -> String {
+    if parents.is_empty() {
+        return format!("prior_{}", value);
+    }
+    let configuration = parent_configurations(&shape[1..]).nth(row).unwrap();
+    let label = parents
+        .iter()
+        .zip(configuration)
+        .map(|(p, v)| format!("{}-{}", p, v))
+        .join("_");
+    format!("{}_{}", label, value)
+}
+
+/// The sample size of a row as shown in the table, empty when not uncertain
+fn sample_size_value(node: &Node, row: usize) -> String {
+    node.sample_sizes
+        .get(row)
+        .filter(|&&size| size > 0.0)
+        .map(|size| size.to_string())
+        .unwrap_or_default()
+}
+
+/// The sample size of a row, zero when it is not a positive number
+fn read_sample_size(name: &str) -> f32 {
+    let query = format!("input[name=\"{}_sample_size\"]", name);
+    let input = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .query_selector(&query)
+        .unwrap()
+        .unwrap();
+    let input: HtmlInputElement = input.dyn_into().unwrap();
+    let size = input.value().trim().parse::<f32>().unwrap_or(0.0);
+    if size.is_finite() {
+        size.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// The credencies, descriptions and sample sizes of the rows, as typed in the
+/// table
+fn extract_credencies(shape: &[usize], parents: &[usize]) -> (ArrayD<f32>, Vec<String>, Vec<f32>) {
     let nval = shape[0];
     let count = shape.iter().product();
     let mut credencies = ArrayD::from_shape_vec(IxDyn(shape), vec![0.0; count]).unwrap();
     let mut descriptions = Vec::new();
+    let mut sample_sizes = Vec::new();
     if shape.len() == 1 {
         // node has no parents
         for i in 0..nval {
@@ -63,6 +112,7 @@ fn extract_credencies(shape: &[usize], parents: &[usize]) -> (ArrayD<f32>, Vec<S
             .unwrap();
         let texta: HtmlTextAreaElement = texta.dyn_into().unwrap();
         descriptions.push(texta.value());
+        sample_sizes.push(read_sample_size("prior"));
     } else {
         // node has parents
         let parent_values = parents
@@ -101,10 +151,65 @@ fn extract_credencies(shape: &[usize], parents: &[usize]) -> (ArrayD<f32>, Vec<S
                 .unwrap();
             let texta: HtmlTextAreaElement = texta.dyn_into().unwrap();
             descriptions.push(texta.value());
+            sample_sizes.push(read_sample_size(&label));
         }
     }
 
-    (credencies, descriptions)
+    (credencies, descriptions, sample_sizes)
+}
+
+fn extract_rules(nrules: usize, nparents: usize, nval: usize) -> RuleTable {
+    let document = window().unwrap().document().unwrap();
+    let read_input = |name: String| -> f32 {
+        let query = format!("input[name=\"{}\"]", name);
+        let input: HtmlInputElement = document
+            .query_selector(&query)
+            .unwrap()
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        input.value().parse::<f32>().unwrap_or(0.0)
+    };
+    let read_textarea = |name: String| -> String {
+        let query = format!("textarea[name=\"{}\"]", name);
+        let texta: HtmlTextAreaElement = document
+            .query_selector(&query)
+            .unwrap()
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        texta.value()
+    };
+    let read_select = |name: String| -> Option<usize> {
+        let query = format!("select[name=\"{}\"]", name);
+        let select: HtmlSelectElement = document
+            .query_selector(&query)
+            .unwrap()
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        // the wildcard is stored as "any", which does not parse
+        select.value().parse().ok()
+    };
+
+    let rules = (0..nrules)
+        .map(|r| CptRule {
+            context: (0..nparents)
+                .map(|k| read_select(format!("rule_{}_parent_{}", r, k)))
+                .collect(),
+            credencies: (0..nval)
+                .map(|i| read_input(format!("rule_{}_{}", r, i)))
+                .collect(),
+            description: read_textarea(format!("rule_{}_description", r)),
+        })
+        .collect();
+    RuleTable {
+        rules,
+        default: (0..nval)
+            .map(|i| read_input(format!("rule_default_{}", i)))
+            .collect(),
+        default_description: read_textarea("rule_default_description".into()),
+    }
 }
 
 impl BayesOMatic {
@@ -134,7 +239,16 @@ impl BayesOMatic {
                     { for node.values.iter().enumerate().map(|(i,v)| {
                         html! {
                             <li>
-                                { v } <a href="#" class="delete-button" onclick={ link.callback(move |_| Msg::DelValue { node: nodeid, value_id: i })}>{ "×" }</a>
+                                { self.make_move_buttons(i, node.values.len(), link, move |to| Msg::MoveValue { node: nodeid, value_id: i, to }) }
+                                <input class="input is-small value-name"
+                                       size={ v.chars().count().max(1).to_string() }
+                                       value={ v.clone() }
+                                       onchange={ link.callback(move |evt: Event| Msg::RenameValue {
+                                           node: nodeid,
+                                           value_id: i,
+                                           name: evt.target_dyn_into::<HtmlInputElement>().unwrap().value()
+                                       }) } />
+                                <a href="#" class="delete-button" onclick={ link.callback(move |_| Msg::DelValue { node: nodeid, value_id: i })}>{ "×" }</a>
                             </li>
                         }
                     })}
@@ -152,6 +266,32 @@ impl BayesOMatic {
         }
     }
 
+    /// Arrows to move an item of a list of `len` elements from position `pos`
+    fn make_move_buttons<F>(&self, pos: usize, len: usize, link: &Scope<Self>, msg: F) -> Html
+    where
+        F: Fn(usize) -> Msg + Copy + 'static,
+    {
+        let left = if pos > 0 {
+            html! {
+                <a href="#" class="move-button" title={ lang!(self.lang, "move-left") }
+                   onclick={ link.callback(move |_| msg(pos - 1)) }>{ "←" }</a>
+            }
+        } else {
+            html! {}
+        };
+        let right = if pos + 1 < len {
+            html! {
+                <a href="#" class="move-button" title={ lang!(self.lang, "move-right") }
+                   onclick={ link.callback(move |_| msg(pos + 1)) }>{ "→" }</a>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <>{ left }{ right }</>
+        }
+    }
+
     fn make_parent_seletor(&self, nodeid: usize, link: &Scope<Self>) -> Html {
         html! {
             <select onchange={link.callback(move |e: Event| if let Some(select) = e.target_dyn_into::<HtmlSelectElement>() {
@@ -179,10 +319,22 @@ impl BayesOMatic {
             <div class="field">
                 <label class="label">{ lang!(self.lang, "node-parents") }</label>
                 <ul class="blocky vlist">
-                    { for node.parents.iter().map(|&p| {
+                    { for node.parents.iter().enumerate().map(|(i, &p)| {
                         let parent = self.dag.get(p).unwrap();
                         html! {
-                            <li>{ &parent.label }<a href="#" class="delete-button" onclick={ link.callback(move |_| Msg::DelParent { node: nodeid, parent_id: p })}>{ "×" }</a></li>
+                            <li>
+                                { self.make_move_buttons(i, node.parents.len(), link, move |to| Msg::MoveParent { node: nodeid, parent_pos: i, to }) }
+                                { &parent.label }
+                                { if self.dag.check_edge_reversal(nodeid, p).is_ok() {
+                                    html! {
+                                        <a href="#" class="reverse-button" title={ lang!(self.lang, "reverse-edge") }
+                                           onclick={ link.callback(move |_| Msg::ReverseEdge { node: nodeid, parent_id: p })}>{ "⇄" }</a>
+                                    }
+                                } else {
+                                    html! {}
+                                }}
+                                <a href="#" class="delete-button" onclick={ link.callback(move |_| Msg::DelParent { node: nodeid, parent_id: p })}>{ "×" }</a>
+                            </li>
                         }
                     })}
                 </ul>
@@ -208,11 +360,79 @@ impl BayesOMatic {
         }
     }
 
+    /// Pasting a block of cells from a spreadsheet fills the table from the given
+    /// cell, and saves it
+    fn paste_credencies(
+        &self,
+        nodeid: usize,
+        row: usize,
+        value: usize,
+        link: &Scope<Self>,
+    ) -> Callback<Event> {
+        let shape = self.dag.credencies_shape(nodeid).unwrap();
+        let parents = self.dag.get(nodeid).unwrap().parents.clone();
+        let rows: usize = shape[1..].iter().product();
+        link.callback(move |evt: Event| {
+            let text = evt
+                .dyn_ref::<ClipboardEvent>()
+                .and_then(|evt| evt.clipboard_data())
+                .and_then(|data| data.get_data("text/plain").ok());
+            let text = match text {
+                // a single cell is pasted as usual
+                Some(text) if text.trim_end().contains(&['\t', '\n'][..]) => text,
+                _ => return Msg::Ignore,
+            };
+            evt.prevent_default();
+            match csv::read_block(&text, rows - row, shape[0] - value) {
+                Ok(block) => {
+                    for (r, cells) in block.iter().enumerate() {
+                        for (v, p) in cells.iter().enumerate() {
+                            let name = credency_input_name(&shape, &parents, row + r, value + v);
+                            let query = format!("input[name=\"{}\"]", name);
+                            let input = window()
+                                .unwrap()
+                                .document()
+                                .unwrap()
+                                .query_selector(&query)
+                                .unwrap()
+                                .unwrap();
+                            let input: HtmlInputElement = input.dyn_into().unwrap();
+                            input.set_value(&p.to_string());
+                        }
+                    }
+                    let (credencies, descriptions, sample_sizes) =
+                        extract_credencies(&shape, &parents);
+                    Msg::UpdateCredencies {
+                        node: nodeid,
+                        credencies,
+                        descriptions,
+                        sample_sizes,
+                    }
+                }
+                Err(errors) => Msg::PasteErrors {
+                    node: nodeid,
+                    // point to the cells of the table rather than of the pasted text
+                    errors: errors
+                        .into_iter()
+                        .map(|mut error| {
+                            if let (Some(line), Some(column)) = (error.line, error.column) {
+                                error.line = Some(row + line - 1);
+                                error.column = Some(value + column - 1);
+                            }
+                            error
+                        })
+                        .collect(),
+                },
+            }
+        })
+    }
+
     #[allow(clippy::type_complexity)]
     fn make_credencies_edit_line(
         &self,
         nodeid: usize,
         target: Option<(usize, Vec<(usize, &String, usize, &String)>)>,
+        link: &Scope<Self>,
     ) -> Html {
         let node = self.dag.get(nodeid).unwrap();
         if let Some((line_id, parent_values)) = target {
@@ -235,6 +455,7 @@ impl BayesOMatic {
                                 <input class="input"
                                        name={ format!("{}_{}", label, i) }
                                        size=2
+                                       onpaste={ self.paste_credencies(nodeid, line_id, i, link) }
                                        value={
                                     node.credencies.as_ref()
                                         .map(|array| array[IxDyn(&idx)])
@@ -249,16 +470,22 @@ impl BayesOMatic {
                                   value={ node.cred_description.get(line_id).cloned().unwrap_or_default() }>
                         </textarea>
                     </td>
+                    <td>
+                        <input class="input" name={ format!("{}_sample_size", label) } size=3
+                               placeholder="0"
+                               value={ sample_size_value(node, line_id) } />
+                    </td>
                 </tr>
             }
         } else {
             html! {
                 <tr>
-                    <th>{ "Prior" }</th>
+                    <th>{ lang!(self.lang, "prior") }</th>
                     { for (0..node.values.len()).map(|i| {
                         html! {
                         <td>
-                            <input class="input" name={ format!("prior_{}", i) } size=2 value={
+                            <input class="input" name={ format!("prior_{}", i) } size=2
+                                   onpaste={ self.paste_credencies(nodeid, 0, i, link) } value={
                                 node.credencies
                                     .as_ref()
                                     .map(|array| array[i])
@@ -274,13 +501,133 @@ impl BayesOMatic {
                                   value={ node.cred_description.get(0).cloned().unwrap_or_default() }>
                         </textarea>
                     </td>
+                    <td>
+                        <input class="input" name="prior_sample_size" size=3
+                               placeholder="0"
+                               value={ sample_size_value(node, 0) } />
+                    </td>
                 </tr>
             }
         }
     }
 
+    fn make_rule_line(
+        &self,
+        nodeid: usize,
+        rule_id: usize,
+        rule: &CptRule,
+        link: &Scope<Self>,
+    ) -> Html {
+        let node = self.dag.get(nodeid).unwrap();
+        let (nrules, nparents, nval) = (
+            node.rules.as_ref().unwrap().rules.len(),
+            node.parents.len(),
+            node.values.len(),
+        );
+        html! {
+            <tr>
+                { for node.parents.iter().enumerate().map(|(k, &p)| {
+                    let parent = self.dag.get(p).unwrap();
+                    html! {
+                        <td>
+                            <div class="select">
+                            <select name={ format!("rule_{}_parent_{}", rule_id, k) }>
+                                <option selected={ rule.context[k].is_none() } value="any">{ lang!(self.lang, "any-value") }</option>
+                                { for parent.values.iter().enumerate().map(|(i, v)| {
+                                    html! { <option selected={ rule.context[k] == Some(i) } value={ i.to_string() }>{ v }</option> }
+                                })}
+                            </select>
+                            </div>
+                        </td>
+                    }
+                })}
+                { for rule.credencies.iter().enumerate().map(|(i, c)| {
+                    html! {
+                        <td>
+                            <input class="input" name={ format!("rule_{}_{}", rule_id, i) } size=2 value={ c.to_string() } />
+                        </td>
+                    }
+                })}
+                <td>
+                    <textarea class="textarea" cols=20 rows=1 name={ format!("rule_{}_description", rule_id) }
+                              placeholder={ lang!(self.lang, "row-desc") }
+                              value={ rule.description.clone() }>
+                    </textarea>
+                </td>
+                <td>
+                    <a href="#" class="delete-button" onclick={ link.callback(move |_| {
+                        let mut rules = extract_rules(nrules, nparents, nval);
+                        rules.rules.remove(rule_id);
+                        Msg::UpdateRules { node: nodeid, rules: Some(rules) }
+                    })}>{ "×" }</a>
+                </td>
+            </tr>
+        }
+    }
+
+    fn make_rules_edit(&self, nodeid: usize, link: &Scope<Self>) -> Html {
+        let node = self.dag.get(nodeid).unwrap();
+        let rules = node.rules.as_ref().unwrap();
+        let (nrules, nparents, nval) = (rules.rules.len(), node.parents.len(), node.values.len());
+
+        html! {
+            <div>
+            <p>{ lang!(self.lang, "rules-help") }</p>
+            <table class="table">
+                <tr>
+                    { for node.parents.iter().map(|&p| {
+                        html! { <th>{ &self.dag.get(p).unwrap().label }</th> }
+                    })}
+                    { for node.values.iter().map(|v| {
+                        html! {
+                            <th>{ format!("𝒫({})", v) }</th>
+                        }
+                    })}
+                    <th>{ lang!(self.lang, "explanation") }</th>
+                    <th></th>
+                </tr>
+                { for rules.rules.iter().enumerate().map(|(i, rule)| self.make_rule_line(nodeid, i, rule, link)) }
+                <tr>
+                    <th colspan={ nparents.max(1).to_string() }>{ lang!(self.lang, "default-row") }</th>
+                    { for rules.default.iter().enumerate().map(|(i, c)| {
+                        html! {
+                            <td>
+                                <input class="input" name={ format!("rule_default_{}", i) } size=2 value={ c.to_string() } />
+                            </td>
+                        }
+                    })}
+                    <td>
+                        <textarea class="textarea" cols=20 rows=1 name="rule_default_description"
+                                  placeholder={ lang!(self.lang, "row-desc") }
+                                  value={ rules.default_description.clone() }>
+                        </textarea>
+                    </td>
+                    <td></td>
+                </tr>
+            </table>
+            <ul class="blocky">
+                <li><a href="#" class="button" onclick={ link.callback(move |_| {
+                    let mut rules = extract_rules(nrules, nparents, nval);
+                    let new_rule = CptRule::wildcard(nparents, rules.default.clone());
+                    rules.rules.push(new_rule);
+                    Msg::UpdateRules { node: nodeid, rules: Some(rules) }
+                })}>{ lang!(self.lang, "add-rule") }</a></li>
+                <li><a href="#" class="button" onclick={ link.callback(move |_| {
+                    Msg::UpdateRules { node: nodeid, rules: Some(extract_rules(nrules, nparents, nval)) }
+                })}>{ lang!(self.lang, "save-credencies") }</a></li>
+                <li><a href="#" class="button" onclick={ link.callback(move |_| {
+                    Msg::UpdateRules { node: nodeid, rules: None }
+                })}>{ lang!(self.lang, "use-full-table") }</a></li>
+            </ul>
+            </div>
+        }
+    }
+
     fn make_credencies_edit(&self, nodeid: usize, link: &Scope<Self>) -> Html {
         let node = self.dag.get(nodeid).unwrap();
+        if node.rules.is_some() {
+            return self.make_rules_edit(nodeid, link);
+        }
         // one line in the table for all possible combination of parent values
         let values_iterator = node
             .parents
@@ -304,13 +651,19 @@ impl BayesOMatic {
                 .map(|&p| self.dag.get(p).unwrap().values.len()),
         );
         let parents = node.parents.clone();
+        // switching to rules starts from the current table
+        let initial_rules = match node.credencies {
+            Some(ref credencies) => RuleTable::from_credencies(credencies, &node.cred_description),
+            None => RuleTable::uniform(node.values.len()),
+        };
 
         let extract_credencies = move || {
-            let (credencies, descriptions) = extract_credencies(&shape, &parents);
+            let (credencies, descriptions, sample_sizes) = extract_credencies(&shape, &parents);
             Msg::UpdateCredencies {
                 node: nodeid,
                 credencies,
                 descriptions,
+                sample_sizes,
             }
         };
 
@@ -327,11 +680,158 @@ impl BayesOMatic {
                         }
                     })}
                     <th>{ lang!(self.lang, "explanation") }</th>
+                    <th title={ lang!(self.lang, "sample-size-help") }>{ lang!(self.lang, "sample-size") }</th>
                 </tr>
-                { if node.parents.is_empty() { self.make_credencies_edit_line(nodeid, None) } else { html!{} }}
-                { for values_iterator.map(|(iv, values)| self.make_credencies_edit_line(nodeid, Some((iv, values)))) }
+                { if node.parents.is_empty() { self.make_credencies_edit_line(nodeid, None, link) } else { html!{} }}
+                { for values_iterator.map(|(iv, values)| self.make_credencies_edit_line(nodeid, Some((iv, values)), link)) }
             </table>
-            <a href="#" class="button" onclick={ link.callback(move |_| extract_credencies())}>{ lang!(self.lang, "save-credencies") }</a>
+            { self.make_paste_errors(nodeid) }
+            <ul class="blocky">
+                <li><a href="#" class="button" onclick={ link.callback(move |_| extract_credencies())}>{ lang!(self.lang, "save-credencies") }</a></li>
+                <li><a href="#" class="button" onclick={ link.callback(move |_| Msg::UpdateRules {
+                    node: nodeid,
+                    rules: Some(initial_rules.clone()),
+                })}>{ lang!(self.lang, "use-rules") }</a></li>
+            </ul>
+            </div>
+        }
+    }
+
+    /// The problems of the last block pasted in the table of the node
+    fn make_paste_errors(&self, nodeid: usize) -> Html {
+        let errors = match self.paste_errors {
+            Some((node, ref errors)) if node == nodeid => errors,
+            _ => return html! {},
+        };
+        html! {
+            <div class="notification is-danger">
+            <p>{ lang!(self.lang, "paste-errors") }</p>
+            <ul>
+            { for errors.iter().map(|error| html! {
+                <li>{ self.describe_paste_error(nodeid, error) }</li>
+            })}
+            </ul>
+            </div>
+        }
+    }
+
+    /// A problem in a pasted cell, with the row and value of the table it was
+    /// pasted in
+    fn describe_paste_error(&self, nodeid: usize, error: &CellError) -> String {
+        let problem = self.describe_cell_problem(&error.problem, &error.found);
+        let node = self.dag.get(nodeid).unwrap();
+        let (row, value) = match (error.line, error.column) {
+            (Some(row), Some(value)) if value < node.values.len() => (row, value),
+            _ => return problem,
+        };
+        let parents: Vec<_> = node
+            .parents
+            .iter()
+            .map(|&p| self.dag.get(p).unwrap())
+            .collect();
+        let shape: Vec<usize> = parents.iter().map(|p| p.values.len()).collect();
+        let row = match parent_configurations(&shape).nth(row) {
+            Some(_) if parents.is_empty() => lang!(self.lang, "prior"),
+            Some(configuration) => parents
+                .iter()
+                .zip(configuration)
+                .map(|(p, v)| format!("{} = {}", p.label, p.values[v]))
+                .join(", "),
+            None => return problem,
+        };
+        lang!(
+            self.lang,
+            "paste-cell",
+            row = &row[..],
+            value = &node.values[value][..],
+            problem = problem
+        )
+    }
+
+    /// A problem in a cell of a CSV file, with its position
+    pub(crate) fn describe_cell_error(&self, error: &CellError) -> String {
+        let problem = self.describe_cell_problem(&error.problem, &error.found);
+        match (error.line, error.column) {
+            (Some(line), Some(column)) => lang!(
+                self.lang,
+                "csv-cell",
+                line = line,
+                column = column,
+                problem = problem
+            ),
+            (Some(line), None) => lang!(self.lang, "csv-line", line = line, problem = problem),
+            _ => problem,
+        }
+    }
+
+    fn describe_cell_problem(&self, problem: &CellProblem, found: &str) -> String {
+        match problem {
+            CellProblem::UnknownColumn => lang!(self.lang, "csv-unknown-column", found = found),
+            CellProblem::MissingColumn(name) => {
+                lang!(self.lang, "csv-missing-column", name = &name[..])
+            }
+            CellProblem::ValueOf(parent) => {
+                lang!(
+                    self.lang,
+                    "csv-value-of",
+                    found = found,
+                    parent = &parent[..]
+                )
+            }
+            CellProblem::Number => lang!(self.lang, "csv-number", found = found),
+            CellProblem::DuplicateRow(line) => lang!(self.lang, "csv-duplicate-row", line = *line),
+            CellProblem::BlockTooLarge { rows, columns } => {
+                lang!(
+                    self.lang,
+                    "paste-too-large",
+                    rows = *rows,
+                    columns = *columns
+                )
+            }
+            CellProblem::MissingRow(values) => {
+                lang!(self.lang, "csv-missing-row", values = &values[..])
+            }
+        }
+    }
+
+    /// Exporting the table of a node to a spreadsheet and importing it back
+    fn make_table_csv(&self, nodeid: usize, link: &Scope<Self>) -> Html {
+        let errors = match self.table_errors {
+            Some((node, ref errors)) if node == nodeid => &errors[..],
+            _ => &[],
+        };
+        html! {
+            <div>
+            <ul class="blocky">
+                <li><a href="#" class="button" onclick={ link.callback(move |_| Msg::ExportTable(nodeid)) }>{ lang!(self.lang, "export-table-csv") }</a></li>
+                <li>
+                <div class="file">
+                <label class="file-label">
+                <input type="file" class="file-input" accept=".csv,.tsv,.txt" onchange={ link.callback_future(move |evt: Event| async move {
+                    let fileinput = evt.target_dyn_into::<HtmlInputElement>().unwrap();
+                    let file = fileinput.files()?.get(0)?;
+                    let text = wasm_bindgen_futures::JsFuture::from(file.text()).await.ok()?.as_string()?;
+                    // allow importing the same file again after fixing it
+                    fileinput.set_value("");
+                    Some(Msg::ImportTable { node: nodeid, text })
+                })} />
+                <span class="file-cta">
+                    <span class="file-label">{ lang!(self.lang, "import-table-csv") }</span>
+                </span>
+                </label>
+                </div>
+                </li>
+            </ul>
+            { if errors.is_empty() { html!{} } else { html! {
+                <div class="notification is-danger">
+                <p>{ lang!(self.lang, "table-csv-errors") }</p>
+                <ul>
+                { for errors.iter().map(|error| html! {
+                    <li>{ self.describe_cell_error(error) }</li>
+                })}
+                </ul>
+                </div>
+            }}}
             </div>
         }
     }
@@ -364,6 +864,7 @@ impl BayesOMatic {
                 <ul class="blocky">
                 <li class="button" onclick={ link.callback(move |_| Msg::DuplicateNode(nodeid)) }>{ lang!(self.lang, "duplicate-node") }</li>
                 <li class="button" onclick={ link.callback(move |_| Msg::RemoveNode(nodeid)) }>{ lang!(self.lang, "remove-node") }</li>
+                <li class="button" title={ lang!(self.lang, "absorb-node-help") } onclick={ link.callback(move |_| Msg::AbsorbNode(nodeid)) }>{ lang!(self.lang, "absorb-node") }</li>
                 </ul>
                 { self.make_label_edit(nodeid, link) }
                 { self.make_values_edit(nodeid, link) }
@@ -371,6 +872,102 @@ impl BayesOMatic {
                 { self.make_parents_edit(nodeid, link) }
                 { self.make_node_description_edit(nodeid, link) }
                 { self.make_credencies_edit(nodeid, link) }
+                { self.make_table_csv(nodeid, link) }
+            </div>
+        }
+    }
+
+    fn make_metadata_field<F>(
+        &self,
+        label: &str,
+        value: &str,
+        multiline: bool,
+        link: &Scope<Self>,
+        set: F,
+    ) -> Html
+    where
+        F: Fn(&mut Metadata, String) + 'static,
+    {
+        let metadata = self.dag.metadata().clone();
+        let make_msg = move |value: String| {
+            let mut metadata = metadata.clone();
+            set(&mut metadata, value);
+            Msg::SetMetadata(metadata)
+        };
+        html! {
+            <div class="field">
+                <label class="label">{ lang!(self.lang, label) }</label>
+                <div class="control">
+                { if multiline {
+                    html! {
+                        <textarea class="textarea" rows=3
+                                  oninput={ link.callback(move |evt: InputEvent| make_msg(evt.target_dyn_into::<HtmlTextAreaElement>().unwrap().value())) }
+                                  value={ value.to_owned() }>
+                        </textarea>
+                    }
+                } else {
+                    html! {
+                        <input class="input"
+                               oninput={ link.callback(move |evt: InputEvent| make_msg(evt.target_dyn_into::<HtmlInputElement>().unwrap().value())) }
+                               value={ value.to_owned() } />
+                    }
+                }}
+                </div>
+            </div>
+        }
+    }
+
+    pub fn make_metadata_tab(&self, link: &Scope<Self>) -> Html {
+        let metadata = self.dag.metadata();
+        html! {
+            <div id="node-editor" class="box">
+                { self.make_metadata_field("model-title", &metadata.title, false, link, |m, v| m.title = v) }
+                { self.make_metadata_field("model-question", &metadata.question, true, link, |m, v| m.question = v) }
+                { self.make_metadata_field("model-author", &metadata.author, false, link, |m, v| m.author = v) }
+                { self.make_metadata_field("model-license", &metadata.license, false, link, |m, v| m.license = v) }
+                { self.make_metadata_field("model-created", &metadata.created, false, link, |m, v| m.created = v) }
+            </div>
+        }
+    }
+
+    pub fn make_text_tab(&self, link: &Scope<Self>) -> Html {
+        // show the line of a syntax error under the message
+        let error_line = match self.source_error {
+            Some(DeserError::Syntax(ref e)) => self.source.lines().nth(e.line.max(1) - 1),
+            _ => None,
+        };
+        html! {
+            <div id="node-editor" class="box">
+                <p class="block">{ lang!(self.lang, "text-editor-help") }</p>
+                <div class="field">
+                    <div class="control">
+                        <textarea class="textarea is-family-monospace" rows=30 spellcheck="false"
+                                  oninput={ link.callback(|evt: InputEvent| Msg::SetSource(evt.target_dyn_into::<HtmlTextAreaElement>().unwrap().value())) }
+                                  value={ self.source.clone() }>
+                        </textarea>
+                    </div>
+                </div>
+                { if let Some(ref error) = self.source_error {
+                    html! {
+                        <div class="block">
+                            { self.print_deser_error(error) }
+                            { if let Some(line) = error_line {
+                                html! { <pre class="error">{ line }</pre> }
+                            } else {
+                                html! {}
+                            }}
+                        </div>
+                    }
+                } else if !self.source_report.is_empty() {
+                    html! {
+                        <div class="block">
+                            <p>{ lang!(self.lang, "text-editor-problems") }</p>
+                            { self.print_report(&self.source_report) }
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }}
             </div>
         }
     }
@@ -0,0 +1,254 @@
+use ndarray::{ArrayD, IxDyn};
+
+use crate::graph::Dag;
+use crate::rules::parent_configurations;
+
+/*
+ * Exact inference by variable elimination
+ *
+ * The beliefs page relies on loopy belief propagation, which is approximate on
+ * graphs with loops. Learning and evaluating models on data needs exact
+ * posteriors and the likelihood of the observations, which this computes by
+ * multiplying the tables of the nodes and summing out the unobserved ones, one
+ * at a time.
+ *
+ * Only the ancestors of the queried and observed nodes are involved, the tables
+ * of the other nodes summing to one.
+ */
+
+/// A function of the values of some nodes, stored as a table where the last
+/// node varies the fastest
+#[derive(Clone, Debug, PartialEq)]
+pub struct Factor {
+    pub nodes: Vec<usize>,
+    pub cards: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+impl Factor {
+    fn scalar(value: f64) -> Factor {
+        Factor {
+            nodes: Vec::new(),
+            cards: Vec::new(),
+            values: vec![value],
+        }
+    }
+
+    /// The table of a node given its parents, restricted to the observed values
+    /// of the row, which the factor then does not depend on
+    fn from_table(
+        node: usize,
+        parents: &[usize],
+        table: &ArrayD<f32>,
+        row: &[Option<usize>],
+    ) -> Factor {
+        let family: Vec<usize> = std::iter::once(node)
+            .chain(parents.iter().copied())
+            .collect();
+        let free: Vec<usize> = (0..family.len())
+            .filter(|&k| row[family[k]].is_none())
+            .collect();
+        let cards: Vec<usize> = free.iter().map(|&k| table.shape()[k]).collect();
+        let mut idx: Vec<usize> = family.iter().map(|&n| row[n].unwrap_or(0)).collect();
+        let values = parent_configurations(&cards)
+            .map(|assignment| {
+                for (&k, &v) in free.iter().zip(&assignment) {
+                    idx[k] = v;
+                }
+                f64::from(table[IxDyn(&idx)])
+            })
+            .collect();
+        Factor {
+            nodes: free.iter().map(|&k| family[k]).collect(),
+            cards,
+            values,
+        }
+    }
+
+    /// The value of the factor for an assignment of the given nodes, which
+    /// include its own
+    pub fn value(&self, nodes: &[usize], assignment: &[usize]) -> f64 {
+        let mut i = 0;
+        for (&n, &card) in self.nodes.iter().zip(&self.cards) {
+            let k = nodes.iter().position(|&m| m == n).unwrap();
+            i = i * card + assignment[k];
+        }
+        self.values[i]
+    }
+
+    fn product(&self, other: &Factor) -> Factor {
+        let mut nodes = self.nodes.clone();
+        let mut cards = self.cards.clone();
+        for (&n, &card) in other.nodes.iter().zip(&other.cards) {
+            if !nodes.contains(&n) {
+                nodes.push(n);
+                cards.push(card);
+            }
+        }
+        let values = parent_configurations(&cards)
+            .map(|assignment| self.value(&nodes, &assignment) * other.value(&nodes, &assignment))
+            .collect();
+        Factor {
+            nodes,
+            cards,
+            values,
+        }
+    }
+
+    fn sum_out(&self, node: usize) -> Factor {
+        let k = self.nodes.iter().position(|&n| n == node).unwrap();
+        let mut nodes = self.nodes.clone();
+        let mut cards = self.cards.clone();
+        nodes.remove(k);
+        cards.remove(k);
+        let mut values = vec![0.0; cards.iter().product()];
+        for (assignment, &value) in parent_configurations(&self.cards).zip(&self.values) {
+            let mut i = 0;
+            for (j, &card) in cards.iter().enumerate() {
+                let v = assignment[if j < k { j } else { j + 1 }];
+                i = i * card + v;
+            }
+            values[i] += value;
+        }
+        Factor {
+            nodes,
+            cards,
+            values,
+        }
+    }
+
+    /// The sum of all the values of the factor
+    pub fn total(&self) -> f64 {
+        self.values.iter().sum()
+    }
+}
+
+/// The conditional probabilities of all the nodes, indexed like the nodes of
+/// the model
+pub fn tables(dag: &Dag) -> Vec<Option<ArrayD<f32>>> {
+    (0..dag.node_ids().len())
+        .map(|i| dag.normalized_credencies(i))
+        .collect()
+}
+
+/// The joint probability of the unobserved nodes of `query` and of the
+/// observations of the row, whose total is the probability of the observations.
+///
+/// The row gives the observed value of each node, indexed like the nodes of the
+/// model, and `tables` their conditional probabilities as given by `tables`.
+pub fn joint(
+    dag: &Dag,
+    tables: &[Option<ArrayD<f32>>],
+    row: &[Option<usize>],
+    query: &[usize],
+) -> Factor {
+    let mut relevant = vec![false; row.len()];
+    let mut stack: Vec<usize> = query
+        .iter()
+        .copied()
+        .chain(
+            dag.iter_nodes()
+                .map(|(i, _)| i)
+                .filter(|&i| row[i].is_some()),
+        )
+        .collect();
+    while let Some(n) = stack.pop() {
+        if !relevant[n] {
+            relevant[n] = true;
+            stack.extend(&dag.get(n).unwrap().parents);
+        }
+    }
+
+    let mut factors: Vec<Factor> = dag
+        .iter_nodes()
+        .filter(|&(i, _)| relevant[i])
+        .map(|(i, node)| Factor::from_table(i, &node.parents, tables[i].as_ref().unwrap(), row))
+        .collect();
+    let mut eliminated: Vec<usize> = (0..row.len())
+        .filter(|&i| relevant[i] && row[i].is_none() && !query.contains(&i))
+        .collect();
+    while !eliminated.is_empty() {
+        // eliminate first the node giving the smallest factor
+        let size = |n: usize| {
+            let mut nodes = Vec::new();
+            let mut size = 1;
+            for factor in factors.iter().filter(|f| f.nodes.contains(&n)) {
+                for (&m, &card) in factor.nodes.iter().zip(&factor.cards) {
+                    if !nodes.contains(&m) {
+                        nodes.push(m);
+                        size *= card;
+                    }
+                }
+            }
+            size
+        };
+        let k = (0..eliminated.len())
+            .min_by_key(|&k| size(eliminated[k]))
+            .unwrap();
+        let node = eliminated.swap_remove(k);
+        let (with, without): (Vec<_>, Vec<_>) =
+            factors.into_iter().partition(|f| f.nodes.contains(&node));
+        factors = without;
+        let product = with
+            .iter()
+            .fold(Factor::scalar(1.0), |acc, f| acc.product(f));
+        factors.push(product.sum_out(node));
+    }
+    factors
+        .iter()
+        .fold(Factor::scalar(1.0), |acc, f| acc.product(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    // a -> b -> c
+    const CHAIN: &str = "node a { values y, n }
+node b { values y, n }
+node c { values t, f }
+a -> b -> c
+table a { 3, 7 }
+table b { (y): 0.9, 0.1
+(n): 0.2, 0.8 }
+table c { (y): 0.6, 0.4
+(n): 0.1, 0.9 }";
+
+    fn assert_close(found: f64, expected: f64) {
+        assert!(
+            (found - expected).abs() < 1e-6,
+            "{} instead of {}",
+            found,
+            expected
+        );
+    }
+
+    #[test]
+    fn chain() {
+        let (dag, _) = text::read(CHAIN).unwrap();
+        let tables = tables(&dag);
+        // the tables are normalized
+        assert_close(f64::from(tables[0].as_ref().unwrap()[0]), 0.3);
+
+        // P(c = t) = 0.41 * 0.6 + 0.59 * 0.1
+        let factor = joint(&dag, &tables, &[None, None, Some(0)], &[]);
+        assert_close(factor.total(), 0.305);
+
+        // P(a, c = t)
+        let factor = joint(&dag, &tables, &[None, None, Some(0)], &[0]);
+        assert_eq!(factor.nodes, vec![0]);
+        assert_close(factor.values[0], 0.3 * 0.55);
+        assert_close(factor.values[1], 0.7 * 0.2);
+
+        // P(b, c) when a is observed
+        let factor = joint(&dag, &tables, &[Some(1), None, None], &[1, 2]);
+        assert_close(factor.total(), 0.7);
+        assert_close(factor.value(&[1, 2], &[1, 1]), 0.7 * 0.8 * 0.9);
+
+        // the nodes downstream of the query and observations are left out
+        let factor = joint(&dag, &tables, &[None, None, None], &[0]);
+        assert_eq!(factor.nodes, vec![0]);
+        assert_close(factor.total(), 1.0);
+    }
+}
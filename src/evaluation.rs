@@ -0,0 +1,180 @@
+/*
+ * Evaluating the predictions of a target node against labelled cases
+ *
+ * Each case gives the probabilities predicted for the values of the target, and
+ * its actual value. The predicted value is the most probable one, from which
+ * come the accuracy and the confusion matrix. The log-loss and the Brier score
+ * judge the probabilities themselves: the log-loss is the average of minus the
+ * logarithm of the probability of the actual value, and the Brier score the
+ * average squared distance between the probabilities and the actual value,
+ * summed over the values.
+ *
+ * The reliability diagram groups all the predicted probabilities, of every value
+ * of every case, by how high they are, and compares each group with how often
+ * these values actually came: a well calibrated model predicting 0.7 is right
+ * 70% of the time.
+ */
+
+pub const CALIBRATION_BINS: usize = 10;
+// probabilities are kept above this in the log-loss, which would otherwise be
+// infinite as soon as the actual value was predicted impossible
+const MIN_PROBABILITY: f64 = 1e-15;
+
+/// Predicted probabilities falling in the same range
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CalibrationBin {
+    pub count: usize,
+    // the average predicted probability
+    pub predicted: f64,
+    // the proportion of these predictions whose value actually came
+    pub observed: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    // the number of cases evaluated
+    pub cases: usize,
+    // the cases without a value for the target
+    pub unlabelled: usize,
+    // the cases whose observations are impossible according to the model
+    pub impossible: usize,
+    // the number of cases of each actual value predicted as each value
+    pub confusion: Vec<Vec<usize>>,
+    pub accuracy: f64,
+    pub log_loss: f64,
+    pub brier: f64,
+    pub calibration: Vec<CalibrationBin>,
+}
+
+/// Evaluate the predictions of a target with `nvalues` values. Each case gives
+/// the predicted probabilities, `None` if the case is impossible, and the actual
+/// value, `None` if it is missing.
+pub fn evaluate(nvalues: usize, cases: &[(Option<Vec<f64>>, Option<usize>)]) -> Evaluation {
+    let mut evaluation = Evaluation {
+        cases: 0,
+        unlabelled: 0,
+        impossible: 0,
+        confusion: vec![vec![0; nvalues]; nvalues],
+        accuracy: 0.0,
+        log_loss: 0.0,
+        brier: 0.0,
+        calibration: vec![CalibrationBin::default(); CALIBRATION_BINS],
+    };
+    for (prediction, actual) in cases {
+        let (prediction, actual) = match (prediction, *actual) {
+            (_, None) => {
+                evaluation.unlabelled += 1;
+                continue;
+            }
+            (None, Some(_)) => {
+                evaluation.impossible += 1;
+                continue;
+            }
+            (Some(prediction), Some(actual)) => (prediction, actual),
+        };
+        evaluation.cases += 1;
+        // ties go to the first value
+        let predicted = (0..nvalues).fold(0, |best, v| {
+            if prediction[v] > prediction[best] {
+                v
+            } else {
+                best
+            }
+        });
+        evaluation.confusion[actual][predicted] += 1;
+        evaluation.log_loss -= prediction[actual].max(MIN_PROBABILITY).ln();
+        for (v, &p) in prediction.iter().enumerate() {
+            let outcome = if v == actual { 1.0 } else { 0.0 };
+            evaluation.brier += (p - outcome) * (p - outcome);
+            let bin = ((p * CALIBRATION_BINS as f64) as usize).min(CALIBRATION_BINS - 1);
+            let bin = &mut evaluation.calibration[bin];
+            bin.count += 1;
+            bin.predicted += p;
+            bin.observed += outcome;
+        }
+    }
+    if evaluation.cases > 0 {
+        let cases = evaluation.cases as f64;
+        let correct: usize = (0..nvalues).map(|v| evaluation.confusion[v][v]).sum();
+        evaluation.accuracy = correct as f64 / cases;
+        evaluation.log_loss /= cases;
+        evaluation.brier /= cases;
+    }
+    for bin in evaluation
+        .calibration
+        .iter_mut()
+        .filter(|bin| bin.count > 0)
+    {
+        bin.predicted /= bin.count as f64;
+        bin.observed /= bin.count as f64;
+    }
+    evaluation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cases() -> Vec<(Option<Vec<f64>>, Option<usize>)> {
+        vec![
+            (Some(vec![0.8, 0.2]), Some(0)),
+            (Some(vec![0.3, 0.7]), Some(0)),
+            // a tie, predicted as the first value
+            (Some(vec![0.5, 0.5]), Some(1)),
+            // the actual value was predicted impossible
+            (Some(vec![1.0, 0.0]), Some(1)),
+            (None, Some(0)),
+            (Some(vec![0.6, 0.4]), None),
+        ]
+    }
+
+    #[test]
+    fn counts_and_accuracy() {
+        let evaluation = evaluate(2, &cases());
+        assert_eq!(evaluation.cases, 4);
+        assert_eq!(evaluation.impossible, 1);
+        assert_eq!(evaluation.unlabelled, 1);
+        assert_eq!(evaluation.confusion, vec![vec![1, 1], vec![2, 0]]);
+        assert!((evaluation.accuracy - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn log_loss_is_clamped() {
+        let evaluation = evaluate(2, &cases());
+        let expected = -(0.8f64.ln() + 0.3f64.ln() + 0.5f64.ln() + MIN_PROBABILITY.ln()) / 4.0;
+        assert!(evaluation.log_loss.is_finite());
+        assert!((evaluation.log_loss - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn brier_score() {
+        let evaluation = evaluate(2, &cases());
+        assert!((evaluation.brier - (0.08 + 0.98 + 0.5 + 2.0) / 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn calibration_bins() {
+        let calibration = evaluate(2, &cases()).calibration;
+        assert_eq!(calibration.len(), CALIBRATION_BINS);
+        assert_eq!(calibration.iter().map(|bin| bin.count).sum::<usize>(), 8);
+        assert_eq!(calibration[1], CalibrationBin::default());
+        assert_eq!(calibration[0].count, 1);
+        assert_eq!(calibration[0].observed, 1.0);
+        assert_eq!(calibration[5].count, 2);
+        assert!((calibration[5].predicted - 0.5).abs() < 1e-12);
+        assert!((calibration[5].observed - 0.5).abs() < 1e-12);
+        // a probability of 1 falls in the last bin
+        assert_eq!(calibration[9].count, 1);
+        assert_eq!(calibration[9].predicted, 1.0);
+        assert_eq!(calibration[9].observed, 0.0);
+    }
+
+    #[test]
+    fn no_labelled_case() {
+        let evaluation = evaluate(2, &[(None, Some(0)), (Some(vec![0.5, 0.5]), None)]);
+        assert_eq!(evaluation.cases, 0);
+        assert_eq!(evaluation.accuracy, 0.0);
+        assert_eq!(evaluation.log_loss, 0.0);
+        assert!(evaluation.calibration.iter().all(|bin| bin.count == 0));
+    }
+}
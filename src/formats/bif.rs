@@ -0,0 +1,445 @@
+use std::fmt::Write;
+
+use super::lexer::{tokenize, Comments, Tokens};
+use super::table::{resolve, rows, Probability};
+use super::{name, quote, Expected, ParseError};
+use crate::graph::{Dag, DeserError, JsonDocument, JsonNode, Metadata, FORMAT_VERSION};
+use crate::validation::ValidationReport;
+
+/*
+ * The BIF interchange format
+ *
+ * Variables are named by the identifiers of the nodes. What BIF has no place
+ * for (labels, descriptions, observations, row explanations and the metadata
+ * of the model) is stored in properties, of the form `property key = "value";`.
+ * Properties written by other tools are ignored.
+ *
+ * The rows of the probability tables are written explicitly, prefixed with the
+ * values of the parents. In a `table`, as in the files of JavaBayes, the values
+ * of the node vary the slowest, the parents varying in the order of
+ * `parent_configurations` for each of them. The parents can be listed after a
+ * `|` or, as in BIF 0.15, directly after the node.
+ *
+ * Row explanations are written as `property explanation(a, b) = "...";`, the
+ * values of the parents in parentheses. This form is our own extension: other
+ * tools read it as an unknown property.
+ */
+
+const COMMENTS: Comments = Comments {
+    line: &["//"],
+    block: true,
+};
+
+/// A property of one of our known forms, the context being the parent values
+/// of a row explanation
+struct Property {
+    key: String,
+    context: Option<Vec<String>>,
+    value: String,
+}
+
+/// Parse a property, the `property` keyword having already been read. Properties
+/// that are not of the form we write are skipped.
+fn property(tokens: &mut Tokens) -> Result<Option<Property>, ParseError> {
+    let key = match tokens.name() {
+        Ok(key) => key,
+        Err(_) => return tokens.skip_past(';').map(|()| None),
+    };
+    let context = if tokens.eat_punct('(') {
+        let mut context = Vec::new();
+        while !tokens.eat_punct(')') {
+            context.push(tokens.name()?);
+            tokens.eat_punct(',');
+        }
+        Some(context)
+    } else {
+        None
+    };
+    if !tokens.eat_punct('=') {
+        return tokens.skip_past(';').map(|()| None);
+    }
+    let value = match tokens.name() {
+        Ok(value) if tokens.is_punct(';') => value,
+        _ => return tokens.skip_past(';').map(|()| None),
+    };
+    tokens.expect_punct(';')?;
+    Ok(Some(Property {
+        key,
+        context,
+        value,
+    }))
+}
+
+/// A list of numbers, optionally separated by commas, ending with `;`
+fn numbers(tokens: &mut Tokens) -> Result<Vec<f32>, ParseError> {
+    let mut numbers = Vec::new();
+    while !tokens.eat_punct(';') {
+        numbers.push(tokens.number()?);
+        tokens.eat_punct(',');
+    }
+    Ok(numbers)
+}
+
+fn network(tokens: &mut Tokens, metadata: &mut Metadata) -> Result<(), ParseError> {
+    let name = tokens.name()?;
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        tokens.expect_keyword("property")?;
+        if let Some(p) = property(tokens)? {
+            match &p.key[..] {
+                "title" => metadata.title = p.value,
+                "author" => metadata.author = p.value,
+                "question" => metadata.question = p.value,
+                "license" => metadata.license = p.value,
+                "created" => metadata.created = p.value,
+                _ => {}
+            }
+        }
+    }
+    if metadata.title.is_empty() && name != "unknown" {
+        metadata.title = name;
+    }
+    Ok(())
+}
+
+fn variable(tokens: &mut Tokens) -> Result<JsonNode, ParseError> {
+    let id = tokens.name()?;
+    let mut node = JsonNode {
+        id: Some(id.clone()),
+        label: id,
+        description: String::new(),
+        values: Vec::new(),
+        parents: Vec::new(),
+        observation: None,
+        credencies: None,
+        cred_description: Vec::new(),
+        sample_sizes: Vec::new(),
+        factor: false,
+        rules: None,
+        position: None,
+    };
+    let mut observation = None;
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        if tokens.eat_keyword("type") {
+            tokens.expect_keyword("discrete")?;
+            tokens.expect_punct('[')?;
+            tokens.integer()?;
+            tokens.expect_punct(']')?;
+            tokens.expect_punct('{')?;
+            while !tokens.eat_punct('}') {
+                node.values.push(tokens.name()?);
+                tokens.eat_punct(',');
+            }
+            tokens.expect_punct(';')?;
+        } else {
+            tokens.expect_keyword("property")?;
+            let line = tokens.line();
+            if let Some(p) = property(tokens)? {
+                match &p.key[..] {
+                    "label" => node.label = p.value,
+                    "description" => node.description = p.value,
+                    "observation" => observation = Some((line, p.value)),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if let Some((line, value)) = observation {
+        match node.values.iter().position(|v| *v == value) {
+            Some(i) => node.observation = Some(i),
+            None => {
+                return Err(ParseError {
+                    line,
+                    expected: Expected::ValueOf(node.label),
+                    found: Some(value),
+                })
+            }
+        }
+    }
+    Ok(node)
+}
+
+fn probability(tokens: &mut Tokens) -> Result<Probability, ParseError> {
+    let mut block = Probability {
+        line: tokens.line(),
+        child: String::new(),
+        parents: Vec::new(),
+        table: None,
+        entries: Vec::new(),
+        default: None,
+        explanations: Vec::new(),
+    };
+    tokens.expect_punct('(')?;
+    block.child = tokens.name()?;
+    tokens.eat_punct('|');
+    while !tokens.is_punct(')') {
+        block.parents.push(tokens.name()?);
+        tokens.eat_punct(',');
+    }
+    tokens.expect_punct(')')?;
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        let line = tokens.line();
+        if tokens.eat_keyword("table") {
+            block.table = Some(numbers(tokens)?);
+        } else if tokens.eat_keyword("default") {
+            block.default = Some(numbers(tokens)?);
+        } else if tokens.eat_keyword("property") {
+            if let Some(p) = property(tokens)? {
+                if p.key == "explanation" {
+                    block.explanations.push((line, p.context, p.value));
+                }
+            }
+        } else {
+            tokens.expect_punct('(')?;
+            let mut context = Vec::new();
+            while !tokens.eat_punct(')') {
+                context.push(tokens.name()?);
+                tokens.eat_punct(',');
+            }
+            block.entries.push((line, context, numbers(tokens)?));
+        }
+    }
+    Ok(block)
+}
+
+/// Reorder a `table` so that the values of the node vary the fastest, as
+/// `resolve` expects. A table of the wrong length is kept for the validation to
+/// report.
+fn child_fastest(table: &[f32], values: usize) -> Vec<f32> {
+    let rows = table.len() / values.max(1);
+    if rows * values != table.len() {
+        return table.to_vec();
+    }
+    (0..table.len())
+        .map(|i| table[(i % values) * rows + i / values])
+        .collect()
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let mut tokens = tokenize(text, &COMMENTS).map_err(DeserError::Syntax)?;
+    let mut metadata = Metadata::default();
+    let mut nodes = Vec::new();
+    let mut blocks = Vec::new();
+    while !tokens.is_empty() {
+        if tokens.eat_keyword("network") {
+            network(&mut tokens, &mut metadata).map_err(DeserError::Syntax)?;
+        } else if tokens.eat_keyword("variable") {
+            nodes.push(variable(&mut tokens).map_err(DeserError::Syntax)?);
+        } else if tokens.eat_keyword("probability") {
+            blocks.push(probability(&mut tokens).map_err(DeserError::Syntax)?);
+        } else {
+            return Err(DeserError::Syntax(
+                tokens.error(Expected::Keyword("variable")),
+            ));
+        }
+    }
+    let mut report = ValidationReport::new();
+    for mut block in blocks {
+        let values = nodes
+            .iter()
+            .find(|n| n.id.as_ref() == Some(&block.child))
+            .map(|n| n.values.len());
+        if let (Some(table), Some(values)) = (block.table.as_mut(), values) {
+            *table = child_fastest(table, values);
+        }
+        resolve(block, &mut nodes, &mut report).map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+fn write_property(out: &mut String, key: &str, value: &str) {
+    if !value.is_empty() {
+        writeln!(out, "    property {} = {};", key, quote(value)).unwrap();
+    }
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = dag.node_ids();
+    let metadata = dag.metadata();
+    let mut out = String::new();
+
+    let network_name = if metadata.title.is_empty() {
+        "unknown".into()
+    } else {
+        Dag::slugify(&metadata.title)
+    };
+    writeln!(out, "network {} {{", name(&network_name)).unwrap();
+    write_property(&mut out, "title", &metadata.title);
+    write_property(&mut out, "author", &metadata.author);
+    write_property(&mut out, "question", &metadata.question);
+    write_property(&mut out, "license", &metadata.license);
+    write_property(&mut out, "created", &metadata.created);
+    writeln!(out, "}}").unwrap();
+
+    for (i, node) in dag.iter_nodes() {
+        writeln!(out, "variable {} {{", name(ids[i].as_ref().unwrap())).unwrap();
+        writeln!(
+            out,
+            "    type discrete [ {} ] {{ {} }};",
+            node.values.len(),
+            node.values
+                .iter()
+                .map(|v| name(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .unwrap();
+        write_property(&mut out, "label", &node.label);
+        write_property(&mut out, "description", &node.description);
+        if let Some(obs) = node.observation {
+            write_property(&mut out, "observation", &node.values[obs]);
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    for (i, node) in dag.iter_nodes() {
+        let child = name(ids[i].as_ref().unwrap());
+        if node.parents.is_empty() {
+            writeln!(out, "probability ( {} ) {{", child).unwrap();
+        } else {
+            let parents = node
+                .parents
+                .iter()
+                .map(|&p| name(ids[p].as_ref().unwrap()))
+                .collect::<Vec<_>>();
+            writeln!(out, "probability ( {} | {} ) {{", child, parents.join(", ")).unwrap();
+        }
+        for (r, (configuration, row)) in rows(dag, i).into_iter().enumerate() {
+            let row = row
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let context = configuration
+                .iter()
+                .zip(node.parents.iter())
+                .map(|(&v, &p)| name(&dag.get(p).unwrap().values[v]))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let explanation = node.cred_description.get(r).map(|d| &d[..]).unwrap_or("");
+            if node.parents.is_empty() {
+                writeln!(out, "    table {};", row).unwrap();
+                write_property(&mut out, "explanation", explanation);
+            } else {
+                writeln!(out, "    ({}) {};", context, row).unwrap();
+                write_property(&mut out, &format!("explanation({})", context), explanation);
+            }
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // as written by JavaBayes
+    const DOG_PROBLEM: &str = r#"// Bayesian Network in the Interchange Format
+// Produced by BayesianNetworks package in JavaBayes
+// Output created Sun Nov 02 17:49:49 GMT+00:00 1997
+// Bayesian network 
+network "Dog-Problem" { //5 variables and 5 probability distributions
+	property "credal-set constant-density-bounded 1.1" ;
+}
+variable  "light-on" { //2 values
+	type discrete[2] {  "true"  "false" };
+	property "position = (218, 195)" ;
+}
+variable  "bowel-problem" { //2 values
+	type discrete[2] {  "true"  "false" };
+	property "position = (335, 99)" ;
+}
+variable  "dog-out" { //2 values
+	type discrete[2] {  "true"  "false" };
+	property "position = (300, 195)" ;
+}
+variable  "hear-bark" { //2 values
+	type discrete[2] {  "true"  "false" };
+	property "position = (296, 268)" ;
+}
+variable  "family-out" { //2 values
+	type discrete[2] {  "true"  "false" };
+	property "position = (257, 99)" ;
+}
+probability (  "light-on"  "family-out" ) { //2 variable(s) and 4 values
+	table 0.6 0.05 0.4 0.95 ;
+}
+probability (  "bowel-problem" ) { //1 variable(s) and 2 values
+	table 0.01 0.99 ;
+}
+probability (  "dog-out"  "bowel-problem"  "family-out" ) { //3 variable(s) and 8 values
+	table 0.99 0.97 0.9 0.3 0.01 0.03 0.1 0.7 ;
+}
+probability (  "hear-bark"  "dog-out" ) { //2 variable(s) and 4 values
+	table 0.7 0.01 0.3 0.99 ;
+}
+probability (  "family-out" ) { //1 variable(s) and 2 values
+	table 0.15 0.85 ;
+}
+"#;
+
+    fn find(dag: &Dag, id: &str) -> usize {
+        dag.iter_nodes().find(|(_, n)| n.id == id).unwrap().0
+    }
+
+    /// P(node = value | parents), the parents being given in the order of the file
+    fn credency(dag: &Dag, id: &str, value: usize, parents: &[usize]) -> f32 {
+        let mut idx = vec![value];
+        idx.extend_from_slice(parents);
+        dag.get(find(dag, id)).unwrap().credencies.as_ref().unwrap()[ndarray::IxDyn(&idx)]
+    }
+
+    #[test]
+    fn reads_javabayes_tables() {
+        let (dag, report) = read(DOG_PROBLEM).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(dag.metadata().title, "Dog-Problem");
+        let dog_out = dag.get(find(&dag, "dog-out")).unwrap();
+        assert_eq!(
+            dog_out.parents,
+            vec![find(&dag, "bowel-problem"), find(&dag, "family-out")]
+        );
+        // P(light-on = true | family-out = true) = 0.6
+        assert_eq!(credency(&dag, "light-on", 0, &[0]), 0.6);
+        assert_eq!(credency(&dag, "light-on", 1, &[0]), 0.4);
+        assert_eq!(credency(&dag, "light-on", 0, &[1]), 0.05);
+        assert_eq!(credency(&dag, "dog-out", 0, &[0, 1]), 0.97);
+        assert_eq!(credency(&dag, "dog-out", 0, &[1, 0]), 0.9);
+        assert_eq!(credency(&dag, "dog-out", 1, &[1, 1]), 0.7);
+        assert_eq!(credency(&dag, "family-out", 0, &[]), 0.15);
+    }
+
+    #[test]
+    fn round_trips_tables_and_explanations() {
+        let (mut dag, _) = read(DOG_PROBLEM).unwrap();
+        let light_on = find(&dag, "light-on");
+        dag.set_cred_descriptions(light_on, vec!["nobody home".to_string(), String::new()])
+            .unwrap();
+        let text = write(&dag);
+        assert!(text.contains("property explanation(true) = \"nobody home\";"));
+        let (read_back, report) = read(&text).unwrap();
+        assert!(report.is_empty());
+        for id in &["light-on", "dog-out", "hear-bark"] {
+            let node = read_back.get(find(&read_back, id)).unwrap();
+            let original = dag.get(find(&dag, id)).unwrap();
+            assert_eq!(node.credencies, original.credencies);
+        }
+        assert_eq!(
+            read_back
+                .get(find(&read_back, "light-on"))
+                .unwrap()
+                .cred_description,
+            vec!["nobody home".to_string(), String::new()]
+        );
+    }
+}
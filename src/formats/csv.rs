@@ -0,0 +1,429 @@
+use ndarray::{ArrayD, IxDyn};
+
+use crate::graph::Dag;
+use crate::rules::parent_configurations;
+
+/*
+ * Comma separated values, as read and written by spreadsheets
+ *
+ * Fields may be quoted with double quotes, doubling the quotes they contain.
+ * Spreadsheets using a comma as their decimal separator write semicolons
+ * between fields instead, so the separator is guessed from the header line.
+ *
+ * The credencies table of a node is written with a column for each parent, then
+ * for each value of the node, and a last one for the explanations of the rows.
+ */
+
+/// Guess the separator of a file from its first line
+pub fn separator(text: &str) -> char {
+    let header = text.lines().next().unwrap_or("");
+    let mut quoted = false;
+    let mut counts = [(',', 0), (';', 0), ('\t', 0)];
+    for c in header.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if !quoted {
+            for count in counts.iter_mut().filter(|count| count.0 == c) {
+                count.1 += 1;
+            }
+        }
+    }
+    // the first one wins in case of tie
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|count| count.1)
+        .map(|count| count.0)
+        .unwrap()
+}
+
+/// Split a file into records of fields, with the line each record starts on.
+/// Empty lines are skipped.
+pub fn parse(text: &str, separator: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            }
+            '\r' if !quoted => {}
+            c if c == separator && !quoted => record.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push((start, record));
+    }
+    records
+}
+
+/// Write a record, quoting the fields that need it
+pub fn write_record<S: AsRef<str>>(fields: &[S], separator: char) -> String {
+    let mut out = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains(&[separator, '"', '\n', '\r'][..]) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.into()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&separator.to_string());
+    out.push('\n');
+    out
+}
+
+/// Read a number, accepting a comma as the decimal separator
+pub fn parse_number(field: &str) -> Option<f32> {
+    let field = field.trim();
+    field
+        .parse()
+        .ok()
+        .or_else(|| field.replace(',', ".").parse().ok())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellProblem {
+    // a column that is neither a parent, a value of the node nor the explanation
+    UnknownColumn,
+    // no column is given for this parent or value of the node
+    MissingColumn(String),
+    // expected one of the values of this parent
+    ValueOf(String),
+    // expected a non-negative number
+    Number,
+    // the same parent values were given on this earlier line
+    DuplicateRow(usize),
+    // no row is given for these parent values
+    MissingRow(String),
+    // a pasted block does not fit in the rows and columns left in the table
+    BlockTooLarge { rows: usize, columns: usize },
+}
+
+/// A problem in a cell of an imported table, or in a whole row or table when
+/// there is no cell to point to
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub found: String,
+    pub problem: CellProblem,
+}
+
+/// Read a block of numbers copied from a spreadsheet, which separates cells with
+/// tabs, checking that it fits in the given number of rows and columns
+pub fn read_block(
+    text: &str,
+    rows: usize,
+    columns: usize,
+) -> Result<Vec<Vec<f32>>, Vec<CellError>> {
+    let records = parse(text, '\t');
+    if records.len() > rows || records.iter().any(|(_, r)| r.len() > columns) {
+        return Err(vec![CellError {
+            line: None,
+            column: None,
+            found: String::new(),
+            problem: CellProblem::BlockTooLarge { rows, columns },
+        }]);
+    }
+    let mut errors = Vec::new();
+    let mut block = Vec::with_capacity(records.len());
+    for (line, record) in records {
+        let mut row = Vec::with_capacity(record.len());
+        for (c, cell) in record.iter().enumerate() {
+            match parse_number(cell) {
+                Some(p) if p >= 0.0 => row.push(p),
+                _ => errors.push(CellError {
+                    line: Some(line),
+                    column: Some(c + 1),
+                    found: cell.trim().into(),
+                    problem: CellProblem::Number,
+                }),
+            }
+        }
+        block.push(row);
+    }
+    if errors.is_empty() {
+        Ok(block)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The credencies table of a node, with the given header for the explanations
+pub fn write_table(dag: &Dag, node: usize, explanation: &str) -> String {
+    let data = dag.get(node).unwrap();
+    let parents: Vec<_> = data.parents.iter().map(|&p| dag.get(p).unwrap()).collect();
+    let shape = dag.credencies_shape(node).unwrap();
+    let mut header: Vec<&str> = parents.iter().map(|p| &p.label[..]).collect();
+    header.extend(data.values.iter().map(|v| &v[..]));
+    header.push(explanation);
+    let mut out = write_record(&header, ',');
+    for (r, configuration) in parent_configurations(&shape[1..]).enumerate() {
+        let mut record: Vec<String> = configuration
+            .iter()
+            .zip(&parents)
+            .map(|(&v, p)| p.values[v].clone())
+            .collect();
+        let mut idx = vec![0];
+        idx.extend(configuration.iter().copied());
+        for v in 0..shape[0] {
+            idx[0] = v;
+            let credency = data
+                .credencies
+                .as_ref()
+                .map(|c| c[IxDyn(&idx)])
+                .unwrap_or(1.0);
+            record.push(credency.to_string());
+        }
+        record.push(data.cred_description.get(r).cloned().unwrap_or_default());
+        out.push_str(&write_record(&record, ','));
+    }
+    out
+}
+
+/// Read the credencies table of a node and the explanations of its rows,
+/// matching the columns with the parents and values by their names. The rows
+/// may come in any order.
+pub fn read_table(
+    dag: &Dag,
+    node: usize,
+    text: &str,
+    explanation: &str,
+) -> Result<(ArrayD<f32>, Vec<String>), Vec<CellError>> {
+    let data = dag.get(node).unwrap();
+    let parents: Vec<_> = data.parents.iter().map(|&p| dag.get(p).unwrap()).collect();
+    let shape = dag.credencies_shape(node).unwrap();
+    let mut records = parse(text, separator(text)).into_iter();
+    let mut errors = Vec::new();
+    let (header_line, header) = records.next().unwrap_or((1, Vec::new()));
+
+    // the column of each parent and value, and of the explanations
+    let mut parent_columns = vec![None; parents.len()];
+    let mut value_columns = vec![None; data.values.len()];
+    let mut explanation_column = None;
+    let same = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+    for (c, name) in header.iter().enumerate() {
+        if let Some(p) = (0..parents.len())
+            .find(|&p| parent_columns[p].is_none() && same(&parents[p].label, name))
+        {
+            parent_columns[p] = Some(c);
+        } else if let Some(v) = (0..data.values.len())
+            .find(|&v| value_columns[v].is_none() && same(&data.values[v], name))
+        {
+            value_columns[v] = Some(c);
+        } else if explanation_column.is_none()
+            && (same(explanation, name) || same("explanation", name))
+        {
+            explanation_column = Some(c);
+        } else if !name.trim().is_empty() {
+            errors.push(CellError {
+                line: Some(header_line),
+                column: Some(c + 1),
+                found: name.clone(),
+                problem: CellProblem::UnknownColumn,
+            });
+        }
+    }
+    let names = parents
+        .iter()
+        .map(|p| &p.label)
+        .zip(&parent_columns)
+        .chain(data.values.iter().zip(&value_columns));
+    for (name, _) in names.filter(|(_, column)| column.is_none()) {
+        errors.push(CellError {
+            line: Some(header_line),
+            column: None,
+            found: String::new(),
+            problem: CellProblem::MissingColumn(name.clone()),
+        });
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let configurations: Vec<Vec<usize>> = parent_configurations(&shape[1..]).collect();
+    let mut rows: Vec<Option<(usize, Vec<f32>, String)>> = vec![None; configurations.len()];
+    for (line, record) in records {
+        let cell = |c: usize| record.get(c).map(|f| f.trim()).unwrap_or("");
+        let mut configuration = Vec::with_capacity(parents.len());
+        for (parent, column) in parents.iter().zip(&parent_columns) {
+            let column = column.unwrap();
+            match parent.values.iter().position(|v| same(v, cell(column))) {
+                Some(v) => configuration.push(v),
+                None => errors.push(CellError {
+                    line: Some(line),
+                    column: Some(column + 1),
+                    found: cell(column).into(),
+                    problem: CellProblem::ValueOf(parent.label.clone()),
+                }),
+            }
+        }
+        let mut row = Vec::with_capacity(data.values.len());
+        for column in &value_columns {
+            let column = column.unwrap();
+            match parse_number(cell(column)) {
+                Some(p) if p >= 0.0 => row.push(p),
+                _ => errors.push(CellError {
+                    line: Some(line),
+                    column: Some(column + 1),
+                    found: cell(column).into(),
+                    problem: CellProblem::Number,
+                }),
+            }
+        }
+        if configuration.len() != parents.len() || row.len() != data.values.len() {
+            continue;
+        }
+        let r = configurations
+            .iter()
+            .position(|c| *c == configuration)
+            .unwrap();
+        if let Some((first, _, _)) = rows[r] {
+            errors.push(CellError {
+                line: Some(line),
+                column: None,
+                found: String::new(),
+                problem: CellProblem::DuplicateRow(first),
+            });
+            continue;
+        }
+        let explanation = explanation_column
+            .and_then(|c| record.get(c))
+            .cloned()
+            .unwrap_or_default();
+        rows[r] = Some((line, row, explanation));
+    }
+    // missing rows are likely the ones whose cells are wrong
+    let complete = errors.is_empty();
+    for (configuration, row) in configurations.iter().zip(&rows) {
+        if row.is_none() && complete {
+            errors.push(CellError {
+                line: None,
+                column: None,
+                found: String::new(),
+                problem: CellProblem::MissingRow(
+                    configuration
+                        .iter()
+                        .zip(&parents)
+                        .map(|(&v, p)| format!("{} = {}", p.label, p.values[v]))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let rows: Vec<(usize, Vec<f32>, String)> = rows.into_iter().map(Option::unwrap).collect();
+    let mut credencies = ArrayD::from_elem(IxDyn(&shape), 0.0);
+    for (configuration, (_, row, _)) in configurations.iter().zip(&rows) {
+        let mut idx = vec![0];
+        idx.extend(configuration.iter().copied());
+        for (v, &p) in row.iter().enumerate() {
+            idx[0] = v;
+            credencies[IxDyn(&idx)] = p;
+        }
+    }
+    let descriptions = rows.into_iter().map(|(_, _, d)| d).collect();
+    Ok((credencies, descriptions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    #[test]
+    fn quoted_fields() {
+        let text = "a,b\r\n\"x, y\",\"say \"\"hi\"\"\"\n\n\"two\nlines\",z";
+        assert_eq!(separator(text), ',');
+        assert_eq!(
+            parse(text, ','),
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (2, vec!["x, y".to_string(), "say \"hi\"".to_string()]),
+                (4, vec!["two\nlines".to_string(), "z".to_string()]),
+            ]
+        );
+        assert_eq!(
+            write_record(&["x, y", "a\"b", "c"], ','),
+            "\"x, y\",\"a\"\"b\",c\n"
+        );
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(separator("a;b;c\n1,5;2;3"), ';');
+        assert_eq!(separator("a\tb\n"), '\t');
+        // a separator inside quotes does not count
+        assert_eq!(separator("\"a;b;c\",d\n"), ',');
+        assert_eq!(separator(""), ',');
+        assert_eq!(parse_number(" 0,25 "), Some(0.25));
+        assert_eq!(parse_number("1e-2"), Some(0.01));
+        assert_eq!(parse_number("x"), None);
+    }
+
+    #[test]
+    fn semicolon_table() {
+        let (dag, _) =
+            text::read("node a { values y, n }\nnode c { values t, f }\na -> c").unwrap();
+        // the rows come in any order, with comma decimals
+        let text = "c;A;F;T;Explanation\nignored;n;0,75;0,25;\"x; y\"\n;y;0;1;\n";
+        let result = read_table(&dag, 1, text, "Explanation");
+        assert!(
+            matches!(result, Err(ref errors) if errors[0].problem == CellProblem::UnknownColumn)
+        );
+        let text = "A;F;T;Explanation\nn;0,75;0,25;\"x; y\"\ny;0;1;\n";
+        let (credencies, descriptions) = read_table(&dag, 1, text, "Explanation").unwrap();
+        assert_eq!(credencies[IxDyn(&[0, 0])], 1.0);
+        assert_eq!(credencies[IxDyn(&[1, 0])], 0.0);
+        assert_eq!(credencies[IxDyn(&[0, 1])], 0.25);
+        assert_eq!(credencies[IxDyn(&[1, 1])], 0.75);
+        assert_eq!(descriptions[1], "x; y");
+    }
+
+    #[test]
+    fn table_errors() {
+        let (dag, _) =
+            text::read("node a { values y, n }\nnode c { values t, f }\na -> c").unwrap();
+        let errors = read_table(&dag, 1, "a,t,f\ny,1,-1\ny,1,0\n", "explanation").unwrap_err();
+        assert_eq!(errors[0].problem, CellProblem::Number);
+        assert_eq!((errors[0].line, errors[0].column), (Some(2), Some(3)));
+        let errors = read_table(&dag, 1, "a,t,f\ny,1,0\ny,1,0\n", "explanation").unwrap_err();
+        assert_eq!(errors[0].problem, CellProblem::DuplicateRow(2));
+        let errors = read_table(&dag, 1, "a,t\n", "explanation").unwrap_err();
+        assert_eq!(errors[0].problem, CellProblem::MissingColumn("f".into()));
+    }
+}
@@ -0,0 +1,300 @@
+use std::fmt::Write;
+
+use roxmltree::{Document, Node};
+
+use super::table::{resolve, rows, Probability};
+use super::{escape_xml, identifiers, node_identifiers, position, Expected, ParseError};
+use crate::graph::{Dag, DeserError, JsonDocument, JsonNode, JsonParent, Metadata, FORMAT_VERSION};
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * The GeNIe `.xdsl` format
+ *
+ * The `nodes` element holds the structure and the tables, the `extensions`
+ * element the labels, descriptions and positions of the nodes as drawn by GeNIe.
+ * Node and state identifiers are restricted to letters, digits and underscores,
+ * so the values of the nodes may be renamed on export.
+ *
+ * Chance nodes are supported, as well as deterministic ones which are turned
+ * into tables. The other kinds of nodes are reported and loaded without their
+ * table.
+ */
+
+fn is_tag(node: &Node, tag: &str) -> bool {
+    node.is_element() && node.tag_name().name() == tag
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| is_tag(c, tag))
+}
+
+fn text(node: Node) -> String {
+    node.text().unwrap_or("").trim().into()
+}
+
+fn line(document: &Document, node: Node) -> usize {
+    document.text_pos_at(node.range().start).row as usize
+}
+
+/// The required attribute `name` of an element
+fn attribute(document: &Document, node: Node, name: &'static str) -> Result<String, ParseError> {
+    node.attribute(name)
+        .map(String::from)
+        .ok_or_else(|| ParseError {
+            line: line(document, node),
+            expected: Expected::Keyword(name),
+            found: Some(format!("<{}>", node.tag_name().name())),
+        })
+}
+
+fn numbers(document: &Document, node: Node) -> Result<Vec<f32>, ParseError> {
+    text(node)
+        .split_whitespace()
+        .map(|word| {
+            word.parse().map_err(|_| ParseError {
+                line: line(document, node),
+                expected: Expected::Number,
+                found: Some(word.into()),
+            })
+        })
+        .collect()
+}
+
+fn variable(
+    document: &Document,
+    node: Node,
+    report: &mut ValidationReport,
+) -> Result<(JsonNode, Option<Probability>), ParseError> {
+    let id = attribute(document, node, "id")?;
+    let mut result = JsonNode {
+        id: Some(id.clone()),
+        label: id.clone(),
+        description: String::new(),
+        values: Vec::new(),
+        parents: Vec::new(),
+        observation: None,
+        credencies: None,
+        cred_description: Vec::new(),
+        sample_sizes: Vec::new(),
+        factor: false,
+        rules: None,
+        position: None,
+    };
+    for state in node.children().filter(|c| is_tag(c, "state")) {
+        result.values.push(attribute(document, state, "id")?);
+    }
+    let parents: Vec<String> = child(node, "parents")
+        .map(|p| text(p).split_whitespace().map(String::from).collect())
+        .unwrap_or_default();
+    let mut block = Probability {
+        line: line(document, node),
+        child: id,
+        parents,
+        table: None,
+        entries: Vec::new(),
+        default: None,
+        explanations: Vec::new(),
+    };
+    match node.tag_name().name() {
+        "cpt" => {
+            if let Some(probabilities) = child(node, "probabilities") {
+                block.table = Some(numbers(document, probabilities)?);
+            }
+        }
+        "deterministic" => {
+            // each configuration of the parents gives one state of the node
+            if let Some(states) = child(node, "resultingstates") {
+                let mut table = Vec::new();
+                for state in text(states).split_whitespace() {
+                    let value = result
+                        .values
+                        .iter()
+                        .position(|v| v == state)
+                        .ok_or_else(|| ParseError {
+                            line: line(document, states),
+                            expected: Expected::ValueOf(result.label.clone()),
+                            found: Some(state.into()),
+                        })?;
+                    table.extend(
+                        (0..result.values.len()).map(|v| if v == value { 1.0 } else { 0.0 }),
+                    );
+                }
+                block.table = Some(table);
+            }
+        }
+        tag => {
+            report.push(
+                result.label.clone(),
+                Problem::Unsupported("type", format!("<{}>", tag)),
+            );
+            // keep the structure, but not the table
+            result.parents = block
+                .parents
+                .iter()
+                .map(|p| JsonParent::Id(p.clone()))
+                .collect();
+            return Ok((result, None));
+        }
+    }
+    Ok((result, Some(block)))
+}
+
+/// Fill the nodes with what GeNIe stores about their drawing
+fn extensions(genie: Node, nodes: &mut [JsonNode], metadata: &mut Metadata) {
+    if let Some(name) = genie.attribute("name") {
+        metadata.title = name.into();
+    }
+    for drawn in genie.descendants().filter(|c| is_tag(c, "node")) {
+        let node = match drawn
+            .attribute("id")
+            .and_then(|id| nodes.iter_mut().find(|n| n.id.as_deref() == Some(id)))
+        {
+            Some(node) => node,
+            None => continue,
+        };
+        if let Some(name) = child(drawn, "name") {
+            node.label = text(name);
+        }
+        if let Some(comment) = child(drawn, "comment") {
+            node.description = comment.text().unwrap_or("").into();
+        }
+        if let Some(position) = child(drawn, "position") {
+            let corners: Vec<f32> = text(position)
+                .split_whitespace()
+                .filter_map(|w| w.parse().ok())
+                .collect();
+            if let [left, top, right, bottom] = corners[..] {
+                node.position = Some(((left + right) / 2.0, (top + bottom) / 2.0));
+            }
+        }
+    }
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let document = Document::parse(text).map_err(DeserError::Xml)?;
+    let root = document.root_element();
+    let syntax = |node: Node, expected| {
+        DeserError::Syntax(ParseError {
+            line: line(&document, node),
+            expected: Expected::Keyword(expected),
+            found: Some(format!("<{}>", node.tag_name().name())),
+        })
+    };
+    if !is_tag(&root, "smile") {
+        return Err(syntax(root, "smile"));
+    }
+    let nodes_element = child(root, "nodes").ok_or_else(|| syntax(root, "nodes"))?;
+
+    let mut metadata = Metadata::default();
+    let mut report = ValidationReport::new();
+    let mut nodes = Vec::new();
+    let mut blocks = Vec::new();
+    for element in nodes_element.children().filter(Node::is_element) {
+        let (node, block) =
+            variable(&document, element, &mut report).map_err(DeserError::Syntax)?;
+        nodes.push(node);
+        blocks.extend(block);
+    }
+    if let Some(genie) = child(root, "extensions").and_then(|e| child(e, "genie")) {
+        extensions(genie, &mut nodes, &mut metadata);
+    }
+    for block in blocks {
+        resolve(block, &mut nodes, &mut report).map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = node_identifiers(dag);
+    let metadata = dag.metadata();
+    let title = if metadata.title.is_empty() {
+        "Network"
+    } else {
+        &metadata.title
+    };
+    let mut out = String::new();
+
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(
+        out,
+        "<smile version=\"1.0\" id=\"{}\" numsamples=\"10000\" discsamples=\"10000\">",
+        identifiers(std::iter::once(title))[0]
+    )
+    .unwrap();
+    writeln!(out, "\t<nodes>").unwrap();
+    // GeNIe requires the parents to be defined before their children
+    let order = dag.topological_order();
+    for &i in &order {
+        let node = dag.get(i).unwrap();
+        writeln!(out, "\t\t<cpt id=\"{}\">", ids[i].as_ref().unwrap()).unwrap();
+        for state in identifiers(node.values.iter().map(|v| &v[..])) {
+            writeln!(out, "\t\t\t<state id=\"{}\" />", state).unwrap();
+        }
+        if !node.parents.is_empty() {
+            let parents = node
+                .parents
+                .iter()
+                .map(|&p| &ids[p].as_ref().unwrap()[..])
+                .collect::<Vec<_>>();
+            writeln!(out, "\t\t\t<parents>{}</parents>", parents.join(" ")).unwrap();
+        }
+        let table = rows(dag, i)
+            .iter()
+            .flat_map(|(_, row)| row.iter().map(|p| p.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(out, "\t\t\t<probabilities>{}</probabilities>", table).unwrap();
+        writeln!(out, "\t\t</cpt>").unwrap();
+    }
+    writeln!(out, "\t</nodes>").unwrap();
+
+    writeln!(out, "\t<extensions>").unwrap();
+    writeln!(
+        out,
+        "\t\t<genie version=\"1.0\" app=\"Bayes-O-Matic\" name=\"{}\">",
+        escape_xml(title)
+    )
+    .unwrap();
+    for &i in &order {
+        let node = dag.get(i).unwrap();
+        let (x, y) = position(dag, i);
+        writeln!(out, "\t\t\t<node id=\"{}\">", ids[i].as_ref().unwrap()).unwrap();
+        writeln!(out, "\t\t\t\t<name>{}</name>", escape_xml(&node.label)).unwrap();
+        writeln!(out, "\t\t\t\t<interior color=\"e5f6f7\" />").unwrap();
+        writeln!(out, "\t\t\t\t<outline color=\"000080\" />").unwrap();
+        writeln!(
+            out,
+            "\t\t\t\t<font color=\"000000\" name=\"Arial\" size=\"8\" />"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "\t\t\t\t<position>{} {} {} {}</position>",
+            (x - 36.0).round(),
+            (y - 18.0).round(),
+            (x + 36.0).round(),
+            (y + 18.0).round()
+        )
+        .unwrap();
+        if !node.description.is_empty() {
+            writeln!(
+                out,
+                "\t\t\t\t<comment>{}</comment>",
+                escape_xml(&node.description)
+            )
+            .unwrap();
+        }
+        writeln!(out, "\t\t\t</node>").unwrap();
+    }
+    writeln!(out, "\t\t</genie>").unwrap();
+    writeln!(out, "\t</extensions>").unwrap();
+    writeln!(out, "</smile>").unwrap();
+    out
+}
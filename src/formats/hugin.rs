@@ -0,0 +1,355 @@
+use std::fmt::Write;
+
+use super::lexer::{tokenize, Comments, Token, Tokens};
+use super::table::{resolve, rows, Probability};
+use super::{node_identifiers, position, quote, Expected, ParseError};
+use crate::graph::{Dag, DeserError, JsonDocument, JsonNode, Metadata, FORMAT_VERSION};
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * The Hugin `.net` format
+ *
+ * Nodes are named by identifiers derived from the identifiers of the nodes,
+ * their label, description and position being attributes. The `data` of the
+ * potentials nests the values of the parents, the values of the node being
+ * innermost.
+ *
+ * Only discrete chance nodes are supported, the other kinds of nodes are
+ * reported and loaded without their potential.
+ */
+
+const COMMENTS: Comments = Comments {
+    line: &["%"],
+    block: false,
+};
+
+#[derive(Debug)]
+enum Value {
+    Word(String),
+    Str(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn text(self) -> Option<String> {
+        match self {
+            Value::Word(w) | Value::Str(w) => Some(w),
+            Value::List(_) => None,
+        }
+    }
+
+    /// All the numbers of a possibly nested list
+    fn numbers(self, line: usize, numbers: &mut Vec<f32>) -> Result<(), ParseError> {
+        match self {
+            Value::Word(w) => match w.parse() {
+                Ok(n) => numbers.push(n),
+                Err(_) => {
+                    return Err(ParseError {
+                        line,
+                        expected: Expected::Number,
+                        found: Some(w),
+                    })
+                }
+            },
+            Value::Str(s) => {
+                return Err(ParseError {
+                    line,
+                    expected: Expected::Number,
+                    found: Some(format!("\"{}\"", s)),
+                })
+            }
+            Value::List(list) => {
+                for value in list {
+                    value.numbers(line, numbers)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn value(tokens: &mut Tokens) -> Result<Value, ParseError> {
+    if tokens.eat_punct('(') {
+        let mut list = Vec::new();
+        while !tokens.eat_punct(')') {
+            list.push(value(tokens)?);
+        }
+        return Ok(Value::List(list));
+    }
+    match tokens.peek() {
+        Some(Token::Word(_)) | Some(Token::Str(_)) => match tokens.next() {
+            Some(Token::Word(w)) => Ok(Value::Word(w)),
+            Some(Token::Str(s)) => Ok(Value::Str(s)),
+            _ => unreachable!(),
+        },
+        _ => Err(tokens.error(Expected::Name)),
+    }
+}
+
+/// The `name = value;` attributes of a block, with their line
+fn attributes(tokens: &mut Tokens) -> Result<Vec<(String, usize, Value)>, ParseError> {
+    let mut attributes = Vec::new();
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        let line = tokens.line();
+        let name = tokens.name()?;
+        tokens.expect_punct('=')?;
+        attributes.push((name, line, value(tokens)?));
+        tokens.expect_punct(';')?;
+    }
+    Ok(attributes)
+}
+
+fn node(
+    tokens: &mut Tokens,
+    kinds: &[String],
+    report: &mut ValidationReport,
+) -> Result<(JsonNode, bool), ParseError> {
+    let id = tokens.name()?;
+    let mut node = JsonNode {
+        id: Some(id.clone()),
+        label: id,
+        description: String::new(),
+        values: Vec::new(),
+        parents: Vec::new(),
+        observation: None,
+        credencies: None,
+        cred_description: Vec::new(),
+        sample_sizes: Vec::new(),
+        factor: false,
+        rules: None,
+        position: None,
+    };
+    for (name, line, value) in attributes(tokens)? {
+        match &name[..] {
+            "label" => node.label = value.text().unwrap_or_default(),
+            "description" => node.description = value.text().unwrap_or_default(),
+            "states" => {
+                if let Value::List(states) = value {
+                    node.values = states.into_iter().filter_map(Value::text).collect();
+                }
+            }
+            "position" => {
+                let mut numbers = Vec::new();
+                value.numbers(line, &mut numbers)?;
+                if let [x, y] = numbers[..] {
+                    node.position = Some((x, y));
+                }
+            }
+            _ => {}
+        }
+    }
+    // `discrete` is the default, and the only supported kind of node
+    let unsupported = kinds
+        .iter()
+        .filter(|k| !k.eq_ignore_ascii_case("discrete"))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !unsupported.is_empty() {
+        report.push(
+            node.label.clone(),
+            Problem::Unsupported("type", format!("{} node", unsupported.join(" "))),
+        );
+    }
+    Ok((node, unsupported.is_empty()))
+}
+
+fn potential(
+    tokens: &mut Tokens,
+    report: &mut ValidationReport,
+) -> Result<Option<Probability>, ParseError> {
+    let line = tokens.line();
+    tokens.expect_punct('(')?;
+    let mut children = Vec::new();
+    while !tokens.is_punct('|') && !tokens.is_punct(')') {
+        children.push(tokens.name()?);
+    }
+    let mut parents = Vec::new();
+    if tokens.eat_punct('|') {
+        while !tokens.is_punct(')') {
+            parents.push(tokens.name()?);
+        }
+    }
+    tokens.expect_punct(')')?;
+    let attributes = attributes(tokens)?;
+    let child = match children.len() {
+        0 => return Err(tokens.error(Expected::Variable)),
+        1 => children.remove(0),
+        _ => {
+            report.push(
+                children.join(" "),
+                Problem::Unsupported("credencies", "joint potential".into()),
+            );
+            return Ok(None);
+        }
+    };
+    let mut table = None;
+    for (name, line, value) in attributes {
+        match &name[..] {
+            "data" => {
+                let mut numbers = Vec::new();
+                value.numbers(line, &mut numbers)?;
+                table = Some(numbers);
+            }
+            "model_nodes" | "model_data" => report.push(
+                child.clone(),
+                Problem::Unsupported("credencies", name.clone()),
+            ),
+            _ => {}
+        }
+    }
+    Ok(Some(Probability {
+        line,
+        child,
+        parents,
+        table,
+        entries: Vec::new(),
+        default: None,
+        explanations: Vec::new(),
+    }))
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let mut tokens = tokenize(text, &COMMENTS).map_err(DeserError::Syntax)?;
+    let mut metadata = Metadata::default();
+    let mut report = ValidationReport::new();
+    let mut nodes = Vec::new();
+    let mut skipped = Vec::new();
+    let mut blocks = Vec::new();
+    while !tokens.is_empty() {
+        if tokens.eat_keyword("net") {
+            for (name, _, value) in attributes(&mut tokens).map_err(DeserError::Syntax)? {
+                let value = value.text().unwrap_or_default();
+                match &name[..] {
+                    "label" => metadata.title = value,
+                    "author" => metadata.author = value,
+                    "question" => metadata.question = value,
+                    "license" => metadata.license = value,
+                    "created" => metadata.created = value,
+                    _ => {}
+                }
+            }
+        } else if tokens.eat_keyword("potential") {
+            if let Some(block) = potential(&mut tokens, &mut report).map_err(DeserError::Syntax)? {
+                blocks.push(block);
+            }
+        } else {
+            let mut kinds = Vec::new();
+            while !tokens.is_keyword("node") {
+                match tokens.peek() {
+                    Some(Token::Word(w)) if kinds.len() < 2 => kinds.push(w.clone()),
+                    _ => return Err(DeserError::Syntax(tokens.error(Expected::Keyword("node")))),
+                }
+                tokens.next();
+            }
+            tokens.next();
+            let (node, supported) =
+                node(&mut tokens, &kinds, &mut report).map_err(DeserError::Syntax)?;
+            if !supported {
+                skipped.push(node.id.clone().unwrap());
+            }
+            nodes.push(node);
+        }
+    }
+    for block in blocks {
+        if skipped.contains(&block.child) {
+            continue;
+        }
+        resolve(block, &mut nodes, &mut report).map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+fn write_attribute(out: &mut String, name: &str, value: &str) {
+    if !value.is_empty() {
+        writeln!(out, "    {} = {};", name, quote(value)).unwrap();
+    }
+}
+
+/// Nest the rows of a table by the values of the parents
+fn nest(rows: &[Vec<f32>], parent_shape: &[usize]) -> String {
+    match parent_shape.split_first() {
+        None => match rows.first() {
+            Some(row) if !row.is_empty() => format!(
+                "( {} )",
+                row.iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            _ => "()".into(),
+        },
+        Some(_) if rows.is_empty() => "()".into(),
+        Some((&n, rest)) => format!(
+            "({})",
+            rows.chunks(rows.len() / n)
+                .map(|chunk| nest(chunk, rest))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+    }
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = node_identifiers(dag);
+    let metadata = dag.metadata();
+    let mut out = String::new();
+
+    writeln!(out, "net\n{{").unwrap();
+    writeln!(out, "    node_size = (80 40);").unwrap();
+    write_attribute(&mut out, "label", &metadata.title);
+    write_attribute(&mut out, "author", &metadata.author);
+    write_attribute(&mut out, "question", &metadata.question);
+    write_attribute(&mut out, "license", &metadata.license);
+    write_attribute(&mut out, "created", &metadata.created);
+    writeln!(out, "}}").unwrap();
+
+    for (i, node) in dag.iter_nodes() {
+        writeln!(out, "\nnode {}\n{{", ids[i].as_ref().unwrap()).unwrap();
+        write_attribute(&mut out, "label", &node.label);
+        let (x, y) = position(dag, i);
+        writeln!(out, "    position = ({} {});", x, y).unwrap();
+        writeln!(
+            out,
+            "    states = ({});",
+            node.values
+                .iter()
+                .map(|v| quote(v))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+        .unwrap();
+        write_attribute(&mut out, "description", &node.description);
+        writeln!(out, "}}").unwrap();
+    }
+
+    for (i, node) in dag.iter_nodes() {
+        let child = ids[i].as_ref().unwrap();
+        if node.parents.is_empty() {
+            writeln!(out, "\npotential ( {} )\n{{", child).unwrap();
+        } else {
+            let parents = node
+                .parents
+                .iter()
+                .map(|&p| &ids[p].as_ref().unwrap()[..])
+                .collect::<Vec<_>>();
+            writeln!(out, "\npotential ( {} | {} )\n{{", child, parents.join(" ")).unwrap();
+        }
+        let rows: Vec<Vec<f32>> = rows(dag, i).into_iter().map(|(_, row)| row).collect();
+        let parent_shape: Vec<usize> = node
+            .parents
+            .iter()
+            .map(|&p| dag.get(p).unwrap().values.len())
+            .collect();
+        writeln!(out, "    data = {};", nest(&rows, &parent_shape)).unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
@@ -0,0 +1,260 @@
+use super::{Expected, ParseError};
+
+/*
+ * A tokenizer shared by the text formats
+ *
+ * Words are runs of alphanumeric characters and of the few symbols that appear
+ * in names and numbers, but stop at arrows `->`, which are two punctuation
+ * tokens. Strings are delimited by double quotes and may contain escaped
+ * characters. Every other character is a punctuation token.
+ */
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Word(String),
+    Str(String),
+    Punct(char),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match *self {
+            Token::Word(ref w) => w.clone(),
+            Token::Str(ref s) => format!("\"{}\"", s),
+            Token::Punct(c) => c.to_string(),
+        }
+    }
+}
+
+/// The comment styles of a format
+pub struct Comments {
+    pub line: &'static [&'static str],
+    // C-style `/* ... */` comments
+    pub block: bool,
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '+' || c == '.'
+}
+
+pub fn tokenize(text: &str, comments: &Comments) -> Result<Tokens, ParseError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\n' {
+            line += 1;
+            rest = &rest[1..];
+        } else if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if let Some(prefix) = comments.line.iter().find(|p| rest.starts_with(*p)) {
+            rest = &rest[prefix.len()..];
+            rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+        } else if comments.block && rest.starts_with("/*") {
+            let end = match rest.find("*/") {
+                Some(end) => end + 2,
+                None => {
+                    return Err(ParseError {
+                        line,
+                        expected: Expected::Punct('/'),
+                        found: None,
+                    })
+                }
+            };
+            line += rest[..end].matches('\n').count();
+            rest = &rest[end..];
+        } else if c == '"' {
+            let start_line = line;
+            let mut string = String::new();
+            let unterminated = ParseError {
+                line: start_line,
+                expected: Expected::Punct('"'),
+                found: None,
+            };
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => string.push('\n'),
+                        Some((_, 't')) => string.push('\t'),
+                        Some((_, c)) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c)
+                        }
+                        None => return Err(unterminated),
+                    },
+                    Some((_, c)) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        string.push(c)
+                    }
+                    None => return Err(unterminated),
+                }
+            };
+            tokens.push((Token::Str(string), start_line));
+            rest = &rest[end..];
+        } else if rest.starts_with("->") {
+            tokens.push((Token::Punct('-'), line));
+            tokens.push((Token::Punct('>'), line));
+            rest = &rest[2..];
+        } else if is_word_char(c) {
+            let end = rest
+                .char_indices()
+                .find(|&(i, c)| !is_word_char(c) || rest[i..].starts_with("->"))
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            tokens.push((Token::Word(rest[..end].into()), line));
+            rest = &rest[end..];
+        } else {
+            tokens.push((Token::Punct(c), line));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(Tokens {
+        tokens,
+        pos: 0,
+        last_line: line,
+    })
+}
+
+pub struct Tokens {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    // the line of the end of the file
+    last_line: usize,
+}
+
+impl Tokens {
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    pub fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|t| t.0.clone());
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// The line of the next token
+    pub fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.1)
+            .unwrap_or(self.last_line)
+    }
+
+    /// An error about the next token
+    pub fn error(&self, expected: Expected) -> ParseError {
+        ParseError {
+            line: self.line(),
+            expected,
+            found: self.peek().map(Token::describe),
+        }
+    }
+
+    pub fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    pub fn eat_punct(&mut self, c: char) -> bool {
+        let found = self.is_punct(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_punct(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            Err(self.error(Expected::Punct(c)))
+        }
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) => w.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &'static str) -> Result<(), ParseError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(Expected::Keyword(keyword)))
+        }
+    }
+
+    /// A name, either a word or a string
+    pub fn name(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Word(w)) | Some(Token::Str(w)) => {
+                let name = w.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error(Expected::Name)),
+        }
+    }
+
+    pub fn number(&mut self) -> Result<f32, ParseError> {
+        match self.peek() {
+            Some(Token::Word(w)) => match w.parse() {
+                Ok(n) => {
+                    self.pos += 1;
+                    Ok(n)
+                }
+                Err(_) => Err(self.error(Expected::Number)),
+            },
+            _ => Err(self.error(Expected::Number)),
+        }
+    }
+
+    pub fn integer(&mut self) -> Result<usize, ParseError> {
+        match self.peek() {
+            Some(Token::Word(w)) => match w.parse() {
+                Ok(n) => {
+                    self.pos += 1;
+                    Ok(n)
+                }
+                Err(_) => Err(self.error(Expected::Number)),
+            },
+            _ => Err(self.error(Expected::Number)),
+        }
+    }
+
+    /// Skip tokens up to and including the given punctuation, keeping track of
+    /// nested braces
+    pub fn skip_past(&mut self, c: char) -> Result<(), ParseError> {
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some(Token::Punct(p)) if p == c && depth == 0 => return Ok(()),
+                Some(Token::Punct('{')) => depth += 1,
+                Some(Token::Punct('}')) if depth > 0 => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error(Expected::Punct(c))),
+            }
+        }
+    }
+}
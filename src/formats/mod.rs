@@ -0,0 +1,251 @@
+use crate::graph::{Dag, DeserError, Float};
+use crate::validation::ValidationReport;
+
+/*
+ * File formats
+ *
+ * Besides its own JSON format, the app can exchange models with other tools.
+ * Each reader converts its input into the same document as the JSON format,
+ * so that all formats go through the same validation when loaded.
+ */
+
+pub mod bif;
+pub mod csv;
+pub mod genie;
+pub mod hugin;
+mod lexer;
+mod table;
+pub mod text;
+pub mod uai;
+pub mod xmlbif;
+pub mod yaml;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Bif,
+    XmlBif,
+    Hugin,
+    Genie,
+    Uai,
+    Text,
+}
+
+impl Format {
+    pub const ALL: &'static [Format] = &[
+        Format::Json,
+        Format::Yaml,
+        Format::Bif,
+        Format::XmlBif,
+        Format::Hugin,
+        Format::Genie,
+        Format::Uai,
+        Format::Text,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Bif => "BIF",
+            Format::XmlBif => "XMLBIF",
+            Format::Hugin => "Hugin",
+            Format::Genie => "GeNIe",
+            Format::Uai => "UAI",
+            Format::Text => "Text",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Bif => "bif",
+            Format::XmlBif => "xml",
+            Format::Hugin => "net",
+            Format::Genie => "xdsl",
+            Format::Uai => "uai",
+            Format::Text => "txt",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Yaml => "application/yaml",
+            Format::Bif | Format::Hugin | Format::Uai | Format::Text => "text/plain",
+            Format::XmlBif | Format::Genie => "application/xml",
+        }
+    }
+
+    /// Guess the format of a file from its name
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        let extension = filename.rsplit('.').next()?.to_lowercase();
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|f| f.extension() == extension)
+    }
+
+    /// The `accept` attribute of the file input for loading models, which also
+    /// loads UAI evidence files
+    pub fn accept() -> String {
+        Format::ALL
+            .iter()
+            .map(|f| f.extension())
+            .chain(std::iter::once(uai::EVIDENCE_EXTENSION))
+            .map(|e| format!(".{}", e))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    pub fn read(self, text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+        match self {
+            Format::Json => Dag::from_json(text),
+            Format::Yaml => yaml::read(text),
+            Format::Bif => bif::read(text),
+            Format::XmlBif => xmlbif::read(text),
+            Format::Hugin => hugin::read(text),
+            Format::Genie => genie::read(text),
+            Format::Uai => uai::read(text),
+            Format::Text => text::read(text),
+        }
+    }
+
+    pub fn write(self, dag: &Dag) -> String {
+        match self {
+            Format::Json => dag.to_json(),
+            Format::Yaml => yaml::write(dag),
+            Format::Bif => bif::write(dag),
+            Format::XmlBif => xmlbif::write(dag),
+            Format::Hugin => hugin::write(dag),
+            Format::Genie => genie::write(dag),
+            Format::Uai => uai::write(dag),
+            Format::Text => text::write(dag),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected {
+    Punct(char),
+    Keyword(&'static str),
+    Name,
+    Number,
+    // a variable declared earlier in the file
+    Variable,
+    // one of the values of the given variable
+    ValueOf(String),
+}
+
+/// An error in the syntax of a file, that prevents reading it at all
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub expected: Expected,
+    // `None` at the end of the file
+    pub found: Option<String>,
+}
+
+/// Convert the rows of a table, one for each configuration of parent values in
+/// the order of `parent_configurations`, into the flat credencies of a document,
+/// where the value of the node varies the slowest.
+///
+/// All rows must have the same length.
+pub(crate) fn flatten_rows(rows: &[Vec<f32>]) -> Vec<Float> {
+    let value_count = rows.first().map(|r| r.len()).unwrap_or(0);
+    (0..value_count)
+        .flat_map(|v| rows.iter().map(move |row| Float::from(row[v])))
+        .collect()
+}
+
+/// Whether a name can be written as is, or must be quoted
+pub(crate) fn is_plain_word(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(lexer::is_word_char) && !name.contains("->")
+}
+
+/// Quote a string, escaping the characters that need it
+pub(crate) fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escape a text to be written in an XML element or attribute
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Turn names into identifiers made of letters, digits and underscores and not
+/// starting with a digit, as required by some formats, keeping them distinct
+pub(crate) fn identifiers<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut identifiers: Vec<String> = Vec::new();
+    for name in names {
+        let mut base: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            base.insert(0, 'n');
+        }
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while identifiers.contains(&candidate) {
+            candidate = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        identifiers.push(candidate);
+    }
+    identifiers
+}
+
+/// The identifiers of all nodes as returned by `identifiers`, indexed like the
+/// nodes of the model
+pub(crate) fn node_identifiers(dag: &Dag) -> Vec<Option<String>> {
+    let ids = dag.node_ids();
+    let mut identifiers = identifiers(ids.iter().flatten().map(|id| &id[..])).into_iter();
+    ids.iter()
+        .map(|id| id.as_ref().and_then(|_| identifiers.next()))
+        .collect()
+}
+
+/// Where to draw a node in other tools, laying out the nodes without a position
+/// on a grid
+pub(crate) fn position(dag: &Dag, node: usize) -> (f32, f32) {
+    dag.get(node).and_then(|n| n.position).unwrap_or_else(|| {
+        let i = dag.iter_nodes().position(|(id, _)| id == node).unwrap_or(0);
+        (
+            100.0 + 150.0 * (i % 5) as f32,
+            100.0 + 100.0 * (i / 5) as f32,
+        )
+    })
+}
+
+/// Write a name as is if possible, quoted otherwise
+pub(crate) fn name(name: &str) -> String {
+    if is_plain_word(name) {
+        name.into()
+    } else {
+        quote(name)
+    }
+}
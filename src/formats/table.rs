@@ -0,0 +1,204 @@
+use super::{flatten_rows, Expected, ParseError};
+use crate::graph::{Dag, JsonNode, JsonParent, JsonRule, JsonRuleTable};
+use crate::rules::parent_configurations;
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * Probability tables of the interchange formats
+ *
+ * Most formats list the rows of the tables with the values of the node varying
+ * the fastest, the configurations of the parents following the order of
+ * `parent_configurations`. Row explanations are attached to configurations.
+ */
+
+/// A probability table as read from a file, referring to variables and values
+/// by their names. It is resolved once all the variables are known.
+pub(crate) struct Probability {
+    pub(crate) line: usize,
+    pub(crate) child: String,
+    pub(crate) parents: Vec<String>,
+    pub(crate) table: Option<Vec<f32>>,
+    pub(crate) entries: Vec<(usize, Vec<String>, Vec<f32>)>,
+    pub(crate) default: Option<Vec<f32>>,
+    pub(crate) explanations: Vec<(usize, Option<Vec<String>>, String)>,
+}
+
+/// Find the configuration of parent values named in a row
+fn configuration(
+    line: usize,
+    names: &[String],
+    parents: &[&JsonNode],
+) -> Result<Vec<usize>, ParseError> {
+    if names.len() != parents.len() {
+        return Err(ParseError {
+            line,
+            expected: Expected::Punct(if names.len() < parents.len() {
+                ','
+            } else {
+                ')'
+            }),
+            found: Some(names.join(", ")),
+        });
+    }
+    names
+        .iter()
+        .zip(parents.iter())
+        .map(|(name, parent)| {
+            parent
+                .values
+                .iter()
+                .position(|v| v == name)
+                .ok_or_else(|| ParseError {
+                    line,
+                    expected: Expected::ValueOf(parent.label.clone()),
+                    found: Some(name.clone()),
+                })
+        })
+        .collect()
+}
+
+/// Fill a node from its probability table.
+///
+/// Rows can be given as a full table, listed individually, or both, the listed
+/// rows taking precedence. When some rows are missing or a default row is given,
+/// the node is described by rules, the listed rows overriding the default one.
+pub(crate) fn resolve(
+    block: Probability,
+    nodes: &mut [JsonNode],
+    report: &mut ValidationReport,
+) -> Result<(), ParseError> {
+    let find = |name: &str| nodes.iter().position(|n| n.id.as_deref() == Some(name));
+    let child = find(&block.child).ok_or_else(|| ParseError {
+        line: block.line,
+        expected: Expected::Variable,
+        found: Some(block.child.clone()),
+    })?;
+    let parents = block
+        .parents
+        .iter()
+        .map(|p| find(p))
+        .collect::<Option<Vec<usize>>>();
+    nodes[child].parents = block
+        .parents
+        .iter()
+        .map(|p| JsonParent::Id(p.clone()))
+        .collect();
+    let parents = match parents {
+        Some(parents) => parents,
+        // the unknown parents are reported when building the model
+        None => return Ok(()),
+    };
+    let parent_nodes: Vec<&JsonNode> = parents.iter().map(|&p| &nodes[p]).collect();
+    let parent_shape: Vec<usize> = parent_nodes.iter().map(|p| p.values.len()).collect();
+    let value_count = nodes[child].values.len();
+    let label = nodes[child].label.clone();
+
+    let mut explanations = Vec::new();
+    let mut default_description = String::new();
+    for (line, context, text) in block.explanations {
+        match context {
+            Some(ref names) if !parents.is_empty() => {
+                explanations.push((configuration(line, names, &parent_nodes)?, text))
+            }
+            _ => default_description = text,
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (line, names, row) in block.entries {
+        let configuration = configuration(line, &names, &parent_nodes)?;
+        if row.len() == value_count {
+            rows.push((configuration, row));
+        } else {
+            report.push(
+                label.clone(),
+                Problem::CredenciesLength {
+                    expected: value_count,
+                    found: row.len(),
+                },
+            );
+        }
+    }
+    if let Some(table) = block.table {
+        if table.len() != value_count * parent_shape.iter().product::<usize>() {
+            // let the validation report the wrong length
+            nodes[child].credencies = Some(table.into_iter().map(Into::into).collect());
+            return Ok(());
+        }
+        for (configuration, row) in
+            parent_configurations(&parent_shape).zip(table.chunks(value_count.max(1)))
+        {
+            if !rows.iter().any(|r| r.0 == configuration) {
+                rows.push((configuration, row.to_vec()));
+            }
+        }
+    }
+    let explanation = |configuration: &[usize]| {
+        explanations
+            .iter()
+            .find(|e| e.0 == configuration)
+            .map(|e| e.1.clone())
+            .unwrap_or_default()
+    };
+
+    let node = &mut nodes[child];
+    let configurations: Vec<Vec<usize>> = parent_configurations(&parent_shape).collect();
+    let complete = configurations
+        .iter()
+        .all(|c| rows.iter().any(|r| r.0 == *c));
+    if complete && block.default.is_none() {
+        let full_rows: Vec<Vec<f32>> = configurations
+            .iter()
+            .map(|c| rows.iter().find(|r| r.0 == *c).unwrap().1.clone())
+            .collect();
+        node.credencies = Some(flatten_rows(&full_rows));
+        if parents.is_empty() {
+            node.cred_description = vec![default_description];
+        } else {
+            node.cred_description = configurations.iter().map(|c| explanation(c)).collect();
+        }
+        if node.cred_description.iter().all(|d| d.is_empty()) {
+            node.cred_description.clear();
+        }
+    } else if !rows.is_empty() || block.default.is_some() {
+        // rows that are not listed take the default row, as rules do
+        node.rules = Some(JsonRuleTable {
+            rules: rows
+                .into_iter()
+                .map(|(configuration, row)| JsonRule {
+                    description: explanation(&configuration),
+                    when: configuration.into_iter().map(Some).collect(),
+                    credencies: row.into_iter().map(Into::into).collect(),
+                })
+                .collect(),
+            default: block
+                .default
+                .unwrap_or_else(|| vec![1.0; value_count])
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            default_description,
+        });
+    }
+    Ok(())
+}
+
+/// The rows of the normalized table of a node, with their configuration of
+/// parent values
+pub(crate) fn rows(dag: &Dag, node: usize) -> Vec<(Vec<usize>, Vec<f32>)> {
+    let credencies = dag.normalized_credencies(node).unwrap();
+    let shape = credencies.shape().to_vec();
+    parent_configurations(&shape[1..])
+        .map(|configuration| {
+            let mut idx = vec![0];
+            idx.extend(configuration.iter().copied());
+            let row = (0..shape[0])
+                .map(|v| {
+                    idx[0] = v;
+                    credencies[ndarray::IxDyn(&idx)]
+                })
+                .collect();
+            (configuration, row)
+        })
+        .collect()
+}
@@ -0,0 +1,686 @@
+use std::fmt::Write;
+
+use ndarray::{ArrayD, IxDyn};
+
+use super::lexer::{tokenize, Comments, Token, Tokens};
+use super::{flatten_rows, name, quote, Expected, ParseError};
+use crate::graph::{
+    Dag, DeserError, JsonDocument, JsonNode, JsonParent, JsonRule, JsonRuleTable, Metadata,
+    FORMAT_VERSION,
+};
+use crate::rules::parent_configurations;
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * A text language to write models by hand
+ *
+ *     title "Sprinkler"
+ *
+ *     node rain {
+ *         label "Does it rain?"
+ *         values yes, no
+ *         sample_sizes 30
+ *     }
+ *
+ *     rain, sprinkler -> wet
+ *
+ *     table rain { 0.2, 0.8 "the explanation of the row" }
+ *     table wet {
+ *         (yes, on): 0.99, 0.01
+ *         (*, off): 0.5, 0.5
+ *         default: 0.1, 0.9
+ *     }
+ *
+ * Edges list the parents of a node in order, and the rows of the tables name
+ * the values of the parents in the same order. A table listing every row once
+ * is a full table, otherwise it is read as rules: `*` matches any value, and
+ * the rows are tried in order before the default one.
+ *
+ * The sample sizes of a node give how many cases each row of its table is
+ * worth, in the order of the rows. A node marked `factor` stands for a factor of
+ * a Markov network, and keeps its observation when loading evidence.
+ */
+
+const COMMENTS: Comments = Comments {
+    line: &["#"],
+    block: false,
+};
+
+const METADATA: &[&str] = &["title", "question", "author", "license", "created"];
+
+/// Write a node name, quoting it if it could be mistaken for a keyword
+fn node_name(id: &str) -> String {
+    if METADATA
+        .iter()
+        .chain(&["node", "table"])
+        .any(|k| k.eq_ignore_ascii_case(id))
+    {
+        quote(id)
+    } else {
+        name(id)
+    }
+}
+
+struct Row {
+    line: usize,
+    // the values of the parents, `None` for the default row
+    context: Option<Vec<Option<String>>>,
+    credencies: Vec<f32>,
+    explanation: String,
+}
+
+struct Table {
+    line: usize,
+    child: String,
+    rows: Vec<Row>,
+}
+
+/// A list of names separated by commas, with their line
+fn names(tokens: &mut Tokens) -> Result<Vec<(usize, String)>, ParseError> {
+    let mut names = vec![(tokens.line(), tokens.name()?)];
+    while tokens.eat_punct(',') {
+        names.push((tokens.line(), tokens.name()?));
+    }
+    Ok(names)
+}
+
+fn numbers(tokens: &mut Tokens) -> Result<Vec<f32>, ParseError> {
+    let mut numbers = vec![tokens.number()?];
+    while tokens.eat_punct(',') {
+        numbers.push(tokens.number()?);
+    }
+    Ok(numbers)
+}
+
+fn eat_arrow(tokens: &mut Tokens) -> Result<bool, ParseError> {
+    if tokens.eat_punct('-') {
+        tokens.expect_punct('>')?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+fn node(tokens: &mut Tokens) -> Result<JsonNode, ParseError> {
+    let id = tokens.name()?;
+    let mut node = JsonNode {
+        id: Some(id.clone()),
+        label: id,
+        description: String::new(),
+        values: Vec::new(),
+        parents: Vec::new(),
+        observation: None,
+        credencies: None,
+        cred_description: Vec::new(),
+        sample_sizes: Vec::new(),
+        factor: false,
+        rules: None,
+        position: None,
+    };
+    let mut observed = None;
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        if tokens.eat_keyword("label") {
+            node.label = tokens.name()?;
+        } else if tokens.eat_keyword("values") {
+            node.values = names(tokens)?.into_iter().map(|(_, v)| v).collect();
+        } else if tokens.eat_keyword("observed") {
+            observed = Some((tokens.line(), tokens.name()?));
+        } else if tokens.eat_keyword("description") {
+            node.description = tokens.name()?;
+        } else if tokens.eat_keyword("position") {
+            let x = tokens.number()?;
+            tokens.expect_punct(',')?;
+            node.position = Some((x, tokens.number()?));
+        } else if tokens.eat_keyword("sample_sizes") {
+            node.sample_sizes = numbers(tokens)?;
+        } else if tokens.eat_keyword("factor") {
+            node.factor = true;
+        } else {
+            return Err(tokens.error(Expected::Keyword("values")));
+        }
+    }
+    // the values may come after the observation
+    if let Some((line, value)) = observed {
+        match node.values.iter().position(|v| *v == value) {
+            Some(i) => node.observation = Some(i),
+            None => {
+                return Err(ParseError {
+                    line,
+                    expected: Expected::ValueOf(node.label),
+                    found: Some(value),
+                })
+            }
+        }
+    }
+    Ok(node)
+}
+
+fn row(tokens: &mut Tokens) -> Result<Row, ParseError> {
+    let line = tokens.line();
+    let context = if tokens.eat_keyword("default") {
+        tokens.expect_punct(':')?;
+        None
+    } else if tokens.eat_punct('(') {
+        let mut context = Vec::new();
+        while !tokens.eat_punct(')') {
+            if !context.is_empty() {
+                tokens.expect_punct(',')?;
+            }
+            if tokens.eat_punct('*') {
+                context.push(None);
+            } else {
+                context.push(Some(tokens.name()?));
+            }
+        }
+        tokens.expect_punct(':')?;
+        Some(context)
+    } else {
+        // the single row of a node without parents
+        Some(Vec::new())
+    };
+    let credencies = numbers(tokens)?;
+    let explanation = match tokens.peek() {
+        Some(Token::Str(_)) => tokens.name()?,
+        _ => String::new(),
+    };
+    Ok(Row {
+        line,
+        context,
+        credencies,
+        explanation,
+    })
+}
+
+fn table(tokens: &mut Tokens) -> Result<Table, ParseError> {
+    let line = tokens.line();
+    let child = tokens.name()?;
+    let mut rows = Vec::new();
+    tokens.expect_punct('{')?;
+    while !tokens.eat_punct('}') {
+        rows.push(row(tokens)?);
+    }
+    Ok(Table { line, child, rows })
+}
+
+/// Find the values of the parents named in a row
+fn context(
+    line: usize,
+    names: &[Option<String>],
+    parents: &[(String, Vec<String>)],
+) -> Result<Vec<Option<usize>>, ParseError> {
+    if names.len() != parents.len() {
+        return Err(ParseError {
+            line,
+            expected: Expected::Punct(if names.len() < parents.len() {
+                ','
+            } else {
+                ')'
+            }),
+            found: Some(
+                names
+                    .iter()
+                    .map(|n| n.as_deref().unwrap_or("*"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        });
+    }
+    names
+        .iter()
+        .zip(parents.iter())
+        .map(|(name, (label, values))| match name {
+            None => Ok(None),
+            Some(name) => match values.iter().position(|v| v == name) {
+                Some(v) => Ok(Some(v)),
+                None => Err(ParseError {
+                    line,
+                    expected: Expected::ValueOf(label.clone()),
+                    found: Some(name.clone()),
+                }),
+            },
+        })
+        .collect()
+}
+
+/// Fill a node from its table, given the indices of its parents
+fn resolve(
+    table: Table,
+    child: usize,
+    parents: &[usize],
+    nodes: &mut [JsonNode],
+    report: &mut ValidationReport,
+) -> Result<(), ParseError> {
+    let parents: Vec<(String, Vec<String>)> = parents
+        .iter()
+        .map(|&p| (nodes[p].label.clone(), nodes[p].values.clone()))
+        .collect();
+    let parent_shape: Vec<usize> = parents.iter().map(|p| p.1.len()).collect();
+    let node = &mut nodes[child];
+    let value_count = node.values.len();
+
+    let mut rules = Vec::new();
+    let mut default = None;
+    for row in table.rows {
+        if row.credencies.len() != value_count {
+            report.push(
+                node.label.clone(),
+                Problem::CredenciesLength {
+                    expected: value_count,
+                    found: row.credencies.len(),
+                },
+            );
+            continue;
+        }
+        match row.context {
+            None => default = Some((row.credencies, row.explanation)),
+            Some(ref names) if names.is_empty() && !parents.is_empty() => {
+                return Err(ParseError {
+                    line: row.line,
+                    expected: Expected::Punct('('),
+                    found: row.credencies.first().map(|p| p.to_string()),
+                })
+            }
+            Some(names) => rules.push((
+                context(row.line, &names, &parents)?,
+                row.credencies,
+                row.explanation,
+            )),
+        }
+    }
+
+    let configurations: Vec<Vec<usize>> = parent_configurations(&parent_shape).collect();
+    let matching = |configuration: &[usize]| {
+        rules
+            .iter()
+            .filter(|r| {
+                r.0.iter()
+                    .zip(configuration)
+                    .all(|(v, c)| v.map(|v| v == *c).unwrap_or(true))
+            })
+            .collect::<Vec<_>>()
+    };
+    // a full table lists every configuration exactly once
+    let full = default.is_none()
+        && rules.len() == configurations.len()
+        && configurations.iter().all(|c| matching(c).len() == 1);
+    if full {
+        let rows: Vec<_> = configurations.iter().map(|c| matching(c)[0]).collect();
+        node.credencies = Some(flatten_rows(
+            &rows.iter().map(|r| r.1.clone()).collect::<Vec<_>>(),
+        ));
+        node.cred_description = rows.iter().map(|r| r.2.clone()).collect();
+        if node.cred_description.iter().all(|d| d.is_empty()) {
+            node.cred_description.clear();
+        }
+    } else if !rules.is_empty() || default.is_some() {
+        let (default, default_description) =
+            default.unwrap_or_else(|| (vec![1.0; value_count], String::new()));
+        node.rules = Some(JsonRuleTable {
+            rules: rules
+                .into_iter()
+                .map(|(when, credencies, description)| JsonRule {
+                    when,
+                    credencies: credencies.into_iter().map(Into::into).collect(),
+                    description,
+                })
+                .collect(),
+            default: default.into_iter().map(Into::into).collect(),
+            default_description,
+        });
+    }
+    Ok(())
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let mut tokens = tokenize(text, &COMMENTS).map_err(DeserError::Syntax)?;
+    let mut metadata = Metadata::default();
+    let mut report = ValidationReport::new();
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut tables = Vec::new();
+    while !tokens.is_empty() {
+        if let Some(&keyword) = METADATA.iter().find(|k| tokens.is_keyword(k)) {
+            tokens.next();
+            let value = tokens.name().map_err(DeserError::Syntax)?;
+            match keyword {
+                "title" => metadata.title = value,
+                "question" => metadata.question = value,
+                "author" => metadata.author = value,
+                "license" => metadata.license = value,
+                _ => metadata.created = value,
+            }
+        } else if tokens.eat_keyword("node") {
+            nodes.push(node(&mut tokens).map_err(DeserError::Syntax)?);
+        } else if tokens.eat_keyword("table") {
+            tables.push(table(&mut tokens).map_err(DeserError::Syntax)?);
+        } else {
+            // edges, possibly chained as in `a -> b -> c`
+            let mut parents = names(&mut tokens).map_err(DeserError::Syntax)?;
+            if !eat_arrow(&mut tokens).map_err(DeserError::Syntax)? {
+                return Err(DeserError::Syntax(tokens.error(Expected::Keyword("->"))));
+            }
+            loop {
+                let children = names(&mut tokens).map_err(DeserError::Syntax)?;
+                for child in &children {
+                    for parent in &parents {
+                        edges.push((parent.clone(), child.clone()));
+                    }
+                }
+                parents = children;
+                if !eat_arrow(&mut tokens).map_err(DeserError::Syntax)? {
+                    break;
+                }
+            }
+        }
+    }
+
+    let find = |nodes: &[JsonNode], (line, name): &(usize, String)| {
+        nodes
+            .iter()
+            .position(|n| n.id.as_ref() == Some(name))
+            .ok_or_else(|| {
+                DeserError::Syntax(ParseError {
+                    line: *line,
+                    expected: Expected::Variable,
+                    found: Some(name.clone()),
+                })
+            })
+    };
+    let mut parents = vec![Vec::new(); nodes.len()];
+    for (parent, child) in edges {
+        let parent_id = find(&nodes, &parent)?;
+        let child_id = find(&nodes, &child)?;
+        nodes[child_id].parents.push(JsonParent::Id(parent.1));
+        if !parents[child_id].contains(&parent_id) {
+            parents[child_id].push(parent_id);
+        }
+    }
+    let mut filled = Vec::new();
+    for table in tables {
+        let child = find(&nodes, &(table.line, table.child.clone()))?;
+        if filled.contains(&child) {
+            report.push(
+                nodes[child].label.clone(),
+                Problem::Unsupported("credencies", "several tables".into()),
+            );
+            continue;
+        }
+        filled.push(child);
+        resolve(table, child, &parents[child], &mut nodes, &mut report)
+            .map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+/// The rows of a credency table as entered, with their configuration of parent
+/// values
+fn raw_rows(credencies: &ArrayD<f32>) -> Vec<(Vec<usize>, Vec<f32>)> {
+    let shape = credencies.shape();
+    parent_configurations(&shape[1..])
+        .map(|configuration| {
+            let mut idx = vec![0];
+            idx.extend(configuration.iter().copied());
+            let row = (0..shape[0])
+                .map(|v| {
+                    idx[0] = v;
+                    credencies[IxDyn(&idx)]
+                })
+                .collect();
+            (configuration, row)
+        })
+        .collect()
+}
+
+fn write_row(out: &mut String, context: Option<Vec<String>>, row: &[f32], explanation: &str) {
+    match context {
+        None => write!(out, "    default: ").unwrap(),
+        Some(ref values) if values.is_empty() => write!(out, "    ").unwrap(),
+        Some(values) => write!(out, "    ({}): ", values.join(", ")).unwrap(),
+    }
+    write!(
+        out,
+        "{}",
+        row.iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+    .unwrap();
+    if !explanation.is_empty() {
+        write!(out, " {}", quote(explanation)).unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = dag.node_ids();
+    let metadata = dag.metadata();
+    let mut out = String::new();
+
+    for (&keyword, value) in METADATA.iter().zip(&[
+        &metadata.title,
+        &metadata.question,
+        &metadata.author,
+        &metadata.license,
+        &metadata.created,
+    ]) {
+        if !value.is_empty() {
+            writeln!(out, "{} {}", keyword, quote(value)).unwrap();
+        }
+    }
+
+    for (i, node) in dag.iter_nodes() {
+        let id = ids[i].as_ref().unwrap();
+        if !out.is_empty() {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "node {} {{", node_name(id)).unwrap();
+        if node.label != *id {
+            writeln!(out, "    label {}", quote(&node.label)).unwrap();
+        }
+        // a new node has no values yet, and then no `values` line
+        if !node.values.is_empty() {
+            writeln!(
+                out,
+                "    values {}",
+                node.values
+                    .iter()
+                    .map(|v| name(v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .unwrap();
+        }
+        if let Some(obs) = node.observation {
+            writeln!(out, "    observed {}", name(&node.values[obs])).unwrap();
+        }
+        if !node.description.is_empty() {
+            writeln!(out, "    description {}", quote(&node.description)).unwrap();
+        }
+        if let Some((x, y)) = node.position {
+            writeln!(out, "    position {}, {}", x, y).unwrap();
+        }
+        if node.factor {
+            writeln!(out, "    factor").unwrap();
+        }
+        if !node.sample_sizes.is_empty() {
+            writeln!(
+                out,
+                "    sample_sizes {}",
+                node.sample_sizes
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    let mut edges = dag
+        .iter_nodes()
+        .filter(|(_, node)| !node.parents.is_empty())
+        .peekable();
+    if edges.peek().is_some() {
+        writeln!(out).unwrap();
+    }
+    for (i, node) in edges {
+        let parents = node
+            .parents
+            .iter()
+            .map(|&p| node_name(ids[p].as_ref().unwrap()))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "{} -> {}",
+            parents.join(", "),
+            node_name(ids[i].as_ref().unwrap())
+        )
+        .unwrap();
+    }
+
+    for (i, node) in dag.iter_nodes() {
+        let value_name = |parent: usize, value: Option<usize>| match value {
+            Some(v) => name(&dag.get(parent).unwrap().values[v]),
+            None => "*".into(),
+        };
+        if let Some(ref rules) = node.rules {
+            writeln!(out, "\ntable {} {{", node_name(ids[i].as_ref().unwrap())).unwrap();
+            for rule in &rules.rules {
+                let context = node
+                    .parents
+                    .iter()
+                    .zip(&rule.context)
+                    .map(|(&p, &v)| value_name(p, v))
+                    .collect();
+                write_row(&mut out, Some(context), &rule.credencies, &rule.description);
+            }
+            write_row(&mut out, None, &rules.default, &rules.default_description);
+            writeln!(out, "}}").unwrap();
+        } else if let Some(ref credencies) = node.credencies {
+            writeln!(out, "\ntable {} {{", node_name(ids[i].as_ref().unwrap())).unwrap();
+            for (r, (configuration, row)) in raw_rows(credencies).into_iter().enumerate() {
+                let context = node
+                    .parents
+                    .iter()
+                    .zip(configuration)
+                    .map(|(&p, v)| value_name(p, Some(v)))
+                    .collect();
+                let explanation = node.cred_description.get(r).map(|d| &d[..]).unwrap_or("");
+                write_row(&mut out, Some(context), &row, explanation);
+            }
+            writeln!(out, "}}").unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPRINKLER: &str = r#"title "Sprinkler"
+author "Someone"
+
+node rain {
+    label "Does it rain?"
+    values yes, no
+    observed no
+    description "The \"weather\""
+    position 10, 20
+    sample_sizes 30
+}
+
+node sprinkler {
+    values on, off
+    factor
+}
+
+node wet {
+    values "very wet", dry
+}
+
+node table {
+    values a, b
+}
+
+rain -> sprinkler
+rain, sprinkler -> wet
+
+table rain { 0.2, 0.8 "rain is rare" }
+table sprinkler {
+    (yes): 0.01, 0.99
+    (no): 0.4, 0.6 "dry days"
+}
+table wet {
+    (yes, on): 0.99, 0.01
+    (*, off): 0.5, 0.5
+    default: 0.1, 0.9
+}
+"#;
+
+    fn round_trip(dag: &Dag) -> Dag {
+        let (read_back, report) = read(&write(dag)).unwrap();
+        assert!(!report.has_errors());
+        read_back
+    }
+
+    #[test]
+    fn reads_the_example() {
+        let (dag, report) = read(SPRINKLER).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(dag.metadata().title, "Sprinkler");
+        let rain = dag.get(0).unwrap();
+        assert_eq!(rain.label, "Does it rain?");
+        assert_eq!(rain.observation, Some(1));
+        assert_eq!(rain.sample_sizes, vec![30.0]);
+        assert!(dag.get(1).unwrap().factor);
+        let wet = dag.get(2).unwrap();
+        assert_eq!(wet.parents, vec![0, 1]);
+        assert_eq!(wet.values, vec!["very wet".to_string(), "dry".to_string()]);
+        let rules = wet.rules.as_ref().unwrap();
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.rules[1].context, vec![None, Some(1)]);
+        assert_eq!(rules.default, vec![0.1, 0.9]);
+        assert_eq!(
+            dag.get(1).unwrap().cred_description,
+            vec![String::new(), "dry days".to_string()]
+        );
+    }
+
+    #[test]
+    fn round_trips_models() {
+        let (dag, _) = read(SPRINKLER).unwrap();
+        let read_back = round_trip(&dag);
+        assert_eq!(read_back.to_json(), dag.to_json());
+        assert_eq!(write(&read_back), write(&dag));
+    }
+
+    #[test]
+    fn round_trips_nodes_without_values() {
+        let mut dag = Dag::new();
+        let node = dag.insert_node();
+        dag.set_label(node, "title".to_string());
+        dag.assign_ids();
+        let read_back = round_trip(&dag);
+        assert_eq!(read_back.to_json(), dag.to_json());
+    }
+
+    #[test]
+    fn reports_unknown_values() {
+        let error = read("node a {\n    values x\n    observed y\n}\n").unwrap_err();
+        match error {
+            DeserError::Syntax(error) => assert_eq!(error.line, 3),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::fmt::Write;
+
+use super::lexer::{tokenize, Comments, Tokens};
+use super::table::{resolve, rows, Probability};
+use super::{Expected, ParseError};
+use crate::graph::{Dag, DeserError, JsonDocument, JsonNode, Metadata, FORMAT_VERSION};
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * The UAI format of the inference competitions
+ *
+ * Variables only have numbers, the `i`-th variable being the `i`-th node of the
+ * model, and their values are numbered as well. Each function lists its scope,
+ * the child being last for Bayesian networks, and its table in the order of the
+ * scope, the last variable varying the fastest.
+ *
+ * Markov networks are loaded as a Bayesian network with the same distribution:
+ * each factor becomes an observed binary node whose parents are its scope, the
+ * probability of observing it being proportional to the factor.
+ *
+ * Evidence files list the observed values of some variables, numbered like the
+ * nodes of the model. Models are always written as Bayesian networks, the
+ * factor nodes of a Markov network becoming ordinary variables whose observation
+ * is written in the evidence file.
+ */
+
+pub const EVIDENCE_EXTENSION: &str = "evid";
+
+const COMMENTS: Comments = Comments {
+    line: &[],
+    block: false,
+};
+
+fn variable_name(i: usize) -> String {
+    format!("x{}", i)
+}
+
+fn factor_name(k: usize) -> String {
+    format!("f{}", k)
+}
+
+/// A function of the file: its scope and its table
+fn function(tokens: &mut Tokens, variable_count: usize) -> Result<Vec<usize>, ParseError> {
+    let size = tokens.integer()?;
+    let mut scope = Vec::with_capacity(size);
+    for _ in 0..size {
+        let line = tokens.line();
+        let variable = tokens.integer()?;
+        if variable >= variable_count {
+            return Err(ParseError {
+                line,
+                expected: Expected::Variable,
+                found: Some(variable.to_string()),
+            });
+        }
+        scope.push(variable);
+    }
+    Ok(scope)
+}
+
+fn table(tokens: &mut Tokens) -> Result<(usize, Vec<f32>), ParseError> {
+    let line = tokens.line();
+    let size = tokens.integer()?;
+    let mut table = Vec::with_capacity(size);
+    for _ in 0..size {
+        table.push(tokens.number()?);
+    }
+    Ok((line, table))
+}
+
+fn parse(
+    tokens: &mut Tokens,
+    report: &mut ValidationReport,
+) -> Result<(Vec<JsonNode>, Vec<Probability>), ParseError> {
+    let markov = if tokens.eat_keyword("MARKOV") {
+        true
+    } else {
+        tokens.expect_keyword("BAYES")?;
+        false
+    };
+    let variable_count = tokens.integer()?;
+    let mut nodes = Vec::with_capacity(variable_count);
+    for i in 0..variable_count {
+        let cardinality = tokens.integer()?;
+        nodes.push(JsonNode {
+            id: Some(variable_name(i)),
+            label: variable_name(i),
+            description: String::new(),
+            values: (0..cardinality).map(|v| v.to_string()).collect(),
+            parents: Vec::new(),
+            observation: None,
+            credencies: None,
+            cred_description: Vec::new(),
+            sample_sizes: Vec::new(),
+            factor: false,
+            rules: None,
+            position: None,
+        });
+    }
+    let function_count = tokens.integer()?;
+    let mut scopes = Vec::with_capacity(function_count);
+    for _ in 0..function_count {
+        scopes.push(function(tokens, variable_count)?);
+    }
+
+    let mut blocks = Vec::with_capacity(function_count);
+    for (k, scope) in scopes.into_iter().enumerate() {
+        let (line, table) = table(tokens)?;
+        if markov {
+            let id = factor_name(k);
+            // rescale the factor so that it fits in a probability
+            let max = table.iter().cloned().fold(0.0, f32::max);
+            let table = table
+                .iter()
+                .flat_map(|&v| {
+                    let p = if max > 0.0 { v / max } else { 0.0 };
+                    vec![p, 1.0 - p]
+                })
+                .collect();
+            nodes.push(JsonNode {
+                id: Some(id.clone()),
+                label: format!("factor {}", k),
+                description: String::new(),
+                values: vec!["true".into(), "false".into()],
+                parents: Vec::new(),
+                observation: Some(0),
+                credencies: None,
+                cred_description: Vec::new(),
+                sample_sizes: Vec::new(),
+                factor: true,
+                rules: None,
+                position: None,
+            });
+            blocks.push(Probability {
+                line,
+                child: id,
+                parents: scope.into_iter().map(variable_name).collect(),
+                table: Some(table),
+                entries: Vec::new(),
+                default: None,
+                explanations: Vec::new(),
+            });
+        } else {
+            let (&child, parents) = match scope.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+            let child = variable_name(child);
+            if blocks.iter().any(|b: &Probability| b.child == child) {
+                report.push(
+                    child,
+                    Problem::Unsupported("credencies", "several functions".into()),
+                );
+                continue;
+            }
+            blocks.push(Probability {
+                line,
+                child,
+                parents: parents.iter().copied().map(variable_name).collect(),
+                table: Some(table),
+                entries: Vec::new(),
+                default: None,
+                explanations: Vec::new(),
+            });
+        }
+    }
+    Ok((nodes, blocks))
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let mut tokens = tokenize(text, &COMMENTS).map_err(DeserError::Syntax)?;
+    let mut report = ValidationReport::new();
+    let (mut nodes, blocks) = parse(&mut tokens, &mut report).map_err(DeserError::Syntax)?;
+    for block in blocks {
+        resolve(block, &mut nodes, &mut report).map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata: Metadata::default(),
+            nodes,
+        },
+        report,
+    )
+}
+
+/// Read an evidence file, giving the observed nodes of the model with their
+/// values
+pub fn read_evidence(dag: &Dag, text: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+    let mut tokens = tokenize(text, &COMMENTS)?;
+    let mut numbers = Vec::new();
+    while !tokens.is_empty() {
+        numbers.push((tokens.line(), tokens.integer()?));
+    }
+    // older files start with the number of samples, always 1
+    if numbers.len() % 2 == 0 && numbers.first().map(|n| n.1) == Some(1) {
+        numbers.remove(0);
+    }
+    let count = numbers.first().map(|n| n.1).unwrap_or(0);
+    if numbers.len() < 1 + 2 * count {
+        return Err(ParseError {
+            line: numbers.last().map(|n| n.0).unwrap_or(1),
+            expected: Expected::Number,
+            found: None,
+        });
+    }
+    if let Some(&(line, extra)) = numbers.get(1 + 2 * count) {
+        return Err(ParseError {
+            line,
+            expected: Expected::Number,
+            found: Some(extra.to_string()),
+        });
+    }
+    let nodes: Vec<usize> = dag.iter_nodes().map(|(i, _)| i).collect();
+    let mut observations = Vec::with_capacity(count);
+    for pair in numbers[1..].chunks(2) {
+        let (line, variable) = pair[0];
+        let node = *nodes.get(variable).ok_or_else(|| ParseError {
+            line,
+            expected: Expected::Variable,
+            found: Some(variable.to_string()),
+        })?;
+        let (line, value) = pair[1];
+        let node_data = dag.get(node).unwrap();
+        if value >= node_data.values.len() {
+            return Err(ParseError {
+                line,
+                expected: Expected::ValueOf(node_data.label.clone()),
+                found: Some(value.to_string()),
+            });
+        }
+        observations.push((node, value));
+    }
+    Ok(observations)
+}
+
+pub fn write(dag: &Dag) -> String {
+    // variables are numbered in the order of the nodes
+    let order: Vec<usize> = dag.iter_nodes().map(|(i, _)| i).collect();
+    let number = |node| order.iter().position(|&n| n == node).unwrap();
+    let mut out = String::new();
+    writeln!(out, "BAYES").unwrap();
+    writeln!(out, "{}", dag.iter_nodes().count()).unwrap();
+    writeln!(
+        out,
+        "{}",
+        dag.iter_nodes()
+            .map(|(_, node)| node.values.len().to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+    .unwrap();
+    writeln!(out, "{}", dag.iter_nodes().count()).unwrap();
+    for (i, node) in dag.iter_nodes() {
+        let scope = node
+            .parents
+            .iter()
+            .chain(std::iter::once(&i))
+            .map(|&n| number(n).to_string())
+            .collect::<Vec<_>>();
+        writeln!(out, "{} {}", scope.len(), scope.join(" ")).unwrap();
+    }
+    for (i, _) in dag.iter_nodes() {
+        let table: Vec<String> = rows(dag, i)
+            .into_iter()
+            .flat_map(|(_, row)| row.into_iter().map(|p| p.to_string()))
+            .collect();
+        writeln!(out, "\n{}\n {}", table.len(), table.join(" ")).unwrap();
+    }
+    out
+}
+
+/// Write the observations of the model as an evidence file, if there are any
+pub fn write_evidence(dag: &Dag) -> Option<String> {
+    let observations: Vec<String> = dag
+        .iter_nodes()
+        .enumerate()
+        .filter_map(|(n, (_, node))| node.observation.map(|v| format!("{} {}", n, v)))
+        .collect();
+    if observations.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "{} {}\n",
+            observations.len(),
+            observations.join(" ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elimination;
+    use crate::formats::{text, yaml};
+
+    const MARKOV: &str = "MARKOV
+2
+2 2
+2
+1 0
+2 0 1
+2
+ 1 3
+4
+ 4 1 1 4
+";
+
+    /// The posterior of a node given the observations of the model
+    fn posterior(dag: &Dag, node: usize) -> Vec<f64> {
+        let row: Vec<Option<usize>> = (0..dag.node_ids().len())
+            .map(|i| dag.get(i).and_then(|n| n.observation))
+            .collect();
+        let joint = elimination::joint(dag, &elimination::tables(dag), &row, &[node]);
+        let total = joint.total();
+        joint.values.iter().map(|p| p / total).collect()
+    }
+
+    fn apply(dag: &mut Dag, evidence: &str) {
+        let observations = read_evidence(dag, evidence).unwrap();
+        for (node, value) in observations {
+            dag.set_observation(node, Some(value));
+        }
+    }
+
+    #[test]
+    fn markov_factors() {
+        let (dag, report) = read(MARKOV).unwrap();
+        assert!(report.is_empty());
+        let factors: Vec<bool> = dag.iter_nodes().map(|(_, n)| n.factor).collect();
+        assert_eq!(factors, vec![false, false, true, true]);
+        assert_eq!(dag.get(2).unwrap().observation, Some(0));
+        // the factors survive the formats of the app
+        let (json, _) = Dag::from_json(&dag.to_json()).unwrap();
+        assert!(json.get(3).unwrap().factor);
+        let (text, _) = text::read(&text::write(&dag)).unwrap();
+        assert!(text.get(3).unwrap().factor && !text.get(1).unwrap().factor);
+        let (yaml, _) = yaml::read(&yaml::write(&dag)).unwrap();
+        assert!(yaml.get(3).unwrap().factor && !yaml.get(1).unwrap().factor);
+    }
+
+    #[test]
+    fn model_and_evidence_round_trip() {
+        let (mut dag, _) = read(MARKOV).unwrap();
+        apply(&mut dag, "1 0 1");
+        let expected = posterior(&dag, 1);
+
+        let model = write(&dag);
+        let evidence = write_evidence(&dag).unwrap();
+        // the factors are ordinary variables of the file, observed in the evidence
+        assert!(model.starts_with("BAYES\n4\n"));
+        assert_eq!(evidence, "3 0 1 2 0 3 0\n");
+
+        let (mut back, _) = read(&model).unwrap();
+        apply(&mut back, &evidence);
+        let observations: Vec<Option<usize>> =
+            back.iter_nodes().map(|(_, n)| n.observation).collect();
+        assert_eq!(observations, vec![Some(1), None, Some(0), Some(0)]);
+        for (found, expected) in posterior(&back, 1).iter().zip(&expected) {
+            assert!((found - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn evidence_numbers_every_node() {
+        let (mut dag, _) =
+            read("BAYES\n3\n2 2 2\n3\n1 0\n1 1\n1 2\n2\n 1 1\n2\n 1 1\n2\n 1 1\n").unwrap();
+        apply(&mut dag, "2 1 1 2 0");
+        let observations: Vec<Option<usize>> =
+            dag.iter_nodes().map(|(_, n)| n.observation).collect();
+        assert_eq!(observations, vec![None, Some(1), Some(0)]);
+        assert_eq!(write_evidence(&dag).unwrap(), "2 1 1 2 0\n");
+        assert!(read_evidence(&dag, "1 3 0").is_err());
+        assert!(read_evidence(&dag, "1 0 2").is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt::Write;
+
+use roxmltree::{Document, Node};
+
+use super::table::{resolve, rows, Probability};
+use super::{escape_xml, Expected, ParseError};
+use crate::graph::{Dag, DeserError, JsonDocument, JsonNode, Metadata, FORMAT_VERSION};
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * The XMLBIF interchange format
+ *
+ * The XML counterpart of BIF: variables are named by the identifiers of the
+ * nodes, and the rest is stored in `PROPERTY` elements of the form
+ * `key = value`, as in BIF. The tables list the values of the node the fastest.
+ *
+ * Only chance variables are supported, decision and utility variables of
+ * influence diagrams are reported.
+ */
+
+/// Split a `key = value` property, the key of a row explanation carrying the
+/// values of the parents as in `explanation(yes, no)`
+fn property(text: &str) -> Option<(String, Option<Vec<String>>, String)> {
+    let (key, value) = text.split_once('=')?;
+    let value = value.strip_prefix(' ').unwrap_or(value);
+    let key = key.trim();
+    match key.split_once('(') {
+        Some((key, context)) => {
+            let context = context.strip_suffix(')')?;
+            Some((
+                key.trim().into(),
+                Some(context.split(',').map(|v| v.trim().into()).collect()),
+                value.into(),
+            ))
+        }
+        None => Some((key.into(), None, value.into())),
+    }
+}
+
+/// Parse a position property of the form `(x, y)`
+fn parse_position(value: &str) -> Option<(f32, f32)> {
+    let value = value.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let (x, y) = value.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn is_tag(node: &Node, tag: &str) -> bool {
+    node.is_element() && node.tag_name().name().eq_ignore_ascii_case(tag)
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |c| is_tag(c, tag))
+}
+
+fn text(node: Node) -> String {
+    node.text().unwrap_or("").trim().into()
+}
+
+fn line(document: &Document, node: Node) -> usize {
+    document.text_pos_at(node.range().start).row as usize
+}
+
+/// The text of the required child element `tag`
+fn required(document: &Document, node: Node, tag: &'static str) -> Result<String, ParseError> {
+    children(node, tag)
+        .next()
+        .map(text)
+        .ok_or_else(|| ParseError {
+            line: line(document, node),
+            expected: Expected::Keyword(tag),
+            found: Some(format!("</{}>", node.tag_name().name())),
+        })
+}
+
+fn network(node: Node, metadata: &mut Metadata) {
+    for child in node.children().filter(Node::is_element) {
+        if is_tag(&child, "NAME") {
+            let name = text(child);
+            if name != "unknown" {
+                metadata.title = name;
+            }
+        } else if is_tag(&child, "PROPERTY") {
+            if let Some((key, None, value)) = property(&text(child)) {
+                match &key[..] {
+                    "author" => metadata.author = value,
+                    "question" => metadata.question = value,
+                    "license" => metadata.license = value,
+                    "created" => metadata.created = value,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn variable(
+    document: &Document,
+    node: Node,
+    report: &mut ValidationReport,
+) -> Result<JsonNode, ParseError> {
+    let id = required(document, node, "NAME")?;
+    let mut result = JsonNode {
+        id: Some(id.clone()),
+        label: id,
+        description: String::new(),
+        values: Vec::new(),
+        parents: Vec::new(),
+        observation: None,
+        credencies: None,
+        cred_description: Vec::new(),
+        sample_sizes: Vec::new(),
+        factor: false,
+        rules: None,
+        position: None,
+    };
+    let mut observation = None;
+    let mut unsupported = Vec::new();
+    if let Some(kind) = node.attribute("TYPE") {
+        if !kind.eq_ignore_ascii_case("nature") {
+            unsupported.push(("type", format!("TYPE=\"{}\"", kind)));
+        }
+    }
+    for child in node.children().filter(Node::is_element) {
+        if is_tag(&child, "OUTCOME") || is_tag(&child, "VALUE") {
+            result.values.push(text(child));
+        } else if is_tag(&child, "PROPERTY") {
+            match property(&text(child)) {
+                Some((key, None, value)) if key == "label" => result.label = value,
+                Some((key, None, value)) if key == "description" => result.description = value,
+                Some((key, None, value)) if key == "position" => {
+                    result.position = parse_position(&value)
+                }
+                Some((key, None, value)) if key == "observation" => {
+                    observation = Some((line(document, child), value))
+                }
+                _ => {}
+            }
+        } else if !is_tag(&child, "NAME") {
+            unsupported.push(("variable", format!("<{}>", child.tag_name().name())));
+        }
+    }
+    for (field, construct) in unsupported {
+        report.push(result.label.clone(), Problem::Unsupported(field, construct));
+    }
+    if let Some((line, value)) = observation {
+        match result.values.iter().position(|v| *v == value) {
+            Some(i) => result.observation = Some(i),
+            None => {
+                return Err(ParseError {
+                    line,
+                    expected: Expected::ValueOf(result.label),
+                    found: Some(value),
+                })
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn definition(
+    document: &Document,
+    node: Node,
+    report: &mut ValidationReport,
+) -> Result<Probability, ParseError> {
+    let mut block = Probability {
+        line: line(document, node),
+        child: required(document, node, "FOR")?,
+        parents: children(node, "GIVEN").map(text).collect(),
+        table: None,
+        entries: Vec::new(),
+        default: None,
+        explanations: Vec::new(),
+    };
+    for child in node.children().filter(Node::is_element) {
+        if is_tag(&child, "TABLE") {
+            let mut table = Vec::new();
+            for word in text(child).split_whitespace() {
+                table.push(word.parse().map_err(|_| ParseError {
+                    line: line(document, child),
+                    expected: Expected::Number,
+                    found: Some(word.into()),
+                })?);
+            }
+            block.table = Some(table);
+        } else if is_tag(&child, "PROPERTY") {
+            if let Some((key, context, value)) = property(&text(child)) {
+                if key == "explanation" {
+                    block
+                        .explanations
+                        .push((line(document, child), context, value));
+                }
+            }
+        } else if !is_tag(&child, "FOR") && !is_tag(&child, "GIVEN") {
+            report.push(
+                block.child.clone(),
+                Problem::Unsupported("definition", format!("<{}>", child.tag_name().name())),
+            );
+        }
+    }
+    Ok(block)
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let document = Document::parse(text).map_err(DeserError::Xml)?;
+    let root = document.root_element();
+    let syntax = |node: Node, expected| {
+        DeserError::Syntax(ParseError {
+            line: line(&document, node),
+            expected: Expected::Keyword(expected),
+            found: Some(format!("<{}>", node.tag_name().name())),
+        })
+    };
+    if !is_tag(&root, "BIF") {
+        return Err(syntax(root, "BIF"));
+    }
+    let network_node = children(root, "NETWORK")
+        .next()
+        .ok_or_else(|| syntax(root, "NETWORK"))?;
+
+    let mut metadata = Metadata::default();
+    let mut report = ValidationReport::new();
+    let mut nodes = Vec::new();
+    let mut blocks = Vec::new();
+    network(network_node, &mut metadata);
+    for child in network_node.children().filter(Node::is_element) {
+        if is_tag(&child, "VARIABLE") {
+            nodes.push(variable(&document, child, &mut report).map_err(DeserError::Syntax)?);
+        } else if is_tag(&child, "DEFINITION") || is_tag(&child, "PROBABILITY") {
+            blocks.push(definition(&document, child, &mut report).map_err(DeserError::Syntax)?);
+        }
+    }
+    for block in blocks {
+        resolve(block, &mut nodes, &mut report).map_err(DeserError::Syntax)?;
+    }
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+fn write_property(out: &mut String, key: &str, value: &str) {
+    if !value.is_empty() {
+        writeln!(
+            out,
+            "    <PROPERTY>{} = {}</PROPERTY>",
+            escape_xml(key),
+            escape_xml(value)
+        )
+        .unwrap();
+    }
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = dag.node_ids();
+    let metadata = dag.metadata();
+    let mut out = String::new();
+
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(out, "<BIF VERSION=\"0.3\">").unwrap();
+    writeln!(out, "<NETWORK>").unwrap();
+    let title = if metadata.title.is_empty() {
+        "unknown"
+    } else {
+        &metadata.title
+    };
+    writeln!(out, "<NAME>{}</NAME>", escape_xml(title)).unwrap();
+    write_property(&mut out, "author", &metadata.author);
+    write_property(&mut out, "question", &metadata.question);
+    write_property(&mut out, "license", &metadata.license);
+    write_property(&mut out, "created", &metadata.created);
+
+    for (i, node) in dag.iter_nodes() {
+        writeln!(out, "<VARIABLE TYPE=\"nature\">").unwrap();
+        writeln!(
+            out,
+            "    <NAME>{}</NAME>",
+            escape_xml(ids[i].as_ref().unwrap())
+        )
+        .unwrap();
+        for value in &node.values {
+            writeln!(out, "    <OUTCOME>{}</OUTCOME>", escape_xml(value)).unwrap();
+        }
+        write_property(&mut out, "label", &node.label);
+        write_property(&mut out, "description", &node.description);
+        if let Some(obs) = node.observation {
+            write_property(&mut out, "observation", &node.values[obs]);
+        }
+        if let Some((x, y)) = node.position {
+            write_property(&mut out, "position", &format!("({}, {})", x, y));
+        }
+        writeln!(out, "</VARIABLE>").unwrap();
+    }
+
+    for (i, node) in dag.iter_nodes() {
+        writeln!(out, "<DEFINITION>").unwrap();
+        writeln!(
+            out,
+            "    <FOR>{}</FOR>",
+            escape_xml(ids[i].as_ref().unwrap())
+        )
+        .unwrap();
+        for &p in &node.parents {
+            writeln!(
+                out,
+                "    <GIVEN>{}</GIVEN>",
+                escape_xml(ids[p].as_ref().unwrap())
+            )
+            .unwrap();
+        }
+        let rows = rows(dag, i);
+        let table = rows
+            .iter()
+            .flat_map(|(_, row)| row.iter().map(|p| p.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(out, "    <TABLE>{}</TABLE>", table).unwrap();
+        for (r, (configuration, _)) in rows.iter().enumerate() {
+            let explanation = node.cred_description.get(r).map(|d| &d[..]).unwrap_or("");
+            if node.parents.is_empty() {
+                write_property(&mut out, "explanation", explanation);
+            } else {
+                let context = configuration
+                    .iter()
+                    .zip(node.parents.iter())
+                    .map(|(&v, &p)| &dag.get(p).unwrap().values[v][..])
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write_property(&mut out, &format!("explanation({})", context), explanation);
+            }
+        }
+        writeln!(out, "</DEFINITION>").unwrap();
+    }
+
+    writeln!(out, "</NETWORK>").unwrap();
+    writeln!(out, "</BIF>").unwrap();
+    out
+}
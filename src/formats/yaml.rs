@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use super::flatten_rows;
+use crate::graph::{
+    Dag, DeserError, JsonDocument, JsonNode, JsonParent, JsonRule, JsonRuleTable, Metadata,
+    FORMAT_VERSION,
+};
+use crate::rules::parent_configurations;
+use crate::validation::{Problem, ValidationReport};
+
+/*
+ * The YAML format, meant to be read and edited by hand
+ *
+ * It holds the same contents as the JSON format, but the nodes are keyed by
+ * their identifiers and the tables are nested maps: first by the values of the
+ * parents in order, then by the values of the node. The explanations of the
+ * rows are nested the same way, without the last level.
+ *
+ *     nodes:
+ *       wet:
+ *         values: [yes, no]
+ *         parents: [rain]
+ *         credencies:
+ *           yes: { yes: 9, no: 1 }
+ *           no: { yes: 1, no: 9 }
+ *         explanations:
+ *           yes: Rain makes the grass wet
+ *
+ * The sample sizes of the rows, when the credencies are uncertain, are nested
+ * like the explanations.
+ */
+
+/// A name, written as a string but also accepting what YAML reads as numbers
+/// or booleans
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "Value", into = "String")]
+struct Name(String);
+
+impl From<Value> for Name {
+    fn from(value: Value) -> Name {
+        Name(key_name(&value))
+    }
+}
+
+impl From<Name> for String {
+    fn from(name: Name) -> String {
+        name.0
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct YamlRule {
+    #[serde(default)]
+    when: Mapping,
+    credencies: Mapping,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct YamlNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    values: Vec<Name>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    observation: Option<Name>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    factor: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<(f32, f32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parents: Vec<Name>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credencies: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    explanations: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sample_sizes: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<Vec<YamlRule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<Mapping>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    default_description: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct YamlDocument {
+    version: u64,
+    #[serde(default)]
+    metadata: Metadata,
+    nodes: Mapping,
+}
+
+fn key_name(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::new(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .into(),
+    }
+}
+
+/// A credency as written, rather than with all the digits of its conversion
+/// to `f64`
+fn number(p: f32) -> Value {
+    match p.to_string().parse::<f64>() {
+        Ok(p) => Value::from(p),
+        Err(_) => Value::from(p as f64),
+    }
+}
+
+/// Read a map from the values of the node to their credencies
+fn row(
+    map: Option<&Mapping>,
+    values: &[String],
+    path: &str,
+    problems: &mut Vec<Problem>,
+) -> Option<Vec<f32>> {
+    let map = match map {
+        Some(map) => map,
+        None => {
+            problems.push(Problem::MissingCredency(path.into()));
+            return None;
+        }
+    };
+    for key in map.keys() {
+        if !values.contains(&key_name(key)) {
+            problems.push(Problem::UnknownName("credencies", key_name(key)));
+        }
+    }
+    let mut row = Vec::with_capacity(values.len());
+    for value in values {
+        let credency = map
+            .iter()
+            .find(|(k, _)| key_name(k) == *value)
+            .and_then(|(_, v)| v.as_f64());
+        match credency {
+            Some(p) => row.push(p as f32),
+            None => problems.push(Problem::MissingCredency(if path.is_empty() {
+                value.clone()
+            } else {
+                format!("{}: {}", path, value)
+            })),
+        }
+    }
+    if row.len() == values.len() {
+        Some(row)
+    } else {
+        None
+    }
+}
+
+/// Find the entry of a nested table for a configuration of parent values
+fn lookup<'a>(
+    table: &'a Value,
+    configuration: &[usize],
+    parents: &[Vec<String>],
+) -> Option<&'a Value> {
+    configuration
+        .iter()
+        .zip(parents)
+        .try_fold(table, |table, (&v, values)| {
+            table
+                .as_mapping()?
+                .iter()
+                .find(|(k, _)| key_name(k) == values[v])
+                .map(|(_, v)| v)
+        })
+}
+
+/// Check that the keys of a nested table are values of the parents
+fn check_keys(
+    table: &Value,
+    parents: &[Vec<String>],
+    field: &'static str,
+    problems: &mut Vec<Problem>,
+) {
+    if let (Some((values, rest)), Some(map)) = (parents.split_first(), table.as_mapping()) {
+        for (key, sub) in map {
+            if values.contains(&key_name(key)) {
+                check_keys(sub, rest, field, problems);
+            } else {
+                problems.push(Problem::UnknownName(field, key_name(key)));
+            }
+        }
+    }
+}
+
+/// Fill a node from its entry, given the values of its parents
+fn fill(yaml: &YamlNode, node: &mut JsonNode, parents: &[(String, Vec<String>)]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let values = &node.values;
+    let parent_values: Vec<Vec<String>> = parents.iter().map(|p| p.1.clone()).collect();
+    let path = |configuration: &[usize]| {
+        configuration
+            .iter()
+            .zip(&parent_values)
+            .map(|(&v, values)| &values[v][..])
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    if let Some(ref table) = yaml.credencies {
+        check_keys(table, &parent_values, "credencies", &mut problems);
+        let shape: Vec<usize> = parent_values.iter().map(|v| v.len()).collect();
+        // read every row before giving up, to report all the problems
+        let rows: Vec<Option<Vec<f32>>> = parent_configurations(&shape)
+            .map(|configuration| {
+                let entry = lookup(table, &configuration, &parent_values);
+                row(
+                    entry.and_then(Value::as_mapping),
+                    values,
+                    &path(&configuration),
+                    &mut problems,
+                )
+            })
+            .collect();
+        node.credencies = rows
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .map(|rows| flatten_rows(&rows));
+    }
+
+    if let Some(ref explanations) = yaml.explanations {
+        check_keys(explanations, &parent_values, "explanations", &mut problems);
+        let shape: Vec<usize> = parent_values.iter().map(|v| v.len()).collect();
+        node.cred_description = parent_configurations(&shape)
+            .map(|configuration| {
+                lookup(explanations, &configuration, &parent_values)
+                    .map(key_name)
+                    .unwrap_or_default()
+            })
+            .collect();
+    }
+
+    if let Some(ref sizes) = yaml.sample_sizes {
+        check_keys(sizes, &parent_values, "sample_sizes", &mut problems);
+        let shape: Vec<usize> = parent_values.iter().map(|v| v.len()).collect();
+        node.sample_sizes = parent_configurations(&shape)
+            .map(|configuration| {
+                lookup(sizes, &configuration, &parent_values)
+                    .and_then(Value::as_f64)
+                    .unwrap_or(0.0) as f32
+            })
+            .collect();
+    }
+
+    if yaml.rules.is_some() || yaml.default.is_some() {
+        let mut rules = Vec::new();
+        for rule in yaml.rules.iter().flatten() {
+            let mut when = vec![None; parents.len()];
+            for (parent, value) in &rule.when {
+                let (parent, value) = (key_name(parent), key_name(value));
+                match parents.iter().position(|p| p.0 == parent) {
+                    Some(p) => match parents[p].1.iter().position(|v| *v == value) {
+                        Some(v) => when[p] = Some(v),
+                        None => problems.push(Problem::UnknownName("rules", value)),
+                    },
+                    None => problems.push(Problem::UnknownName("rules", parent)),
+                }
+            }
+            if let Some(credencies) = row(Some(&rule.credencies), values, "", &mut problems) {
+                rules.push(JsonRule {
+                    when,
+                    credencies: credencies.into_iter().map(Into::into).collect(),
+                    description: rule.description.clone(),
+                });
+            }
+        }
+        let default = match yaml.default {
+            Some(ref default) => row(Some(default), values, "default", &mut problems),
+            None => Some(vec![1.0; values.len()]),
+        };
+        if let Some(default) = default {
+            node.rules = Some(JsonRuleTable {
+                rules,
+                default: default.into_iter().map(Into::into).collect(),
+                default_description: yaml.default_description.clone(),
+            });
+        }
+    }
+    problems
+}
+
+pub fn read(text: &str) -> Result<(Dag, ValidationReport), DeserError> {
+    let document: YamlDocument = serde_yaml::from_str(text).map_err(DeserError::Yaml)?;
+    if document.version != FORMAT_VERSION {
+        return Err(DeserError::Version(document.version));
+    }
+    let mut report = ValidationReport::new();
+    let mut entries = Vec::with_capacity(document.nodes.len());
+    for (id, entry) in document.nodes {
+        let entry: YamlNode = serde_yaml::from_value(entry).map_err(DeserError::Yaml)?;
+        entries.push((key_name(&id), entry));
+    }
+    let mut nodes: Vec<JsonNode> = entries
+        .iter()
+        .map(|(id, entry)| JsonNode {
+            id: Some(id.clone()),
+            label: entry.label.clone().unwrap_or_else(|| id.clone()),
+            description: entry.description.clone(),
+            values: entry.values.iter().map(|v| v.0.clone()).collect(),
+            parents: entry
+                .parents
+                .iter()
+                .map(|p| JsonParent::Id(p.0.clone()))
+                .collect(),
+            observation: None,
+            credencies: None,
+            cred_description: Vec::new(),
+            sample_sizes: Vec::new(),
+            factor: entry.factor,
+            rules: None,
+            position: entry.position,
+        })
+        .collect();
+
+    for (i, (_, entry)) in entries.iter().enumerate() {
+        if let Some(Name(ref observation)) = entry.observation {
+            match nodes[i].values.iter().position(|v| v == observation) {
+                Some(v) => nodes[i].observation = Some(v),
+                None => report.push(
+                    nodes[i].label.clone(),
+                    Problem::UnknownName("observation", observation.clone()),
+                ),
+            }
+        }
+        let parents: Option<Vec<(String, Vec<String>)>> = entry
+            .parents
+            .iter()
+            .map(|p| {
+                let parent = nodes.iter().find(|n| n.id.as_ref() == Some(&p.0))?;
+                Some((p.0.clone(), parent.values.clone()))
+            })
+            .collect();
+        // the unknown parents are reported when building the model
+        if let Some(parents) = parents {
+            for problem in fill(entry, &mut nodes[i], &parents) {
+                report.push(nodes[i].label.clone(), problem);
+            }
+        }
+    }
+
+    Dag::from_document(
+        JsonDocument {
+            version: FORMAT_VERSION,
+            metadata: document.metadata,
+            nodes,
+        },
+        report,
+    )
+}
+
+/// Nest the rows of a table, or their explanations, by the values of the
+/// parents
+fn nest(leaves: Vec<Value>, parents: &[&[String]]) -> Value {
+    match parents.split_first() {
+        None => leaves.into_iter().next().unwrap_or(Value::Null),
+        Some((values, rest)) => {
+            let size = leaves.len() / values.len().max(1);
+            let mut map = Mapping::new();
+            let mut leaves = leaves.into_iter();
+            for value in values.iter() {
+                let chunk: Vec<Value> = leaves.by_ref().take(size).collect();
+                map.insert(Value::from(value.clone()), nest(chunk, rest));
+            }
+            Value::Mapping(map)
+        }
+    }
+}
+
+fn row_map(values: &[String], row: &[f32]) -> Mapping {
+    values
+        .iter()
+        .zip(row)
+        .map(|(v, &p)| (Value::from(v.clone()), number(p)))
+        .collect()
+}
+
+pub fn write(dag: &Dag) -> String {
+    let ids = dag.node_ids();
+    let mut nodes = Mapping::new();
+    for (i, node) in dag.iter_nodes() {
+        let id = ids[i].clone().unwrap();
+        let parent_values: Vec<&[String]> = node
+            .parents
+            .iter()
+            .map(|&p| &dag.get(p).unwrap().values[..])
+            .collect();
+        let mut entry = YamlNode {
+            label: if node.label == id {
+                None
+            } else {
+                Some(node.label.clone())
+            },
+            description: node.description.clone(),
+            values: node.values.iter().cloned().map(Name).collect(),
+            observation: node.observation.map(|v| Name(node.values[v].clone())),
+            factor: node.factor,
+            position: node.position,
+            parents: node
+                .parents
+                .iter()
+                .map(|&p| Name(ids[p].clone().unwrap()))
+                .collect(),
+            credencies: None,
+            explanations: None,
+            sample_sizes: None,
+            rules: None,
+            default: None,
+            default_description: String::new(),
+        };
+        if let Some(ref rules) = node.rules {
+            entry.rules = Some(
+                rules
+                    .rules
+                    .iter()
+                    .map(|rule| YamlRule {
+                        when: node
+                            .parents
+                            .iter()
+                            .zip(&rule.context)
+                            .filter_map(|(&p, v)| {
+                                let v = (*v)?;
+                                Some((
+                                    Value::from(ids[p].clone().unwrap()),
+                                    Value::from(dag.get(p).unwrap().values[v].clone()),
+                                ))
+                            })
+                            .collect(),
+                        credencies: row_map(&node.values, &rule.credencies),
+                        description: rule.description.clone(),
+                    })
+                    .collect(),
+            );
+            entry.default = Some(row_map(&node.values, &rules.default));
+            entry.default_description = rules.default_description.clone();
+        } else if let Some(ref credencies) = node.credencies {
+            let shape = credencies.shape();
+            let rows = parent_configurations(&shape[1..])
+                .map(|configuration| {
+                    let mut idx = vec![0];
+                    idx.extend(configuration);
+                    let row: Vec<f32> = (0..shape[0])
+                        .map(|v| {
+                            idx[0] = v;
+                            credencies[ndarray::IxDyn(&idx)]
+                        })
+                        .collect();
+                    Value::Mapping(row_map(&node.values, &row))
+                })
+                .collect();
+            entry.credencies = Some(nest(rows, &parent_values));
+            if node.cred_description.iter().any(|d| !d.is_empty()) {
+                let explanations = node
+                    .cred_description
+                    .iter()
+                    .map(|d| Value::from(d.clone()))
+                    .collect();
+                entry.explanations = Some(nest(explanations, &parent_values));
+            }
+        }
+        if !node.sample_sizes.is_empty() {
+            let sizes = node.sample_sizes.iter().map(|&s| number(s)).collect();
+            entry.sample_sizes = Some(nest(sizes, &parent_values));
+        }
+        nodes.insert(
+            Value::from(id),
+            serde_yaml::to_value(entry).expect("nodes can always be serialized"),
+        );
+    }
+    serde_yaml::to_string(&YamlDocument {
+        version: FORMAT_VERSION,
+        metadata: dag.metadata().clone(),
+        nodes,
+    })
+    .unwrap()
+}
@@ -1,9 +1,17 @@
 use loopybayesnet::BayesNet;
-use ndarray::{ArrayD, IxDyn};
+use ndarray::{ArrayD, Axis, IxDyn};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    formats::ParseError,
+    reshape,
+    rules::{parent_configurations, CptRule, RuleTable},
+    validation::{Problem, ValidationReport},
+};
+
 #[derive(Debug, Clone)]
 pub struct Node {
+    pub id: String,
     pub parents: Vec<usize>,
     pub children: Vec<usize>,
     pub label: String,
@@ -11,10 +19,19 @@ pub struct Node {
     pub values: Vec<String>,
     pub credencies: Option<ArrayD<f32>>,
     pub cred_description: Vec<String>,
+    // how many cases each row of the credencies is worth, making them the
+    // parameters of a Dirichlet distribution, empty when they are not uncertain
+    pub sample_sizes: Vec<f32>,
+    pub rules: Option<RuleTable>,
     pub observation: Option<usize>,
+    // the node stands for a factor of a Markov network, and stays observed when
+    // loading evidence
+    pub factor: bool,
+    // where other tools draw the node, kept when exchanging files with them
+    pub position: Option<(f32, f32)>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum EdgeError {
     BadNode,
     WouldCycle,
@@ -24,11 +41,30 @@ pub enum EdgeError {
 #[derive(Debug)]
 pub struct Dag {
     nodes: Vec<Option<Node>>,
+    metadata: Metadata,
+}
+
+/// Version of the file format written by `Dag::to_json`. Files of older versions
+/// are migrated on load, version 1 being the bare list of nodes.
+pub const FORMAT_VERSION: u64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub question: String,
+    #[serde(default)]
+    pub license: String,
+    #[serde(default)]
+    pub created: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 #[serde(into = "f32", from = "OptFloat")]
-struct Float(f32);
+pub(crate) struct Float(f32);
 
 #[derive(Deserialize, Clone, Copy)]
 #[serde(transparent)]
@@ -40,34 +76,124 @@ impl From<OptFloat> for Float {
     }
 }
 
+impl From<f32> for Float {
+    fn from(f: f32) -> Float {
+        Float(f)
+    }
+}
+
 impl From<Float> for f32 {
     fn from(f: Float) -> f32 {
         f.0
     }
 }
 
+/// A reference to a parent node, either by its identifier or, in files saved by
+/// older versions, by its position in the file
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonParent {
+    Id(String),
+    Index(usize),
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct JsonNode {
-    label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+    pub(crate) label: String,
+    #[serde(default)]
+    pub(crate) description: String,
+    pub(crate) values: Vec<String>,
+    pub(crate) parents: Vec<JsonParent>,
+    pub(crate) observation: Option<usize>,
+    pub(crate) credencies: Option<Vec<Float>>,
+    #[serde(default)]
+    pub(crate) cred_description: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) sample_sizes: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rules: Option<JsonRuleTable>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) factor: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) position: Option<(f32, f32)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JsonRule {
+    pub(crate) when: Vec<Option<usize>>,
+    pub(crate) credencies: Vec<Float>,
     #[serde(default)]
-    description: String,
-    values: Vec<String>,
-    parents: Vec<usize>,
-    observation: Option<usize>,
-    credencies: Option<Vec<Float>>,
+    pub(crate) description: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JsonRuleTable {
+    pub(crate) rules: Vec<JsonRule>,
+    pub(crate) default: Vec<Float>,
     #[serde(default)]
-    cred_description: Vec<String>,
+    pub(crate) default_description: String,
 }
 
 #[derive(Debug)]
 pub enum DeserError {
     Json(serde_json::Error),
-    Graph(EdgeError),
+    Xml(roxmltree::Error),
+    Yaml(serde_yaml::Error),
+    Syntax(ParseError),
+    Version(u64),
+    Invalid(ValidationReport),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct JsonDocument {
+    pub(crate) version: u64,
+    #[serde(default)]
+    pub(crate) metadata: Metadata,
+    pub(crate) nodes: Vec<JsonNode>,
+}
+
+/// Upgrade the contents of a file to the current format version, one version
+/// at a time
+fn migrate(mut value: serde_json::Value) -> Result<serde_json::Value, DeserError> {
+    loop {
+        let version = match value {
+            serde_json::Value::Array(_) => 1,
+            serde_json::Value::Object(ref map) => match map.get("version") {
+                Some(v) => v.as_u64().unwrap_or(0),
+                // let the deserialization report the missing field
+                None => return Ok(value),
+            },
+            // let the deserialization report the type error
+            _ => return Ok(value),
+        };
+        value = match version {
+            // version 1 is a bare list of nodes, without metadata
+            1 => serde_json::json!({
+                "version": 2,
+                "nodes": value,
+            }),
+            FORMAT_VERSION => return Ok(value),
+            v => return Err(DeserError::Version(v)),
+        };
+    }
 }
 
 impl Dag {
     pub fn new() -> Dag {
-        Dag { nodes: Vec::new() }
+        Dag {
+            nodes: Vec::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
     }
 
     pub fn estimate_iteration_number(&self) -> usize {
@@ -76,6 +202,7 @@ impl Dag {
 
     pub fn insert_node(&mut self) -> usize {
         let new_node = Node {
+            id: String::new(),
             parents: Vec::new(),
             children: Vec::new(),
             description: String::new(),
@@ -83,7 +210,11 @@ impl Dag {
             values: Vec::new(),
             credencies: None,
             cred_description: Vec::new(),
+            sample_sizes: Vec::new(),
+            rules: None,
             observation: None,
+            factor: false,
+            position: None,
         };
         if let Some(id) = self.nodes.iter().position(|n| n.is_none()) {
             self.nodes[id] = Some(new_node);
@@ -100,13 +231,17 @@ impl Dag {
         // duplicate the node
         self.nodes[new_node] = self.nodes[node].clone();
         // properly update the parents & children though
-        let (new_parents, new_children, label, credencies) = {
+        let (new_parents, new_children, label, credencies, rules) = {
             let new_node = self.nodes[new_node].as_mut().unwrap();
             let new_parents = std::mem::take(&mut new_node.parents);
             let new_children = std::mem::take(&mut new_node.children);
             let new_label = std::mem::take(&mut new_node.label);
+            // the copy will get its own identifier on export
+            new_node.id = String::new();
+            new_node.position = None;
             let credencies = new_node.credencies.take();
-            (new_parents, new_children, new_label, credencies)
+            let rules = new_node.rules.take();
+            (new_parents, new_children, new_label, credencies, rules)
         };
         for p in new_parents {
             self.add_edge(new_node, p).unwrap();
@@ -117,7 +252,9 @@ impl Dag {
         // update the label to differentiate
         self.set_label(new_node, format!("{} (bis)", label));
         // set back the credency matrix that was lost in the process
-        self.nodes[new_node].as_mut().unwrap().credencies = credencies;
+        let node = self.nodes[new_node].as_mut().unwrap();
+        node.credencies = credencies;
+        node.rules = rules;
         Some(new_node)
     }
 
@@ -136,6 +273,32 @@ impl Dag {
         self.nodes[node] = None;
     }
 
+    /// Remove a node while keeping the joint distribution over the other nodes
+    /// unchanged.
+    ///
+    /// The edges towards its children are reversed one by one, so that the node
+    /// ends up without children and can be summed out by simply removing it. Its
+    /// children thus inherit its parents.
+    pub fn absorb_node(&mut self, node: usize) {
+        loop {
+            let children = match self.get(node) {
+                Some(n) => n.children.clone(),
+                None => return,
+            };
+            if children.is_empty() {
+                break;
+            }
+            // there is always a child with no other path from the node: the first
+            // one in topological order
+            let child = children
+                .into_iter()
+                .find(|&c| self.check_edge_reversal(c, node).is_ok())
+                .unwrap();
+            self.reverse_edge(child, node).unwrap();
+        }
+        self.remove_node(node);
+    }
+
     pub fn check_edge_addition(&self, child: usize, parent: usize) -> Result<(), EdgeError> {
         if let Some(&Some(ref node)) = self.nodes.get(parent) {
             if parent == child {
@@ -180,15 +343,24 @@ impl Dag {
         // check if a cycle would be created...
         self.check_edge_addition(child, parent)?;
 
+        let parent_len = self.nodes[parent].as_ref().unwrap().values.len();
         // no cycle, all is good, insert
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(child) {
             node.parents.push(parent);
-            // reset the credencies when changing the parents
-            node.credencies = None;
-            node.cred_description = Vec::new();
+            // the node does not depend on its new parent yet, so its table is
+            // repeated for every value of the parent
+            if let Some(ref credencies) = node.credencies {
+                node.credencies = Some(reshape::push_axis(credencies, parent_len));
+                node.cred_description = reshape::push_rows(&node.cred_description, parent_len);
+                node.sample_sizes = reshape::push_rows(&node.sample_sizes, parent_len);
+            }
+            if let Some(ref mut rules) = node.rules {
+                rules.push_parent();
+            }
         } else {
             return Err(EdgeError::BadNode);
         }
+        self.refresh_rules(child);
         // also insert as a child to the parent
         let node = self.nodes[parent].as_mut().unwrap();
         if !node.children.contains(&child) {
@@ -198,43 +370,167 @@ impl Dag {
     }
 
     pub fn remove_edge(&mut self, child: usize, parent: usize) {
+        let parent_shape = self
+            .credencies_shape(child)
+            .map(|shape| shape[1..].to_vec())
+            .unwrap_or_default();
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(child) {
-            node.parents.retain(|&v| v != parent);
-            // reset the credencies when changing the parents
-            node.credencies = None;
-            node.cred_description = Vec::new();
+            if let Some(k) = node.parents.iter().position(|&v| v == parent) {
+                node.parents.remove(k);
+                if let Some(ref credencies) = node.credencies {
+                    node.credencies = Some(reshape::remove_axis(credencies, k + 1));
+                    node.cred_description =
+                        reshape::remove_rows(&node.cred_description, &parent_shape, k);
+                    node.sample_sizes =
+                        reshape::remove_sample_sizes(&node.sample_sizes, &parent_shape, k);
+                }
+                if let Some(ref mut rules) = node.rules {
+                    rules.remove_parent(k, &parent_shape);
+                }
+            }
         }
+        self.refresh_rules(child);
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(parent) {
             node.children.retain(|&v| v != child);
         }
     }
 
-    pub fn add_value(&mut self, node: usize, value: String) {
-        let children = if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(node) {
-            node.values.push(value);
-            // reset the credencies when changing the values
-            node.credencies = None;
-            node.cred_description = Vec::new();
-            node.observation = None;
-            node.children.clone()
-        } else {
-            Vec::new()
+    /// Replace the values of a node, reshaping its credencies and the ones of its
+    /// children accordingly. `mapping` gives for each new value the index of the
+    /// old value it corresponds to, or `None` if it is a new value.
+    fn remap_values(&mut self, node: usize, values: Vec<String>, mapping: &[Option<usize>]) {
+        let children = match self.get(node) {
+            Some(n) => n.children.clone(),
+            None => return,
         };
-        // also reset the credencies of the children
-        for child in children {
-            if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(child) {
-                node.credencies = None;
+        // the layout of the children rows depends on the old values, so collect it first
+        let children_shapes: Vec<(usize, usize, Vec<usize>)> = children
+            .iter()
+            .map(|&c| {
+                let shape = self.credencies_shape(c).unwrap();
+                let k = self.get(c).unwrap().parents.iter().position(|&p| p == node);
+                (c, k.unwrap(), shape[1..].to_vec())
+            })
+            .collect();
+
+        let this = self.nodes[node].as_mut().unwrap();
+        this.values = values;
+        if let Some(ref credencies) = this.credencies {
+            this.credencies = Some(reshape::remap_axis(credencies, 0, mapping, 1.0));
+        }
+        if let Some(ref mut rules) = this.rules {
+            rules.remap_values(mapping);
+        }
+        this.observation = this
+            .observation
+            .and_then(|obs| mapping.iter().position(|&m| m == Some(obs)));
+        self.refresh_rules(node);
+
+        for (child, k, parent_shape) in children_shapes {
+            let child_node = self.nodes[child].as_mut().unwrap();
+            if let Some(ref credencies) = child_node.credencies {
+                child_node.credencies = Some(reshape::remap_axis(credencies, k + 1, mapping, 1.0));
+                child_node.cred_description =
+                    reshape::remap_rows(&child_node.cred_description, &parent_shape, k, mapping);
+                child_node.sample_sizes =
+                    reshape::remap_rows(&child_node.sample_sizes, &parent_shape, k, mapping);
             }
+            if let Some(ref mut rules) = child_node.rules {
+                rules.remap_parent(k, mapping);
+            }
+            self.refresh_rules(child);
         }
     }
 
+    /// Expand again the rules of a node after its shape changed
+    fn refresh_rules(&mut self, node: usize) {
+        let shape = match self.credencies_shape(node) {
+            Some(shape) => shape,
+            None => return,
+        };
+        let node = self.nodes[node].as_mut().unwrap();
+        if let Some(ref rules) = node.rules {
+            let (credencies, descriptions) = rules.expand(&shape);
+            node.credencies = Some(credencies);
+            node.cred_description = descriptions;
+        }
+    }
+
+    pub fn add_value(&mut self, node: usize, value: String) {
+        let (values, mapping) = if let Some(node) = self.get(node) {
+            let mut values = node.values.clone();
+            let mut mapping: Vec<_> = (0..values.len()).map(Some).collect();
+            values.push(value);
+            mapping.push(None);
+            (values, mapping)
+        } else {
+            return;
+        };
+        self.remap_values(node, values, &mapping);
+    }
+
     pub fn remove_value(&mut self, node: usize, value_id: usize) {
+        let (values, mapping) = match self.get(node) {
+            Some(node) if value_id < node.values.len() => {
+                let mut values = node.values.clone();
+                let mut mapping: Vec<_> = (0..values.len()).map(Some).collect();
+                values.remove(value_id);
+                mapping.remove(value_id);
+                (values, mapping)
+            }
+            _ => return,
+        };
+        self.remap_values(node, values, &mapping);
+    }
+
+    pub fn rename_value(&mut self, node: usize, value_id: usize, name: String) {
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(node) {
-            node.values.remove(value_id);
-            // reset the credencies when changing the values
-            node.credencies = None;
-            node.cred_description = Vec::new();
-            node.observation = None;
+            if let Some(value) = node.values.get_mut(value_id) {
+                *value = name;
+            }
+        }
+    }
+
+    /// Move a value of a node to a new position, keeping its credencies
+    pub fn move_value(&mut self, node: usize, value_id: usize, to: usize) {
+        let (values, mapping) = match self.get(node) {
+            Some(node) if value_id < node.values.len() && to < node.values.len() => {
+                let mut values = node.values.clone();
+                let mut mapping: Vec<_> = (0..values.len()).map(Some).collect();
+                let value = values.remove(value_id);
+                values.insert(to, value);
+                mapping.remove(value_id);
+                mapping.insert(to, Some(value_id));
+                (values, mapping)
+            }
+            _ => return,
+        };
+        self.remap_values(node, values, &mapping);
+    }
+
+    /// Move a parent of a node to a new position in its parent list, which
+    /// changes the order of the rows of its credency table
+    pub fn move_parent(&mut self, node: usize, parent_pos: usize, to: usize) {
+        let parent_shape = match self.credencies_shape(node) {
+            Some(shape) if parent_pos < shape.len() - 1 && to < shape.len() - 1 => {
+                shape[1..].to_vec()
+            }
+            _ => return,
+        };
+        let mut order: Vec<usize> = (0..parent_shape.len()).collect();
+        order.remove(parent_pos);
+        order.insert(to, parent_pos);
+
+        let node = self.nodes[node].as_mut().unwrap();
+        node.parents = order.iter().map(|&p| node.parents[p]).collect();
+        if let Some(ref credencies) = node.credencies {
+            node.credencies = Some(reshape::permute_parents(credencies, &order));
+            node.cred_description =
+                reshape::permute_rows(&node.cred_description, &parent_shape, &order);
+            node.sample_sizes = reshape::permute_rows(&node.sample_sizes, &parent_shape, &order);
+        }
+        if let Some(ref mut rules) = node.rules {
+            rules.permute_parents(&order);
         }
     }
 
@@ -244,13 +540,20 @@ impl Dag {
         }
     }
 
+    /// The expected shape of the credencies of a node: its number of values
+    /// followed by the number of values of each of its parents.
+    pub fn credencies_shape(&self, node: usize) -> Option<Vec<usize>> {
+        let node = self.get(node)?;
+        let mut shape = vec![node.values.len()];
+        for &p in &node.parents {
+            shape.push(self.nodes[p].as_ref().unwrap().values.len());
+        }
+        Some(shape)
+    }
+
     pub fn set_credencies(&mut self, node: usize, credencies: ArrayD<f32>) -> Result<(), ()> {
         // sanity check, the dimensions of the array must match
-        if let Some(&Some(ref node)) = self.nodes.get(node) {
-            let mut shape = vec![node.values.len()];
-            for &p in &node.parents {
-                shape.push(self.nodes[p].as_ref().unwrap().values.len());
-            }
+        if let Some(shape) = self.credencies_shape(node) {
             if credencies.shape() != &shape[..] {
                 return Err(());
             }
@@ -258,11 +561,31 @@ impl Dag {
 
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(node) {
             node.credencies = Some(credencies);
+            // the full table now takes precedence over any rule
+            node.rules = None;
         }
 
         Ok(())
     }
 
+    /// Set the context-specific rules of a node, or go back to a plain table
+    /// with `None`. The credencies and their descriptions are expanded from
+    /// the rules.
+    pub fn set_rules(&mut self, node: usize, rules: Option<RuleTable>) -> Result<(), ()> {
+        let shape = self.credencies_shape(node).ok_or(())?;
+        let node = self.nodes[node].as_mut().unwrap();
+        if let Some(ref rules) = rules {
+            if !rules.check_shape(&shape) {
+                return Err(());
+            }
+            let (credencies, descriptions) = rules.expand(&shape);
+            node.credencies = Some(credencies);
+            node.cred_description = descriptions;
+        }
+        node.rules = rules;
+        Ok(())
+    }
+
     pub fn set_observation(&mut self, node: usize, observation: Option<usize>) {
         if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(node) {
             node.observation = observation;
@@ -292,13 +615,186 @@ impl Dag {
         }
     }
 
+    /// Set how many cases each row of the credencies is worth, an empty list
+    /// meaning they are not uncertain
+    pub fn set_sample_sizes(&mut self, node: usize, sizes: Vec<f32>) -> Result<(), ()> {
+        let parent_values = self.count_parent_values(node);
+        if !sizes.is_empty() && sizes.len() != parent_values {
+            return Err(());
+        }
+        if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(node) {
+            // only zeroes is the same as not uncertain
+            node.sample_sizes = if sizes.iter().all(|&s| s == 0.0) {
+                Vec::new()
+            } else {
+                sizes
+            };
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// The credencies of a node normalized into conditional probabilities, a
+    /// missing table or a row of only zeroes being treated as uniform
+    pub fn normalized_credencies(&self, node: usize) -> Option<ArrayD<f32>> {
+        let shape = self.credencies_shape(node)?;
+        let mut array = self
+            .get(node)?
+            .credencies
+            .as_ref()
+            .map(|array| array.map(|v| v.max(0.0)))
+            .unwrap_or_else(|| ArrayD::from_elem(IxDyn(&shape), 1.0));
+        for mut row in array.lanes_mut(Axis(0)) {
+            let sum = row.sum();
+            if sum > 0.0 && sum.is_finite() {
+                row.mapv_inplace(|v| v / sum);
+            } else {
+                row.fill(1.0 / shape[0] as f32);
+            }
+        }
+        Some(array)
+    }
+
+    /// Check whether the edge from `parent` to `child` can be reversed: it must
+    /// exist and be the only directed path between these nodes.
+    pub fn check_edge_reversal(&self, child: usize, parent: usize) -> Result<(), EdgeError> {
+        match (self.get(child), self.get(parent)) {
+            (Some(node), Some(_)) if node.parents.contains(&parent) => {}
+            _ => return Err(EdgeError::BadNode),
+        }
+        let mut descendants: Vec<usize> = self.nodes[parent]
+            .as_ref()
+            .unwrap()
+            .children
+            .iter()
+            .copied()
+            .filter(|&c| c != child)
+            .collect();
+        let mut visited = Vec::new();
+        while let Some(id) = descendants.pop() {
+            if id == child {
+                return Err(EdgeError::WouldCycle);
+            }
+            if visited.contains(&id) {
+                continue;
+            }
+            visited.push(id);
+            descendants.extend(&self.nodes[id].as_ref().unwrap().children);
+        }
+        Ok(())
+    }
+
+    /// Reverse the edge from `parent` to `child`, keeping the joint distribution
+    /// unchanged.
+    ///
+    /// Both nodes end up sharing the union of their parents, and their tables are
+    /// recomputed using Bayes' rule. The explanations of these tables are lost.
+    pub fn reverse_edge(&mut self, child: usize, parent: usize) -> Result<(), EdgeError> {
+        self.check_edge_reversal(child, parent)?;
+        let (x, y) = (parent, child);
+        let x_parents = self.get(x).unwrap().parents.clone();
+        let y_parents = self.get(y).unwrap().parents.clone();
+        let y_other_parents: Vec<usize> = y_parents.iter().copied().filter(|&p| p != x).collect();
+
+        let mut new_y_parents = y_other_parents.clone();
+        new_y_parents.extend(x_parents.iter().filter(|p| !y_other_parents.contains(p)));
+        let mut new_x_parents = x_parents.clone();
+        new_x_parents.extend(y_other_parents.iter().filter(|p| !x_parents.contains(p)));
+
+        let old_x = self.normalized_credencies(x).unwrap();
+        let old_y = self.normalized_credencies(y).unwrap();
+        let card = |n: usize| self.get(n).unwrap().values.len();
+        let (x_card, y_card) = (card(x), card(y));
+
+        let mut y_shape = vec![y_card];
+        y_shape.extend(new_y_parents.iter().map(|&p| card(p)));
+        let mut x_shape = vec![x_card];
+        x_shape.extend(new_x_parents.iter().map(|&p| card(p)));
+        x_shape.push(y_card);
+        let mut new_y = ArrayD::from_elem(IxDyn(&y_shape), 0.0);
+        let mut new_x = ArrayD::from_elem(IxDyn(&x_shape), 0.0);
+
+        // new_x_parents contains all the parents of both nodes but themselves, so
+        // iterate over all their values
+        let parents_shape: Vec<usize> = new_x_parents.iter().map(|&p| card(p)).collect();
+        for configuration in parent_configurations(&parents_shape) {
+            let value_of =
+                |n: usize| configuration[new_x_parents.iter().position(|&p| p == n).unwrap()];
+            // joint[x][y] = P(x | pa(x)) P(y | x, pa(y))
+            let mut joint = vec![vec![0.0f32; y_card]; x_card];
+            for (xi, row) in joint.iter_mut().enumerate() {
+                let mut x_idx = vec![xi];
+                x_idx.extend(x_parents.iter().map(|&p| value_of(p)));
+                let px = old_x[IxDyn(&x_idx)];
+                for (yi, v) in row.iter_mut().enumerate() {
+                    let y_idx: Vec<usize> = std::iter::once(yi)
+                        .chain(
+                            y_parents
+                                .iter()
+                                .map(|&p| if p == x { xi } else { value_of(p) }),
+                        )
+                        .collect();
+                    *v = px * old_y[IxDyn(&y_idx)];
+                }
+            }
+            for yi in 0..y_card {
+                // P(y | pa) = sum_x P(x | pa(x)) P(y | x, pa(y))
+                let py: f32 = joint.iter().map(|row| row[yi]).sum();
+                let mut y_idx = vec![yi];
+                y_idx.extend(new_y_parents.iter().map(|&p| value_of(p)));
+                new_y[IxDyn(&y_idx)] = py;
+                // P(x | y, pa) = P(x | pa(x)) P(y | x, pa(y)) / P(y | pa)
+                for (xi, row) in joint.iter().enumerate() {
+                    let mut x_idx = vec![xi];
+                    x_idx.extend(configuration.iter().copied());
+                    x_idx.push(yi);
+                    new_x[IxDyn(&x_idx)] = if py > 0.0 {
+                        row[yi] / py
+                    } else {
+                        1.0 / x_card as f32
+                    };
+                }
+            }
+        }
+
+        // now update the structure of the graph
+        for &p in &new_y_parents {
+            let pnode = self.nodes[p].as_mut().unwrap();
+            if !pnode.children.contains(&y) {
+                pnode.children.push(y);
+            }
+        }
+        for &p in &new_x_parents {
+            let pnode = self.nodes[p].as_mut().unwrap();
+            if !pnode.children.contains(&x) {
+                pnode.children.push(x);
+            }
+        }
+        let xnode = self.nodes[x].as_mut().unwrap();
+        xnode.children.retain(|&c| c != y);
+        new_x_parents.push(y);
+        xnode.parents = new_x_parents;
+        xnode.credencies = Some(new_x);
+        xnode.cred_description = Vec::new();
+        xnode.sample_sizes = Vec::new();
+        xnode.rules = None;
+        let ynode = self.nodes[y].as_mut().unwrap();
+        ynode.children.push(x);
+        ynode.parents = new_y_parents;
+        ynode.credencies = Some(new_y);
+        ynode.cred_description = Vec::new();
+        ynode.sample_sizes = Vec::new();
+        ynode.rules = None;
+        Ok(())
+    }
+
     pub fn get(&self, id: usize) -> Option<&Node> {
         self.nodes.get(id).and_then(|o| o.as_ref())
     }
 
-    fn compact_ids(&self) -> (Vec<usize>, Vec<Option<usize>>) {
-        // Order the nodes of the graph into a topological order for insertion into
-        // loopybayesnet
+    /// The nodes of the graph ordered so that parents come before their children
+    pub fn topological_order(&self) -> Vec<usize> {
         let mut order = Vec::new();
         fn visit(nodes: &[Option<Node>], order: &mut Vec<usize>, n: usize) {
             if order.contains(&n) {
@@ -315,6 +811,13 @@ impl Dag {
             }
         }
         order.reverse();
+        order
+    }
+
+    fn compact_ids(&self) -> (Vec<usize>, Vec<Option<usize>>) {
+        // Order the nodes of the graph into a topological order for insertion into
+        // loopybayesnet
+        let order = self.topological_order();
 
         // a map for reverse indexing the nodes from our indices indices to compacted ones
         let mut map: Vec<Option<usize>> = vec![None; self.nodes.len()];
@@ -327,6 +830,15 @@ impl Dag {
     }
 
     pub fn make_bayesnet(&self) -> Result<(BayesNet, Vec<usize>), ()> {
+        self.make_bayesnet_with(&[])
+    }
+
+    /// Like `make_bayesnet`, but the nodes having a table in `tables`, indexed
+    /// like the nodes, use it instead of their credencies
+    pub fn make_bayesnet_with(
+        &self,
+        tables: &[Option<ArrayD<f32>>],
+    ) -> Result<(BayesNet, Vec<usize>), ()> {
         let (order, map) = self.compact_ids();
         // order now contains a topological ordering of the nodes of the graph,
         // which we will now feed into loopybayesnet
@@ -346,9 +858,10 @@ impl Dag {
                 parent_ids.push(map[p].unwrap());
                 values_count.push(self.nodes[p].as_ref().unwrap().values.len());
             }
-            let log_probas = node
-                .credencies
-                .as_ref()
+            let log_probas = tables
+                .get(n)
+                .and_then(Option::as_ref)
+                .or(node.credencies.as_ref())
                 .map(|array| array.map(|v| v.max(0.0).ln()))
                 .unwrap_or_else(|| {
                     let count = values_count.iter().product();
@@ -374,71 +887,291 @@ impl Dag {
             .filter_map(|(i, n)| n.as_ref().map(|n| (i, n)))
     }
 
+    /// Derive an identifier from a node label, lowercasing it and replacing
+    /// anything that is not alphanumeric by dashes
+    pub(crate) fn slugify(label: &str) -> String {
+        let slug = label
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("-");
+        if slug.is_empty() {
+            "node".into()
+        } else {
+            slug
+        }
+    }
+
+    /// The identifiers of all nodes, indexed like `self.nodes`. Nodes that do not
+    /// have an identifier yet get one derived from their label, not clashing with
+    /// any other.
+    pub(crate) fn node_ids(&self) -> Vec<Option<String>> {
+        let mut ids: Vec<Option<String>> = self
+            .nodes
+            .iter()
+            .map(|n| n.as_ref().map(|n| n.id.clone()))
+            .collect();
+        for (i, node) in self.iter_nodes() {
+            if !node.id.is_empty() {
+                continue;
+            }
+            let base = Dag::slugify(&node.label);
+            let mut candidate = base.clone();
+            let mut suffix = 2;
+            while ids.iter().any(|id| id.as_ref() == Some(&candidate)) {
+                candidate = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+            ids[i] = Some(candidate);
+        }
+        ids
+    }
+
+    /// Give their identifier to all the nodes that don't have one yet, so that
+    /// they remain stable even if their label changes later
+    pub fn assign_ids(&mut self) {
+        let ids = self.node_ids();
+        for (node, id) in self.nodes.iter_mut().zip(ids) {
+            if let (Some(node), Some(id)) = (node, id) {
+                node.id = id;
+            }
+        }
+    }
+
     pub fn to_json(&self) -> String {
-        let (order, map) = self.compact_ids();
-        let mut nodelist: Vec<JsonNode> = Vec::with_capacity(order.len());
+        // nodes are saved in the order of their indices and referenced by their
+        // identifiers, so that saving a loaded file gives back the same file
+        let ids = self.node_ids();
+        let mut nodelist: Vec<JsonNode> = Vec::with_capacity(self.nodes.len());
 
-        for &n in &order {
-            let node = self.nodes[n].as_ref().unwrap();
+        for (i, node) in self.iter_nodes() {
+            // when the node is described by rules, only store them as the full
+            // table can be expanded back from them
+            let (credencies, cred_description) = if node.rules.is_some() {
+                (None, Vec::new())
+            } else {
+                (
+                    node.credencies
+                        .as_ref()
+                        .map(|a| a.iter().map(|&f| Float(f)).collect()),
+                    node.cred_description.clone(),
+                )
+            };
             nodelist.push(JsonNode {
+                id: ids[i].clone(),
                 label: node.label.clone(),
                 values: node.values.clone(),
                 description: node.description.clone(),
                 parents: node
                     .parents
                     .iter()
-                    .map(|&i| map[i].unwrap())
+                    .map(|&p| JsonParent::Id(ids[p].clone().unwrap()))
                     .collect::<Vec<_>>(),
                 observation: node.observation,
-                credencies: node
-                    .credencies
-                    .as_ref()
-                    .map(|a| a.iter().map(|&f| Float(f)).collect()),
-                cred_description: node.cred_description.clone(),
+                credencies,
+                cred_description,
+                sample_sizes: node.sample_sizes.clone(),
+                factor: node.factor,
+                rules: node.rules.as_ref().map(|rules| JsonRuleTable {
+                    rules: rules
+                        .rules
+                        .iter()
+                        .map(|r| JsonRule {
+                            when: r.context.clone(),
+                            credencies: r.credencies.iter().map(|&f| Float(f)).collect(),
+                            description: r.description.clone(),
+                        })
+                        .collect(),
+                    default: rules.default.iter().map(|&f| Float(f)).collect(),
+                    default_description: rules.default_description.clone(),
+                }),
+                position: node.position,
             });
         }
 
-        serde_json::to_string_pretty(&nodelist).unwrap()
+        serde_json::to_string_pretty(&JsonDocument {
+            version: FORMAT_VERSION,
+            metadata: self.metadata.clone(),
+            nodes: nodelist,
+        })
+        .unwrap()
+    }
+
+    /// Load a model from its JSON representation.
+    ///
+    /// If the structure of the graph is invalid, the model cannot be loaded and
+    /// the problems are reported in `DeserError::Invalid`. Otherwise the parts of
+    /// the nodes that are invalid are dropped, and reported along with the model.
+    pub fn from_json(json: &str) -> Result<(Dag, ValidationReport), DeserError> {
+        let value = serde_json::from_str(json).map_err(DeserError::Json)?;
+        let document: JsonDocument =
+            serde_json::from_value(migrate(value)?).map_err(DeserError::Json)?;
+        Dag::from_document(document, ValidationReport::new())
     }
 
-    pub fn from_json(json: &str) -> Result<Dag, DeserError> {
-        let contents: Vec<JsonNode> = serde_json::from_str(json).map_err(DeserError::Json)?;
+    /// Build a model from the contents of a file, validating them. This is shared
+    /// with the other file formats, which first convert their contents into this
+    /// representation and may already have found some problems.
+    pub(crate) fn from_document(
+        document: JsonDocument,
+        mut report: ValidationReport,
+    ) -> Result<(Dag, ValidationReport), DeserError> {
+        let contents = document.nodes;
+        let names: Vec<String> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                if node.label.is_empty() {
+                    format!("#{}", i)
+                } else {
+                    node.label.clone()
+                }
+            })
+            .collect();
 
         let mut dag = Dag::new();
+        dag.metadata = document.metadata;
 
-        for node in &contents {
+        // first insert all the nodes, as parents may be listed after their children
+        for (i, node) in contents.iter().enumerate() {
             let id = dag.insert_node();
             dag.set_label(id, node.label.clone());
-            for &p in &node.parents {
-                dag.add_edge(id, p).map_err(DeserError::Graph)?;
+            if let Some(ref node_id) = node.id {
+                if contents[..i].iter().any(|n| n.id.as_ref() == Some(node_id)) {
+                    report.push(names[i].clone(), Problem::DuplicateId(node_id.clone()));
+                }
+                dag.nodes[id].as_mut().unwrap().id = node_id.clone();
             }
-            for v in &node.values {
+            if node.values.is_empty() {
+                report.push(names[i].clone(), Problem::NoValues);
+            }
+            for (j, v) in node.values.iter().enumerate() {
+                if node.values[..j].contains(v) {
+                    report.push(names[i].clone(), Problem::DuplicateValue(v.clone()));
+                }
                 dag.add_value(id, v.into());
             }
-            dag.set_observation(id, node.observation);
+        }
+
+        // then the edges
+        for (id, node) in contents.iter().enumerate() {
+            for p in &node.parents {
+                let parent = match *p {
+                    JsonParent::Index(i) if i < contents.len() => Some(i),
+                    JsonParent::Index(i) => {
+                        report.push(names[id].clone(), Problem::UnknownParent(i.to_string()));
+                        None
+                    }
+                    JsonParent::Id(ref p) => {
+                        let parent = contents.iter().position(|n| n.id.as_ref() == Some(p));
+                        if parent.is_none() {
+                            report.push(names[id].clone(), Problem::UnknownParent(p.clone()));
+                        }
+                        parent
+                    }
+                };
+                if let Some(parent) = parent {
+                    if let Err(e) = dag.add_edge(id, parent) {
+                        report.push(names[id].clone(), Problem::Edge(e, names[parent].clone()));
+                    }
+                }
+            }
+        }
+
+        // the graph itself cannot be built, give up
+        if report.has_errors() {
+            return Err(DeserError::Invalid(report));
+        }
+
+        // and finally the contents of the nodes
+        for (id, node) in contents.iter().enumerate() {
+            let name = &names[id];
+            match node.observation {
+                Some(obs) if obs >= node.values.len() => report.push(
+                    name.clone(),
+                    Problem::ObservationOutOfRange {
+                        observation: obs,
+                        values: node.values.len(),
+                    },
+                ),
+                obs => dag.set_observation(id, obs),
+            }
             dag.set_description(id, node.description.clone());
-            // ingore bad descriptions
-            let _ = dag.set_cred_descriptions(id, node.cred_description.clone());
+            dag.nodes[id].as_mut().unwrap().position = node.position;
+            dag.nodes[id].as_mut().unwrap().factor = node.factor;
+            // an empty list means no description was written
+            if !node.cred_description.is_empty() {
+                let expected = dag.count_parent_values(id);
+                if node.cred_description.len() == expected {
+                    dag.set_cred_descriptions(id, node.cred_description.clone())
+                        .unwrap();
+                } else {
+                    report.push(
+                        name.clone(),
+                        Problem::DescriptionsCount {
+                            expected,
+                            found: node.cred_description.len(),
+                        },
+                    );
+                }
+            }
+            if !node.sample_sizes.is_empty() {
+                let expected = dag.count_parent_values(id);
+                if node.sample_sizes.len() == expected {
+                    dag.set_sample_sizes(id, node.sample_sizes.clone()).unwrap();
+                } else {
+                    report.push(
+                        name.clone(),
+                        Problem::SampleSizesCount {
+                            expected,
+                            found: node.sample_sizes.len(),
+                        },
+                    );
+                }
+            }
             // and the credencies
             if let Some(ref array) = node.credencies {
-                let mut shape = vec![node.values.len()];
-                for &p in &node.parents {
-                    shape.push(contents[p].values.len());
-                }
-                let array = match ArrayD::from_shape_vec(
-                    IxDyn(&shape),
-                    array.iter().map(|&f| f.into()).collect::<Vec<f32>>(),
-                )
-                .ok()
+                let shape = dag.credencies_shape(id).unwrap();
+                let values = array.iter().map(|&f| f.into()).collect::<Vec<f32>>();
+                if let Some(&v) = values
+                    .iter()
+                    .find(|v| v.is_nan() || (v.is_finite() && **v < 0.0))
                 {
-                    Some(a) => a,
-                    None => continue, // ignore bad arrays
+                    report.push(name.clone(), Problem::NegativeCredency(v));
+                }
+                match ArrayD::from_shape_vec(IxDyn(&shape), values) {
+                    Ok(array) => dag.set_credencies(id, array).unwrap(),
+                    Err(_) => report.push(
+                        name.clone(),
+                        Problem::CredenciesLength {
+                            expected: shape.iter().product(),
+                            found: array.len(),
+                        },
+                    ),
+                }
+            }
+            // the rules take precedence over the full table if any
+            if let Some(ref rules) = node.rules {
+                let rules = RuleTable {
+                    rules: rules
+                        .rules
+                        .iter()
+                        .map(|r| CptRule {
+                            context: r.when.clone(),
+                            credencies: r.credencies.iter().map(|&f| f.into()).collect(),
+                            description: r.description.clone(),
+                        })
+                        .collect(),
+                    default: rules.default.iter().map(|&f| f.into()).collect(),
+                    default_description: rules.default_description.clone(),
                 };
-                // ignore bad arrays
-                let _ = dag.set_credencies(id, array);
+                if dag.set_rules(id, Some(rules)).is_err() {
+                    report.push(name.clone(), Problem::InvalidRules);
+                }
             }
         }
 
-        Ok(dag)
+        Ok((dag, report))
     }
 }
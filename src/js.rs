@@ -6,6 +6,6 @@ extern "C" {
     pub fn graph_render(dot: JsValue, svg: JsValue);
     #[wasm_bindgen(js_name = "mathjax_typeset")]
     pub fn mathjax_typeset();
-    #[wasm_bindgen(js_name = "make_json_download")]
-    pub fn make_json_download(filename: JsValue, text: JsValue);
+    #[wasm_bindgen(js_name = "make_text_download")]
+    pub fn make_text_download(filename: JsValue, mime: JsValue, text: JsValue);
 }
@@ -19,9 +19,9 @@ export function mathjax_typeset() {
     MathJax.Hub.Queue(["Typeset",MathJax.Hub]);
 }
 
-export function make_json_download(filename, text) {
+export function make_text_download(filename, mime, text) {
     var element = document.createElement('a');
-    element.setAttribute('href', 'data:application/json,' + encodeURIComponent(text));
+    element.setAttribute('href', 'data:' + mime + ',' + encodeURIComponent(text));
     element.setAttribute('download', filename);
     element.click();
   }
\ No newline at end of file
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+
+use ndarray::{ArrayD, Axis, IxDyn};
+
+use crate::dataset::Cases;
+use crate::elimination;
+use crate::graph::Dag;
+use crate::random::Rng;
+use crate::rules::parent_configurations;
+
+/*
+ * Learning the tables of a model from case data
+ *
+ * The credencies of a node are estimated from the number of rows where the node
+ * and its parents take each of their values. Maximum likelihood uses these
+ * counts as they are, while Bayesian estimation adds the same pseudo-count to
+ * every cell, which amounts to a symmetric Dirichlet prior over each row and
+ * avoids zero probabilities for the combinations absent from the data.
+ *
+ * Rows where the node or one of its parents is missing are left out of its table.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Estimator {
+    MaximumLikelihood,
+    // the pseudo-count added to every cell of the tables
+    Bayesian(f32),
+}
+
+/// The table of a node learned from data
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearnedTable {
+    pub node: usize,
+    pub credencies: ArrayD<f32>,
+    // the number of rows each row of the table was learned from, in the order of
+    // `parent_configurations`
+    pub rows: Vec<f32>,
+}
+
+/// The nodes whose table can be learned, having a column for themselves and for
+/// all their parents
+pub fn learnable_nodes(dag: &Dag, cases: &Cases) -> Vec<usize> {
+    dag.iter_nodes()
+        .filter(|&(i, node)| {
+            !node.values.is_empty()
+                && cases.columns[i].is_some()
+                && node.parents.iter().all(|&p| cases.columns[p].is_some())
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// The number of rows where the node and its parents take each of their values,
+/// in the shape of its credencies
+pub fn count(dag: &Dag, node: usize, cases: &Cases) -> ArrayD<f32> {
+    let shape = dag.credencies_shape(node).unwrap();
+    let parents = &dag.get(node).unwrap().parents;
+    let mut counts = ArrayD::from_elem(IxDyn(&shape), 0.0);
+    let mut idx = vec![0; shape.len()];
+    'rows: for row in &cases.rows {
+        for (k, &n) in std::iter::once(&node).chain(parents).enumerate() {
+            match row[n] {
+                Some(v) => idx[k] = v,
+                None => continue 'rows,
+            }
+        }
+        counts[IxDyn(&idx)] += 1.0;
+    }
+    counts
+}
+
+/// Turn counts into conditional probabilities, the rows without any count being
+/// uniform
+pub fn estimate(counts: &ArrayD<f32>, estimator: Estimator) -> ArrayD<f32> {
+    let mut credencies = match estimator {
+        Estimator::MaximumLikelihood => counts.clone(),
+        Estimator::Bayesian(pseudo_count) => counts.mapv(|c| c + pseudo_count),
+    };
+    let nval = credencies.shape()[0];
+    for mut row in credencies.lanes_mut(Axis(0)) {
+        let sum = row.sum();
+        if sum > 0.0 {
+            row.mapv_inplace(|v| v / sum);
+        } else {
+            row.fill(1.0 / nval as f32);
+        }
+    }
+    credencies
+}
+
+/// The number of rows counted for each row of a table, in the order of
+/// `parent_configurations`
+pub fn row_totals(counts: &ArrayD<f32>) -> Vec<f32> {
+    // the rows are laid out in the order of `parent_configurations` once the
+    // values of the node are summed out
+    counts.sum_axis(Axis(0)).iter().copied().collect()
+}
+
+/// Learn the tables of the given nodes, which must be learnable
+pub fn learn_parameters(
+    dag: &Dag,
+    cases: &Cases,
+    nodes: &[usize],
+    estimator: Estimator,
+) -> Vec<LearnedTable> {
+    nodes
+        .iter()
+        .map(|&node| {
+            let counts = count(dag, node, cases);
+            LearnedTable {
+                node,
+                credencies: estimate(&counts, estimator),
+                rows: row_totals(&counts),
+            }
+        })
+        .collect()
+}
+
+/*
+ * Expectation-maximization
+ *
+ * When some cells are missing, or some nodes have no column at all, the tables
+ * are learned by alternating two steps: the expected counts of each table are
+ * computed from the posterior of the missing values given the current tables,
+ * then the tables are estimated from these counts. The log-likelihood of the
+ * data increases at each iteration, until it converges to a local maximum.
+ *
+ * The tables of the frozen nodes are kept as they are. The tables of the latent
+ * nodes and of their children are slightly perturbed before starting: with the
+ * symmetric tables a new node usually has, the values of a latent node could
+ * never be told apart.
+ */
+
+pub const EM_MAX_ITERATIONS: usize = 100;
+// the relative change of the log-likelihood under which it has converged
+pub const EM_TOLERANCE: f64 = 1e-6;
+const EM_SEED: u64 = 42;
+// the relative amplitude of the perturbation of the tables
+const EM_PERTURBATION: f32 = 0.1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmReport {
+    // the log-likelihood of the data at each iteration, in natural log
+    pub log_likelihoods: Vec<f64>,
+    pub converged: bool,
+    // the rows that are impossible given the tables, which are left out
+    pub impossible_rows: usize,
+}
+
+/// Learn the tables of the given nodes by expectation-maximization, the other
+/// nodes keeping their tables. All the nodes must have values.
+pub fn expectation_maximization(
+    dag: &Dag,
+    cases: &Cases,
+    nodes: &[usize],
+    estimator: Estimator,
+) -> (Vec<LearnedTable>, EmReport) {
+    // identical rows only need to be computed once
+    let mut unique: BTreeMap<&[Option<usize>], f64> = BTreeMap::new();
+    for row in &cases.rows {
+        *unique.entry(&row[..]).or_insert(0.0) += 1.0;
+    }
+
+    let mut tables = elimination::tables(dag);
+    let mut rng = Rng::new(EM_SEED);
+    let latent = |n: usize| cases.columns[n].is_none();
+    for &node in nodes {
+        if latent(node) || dag.get(node).unwrap().parents.iter().any(|&p| latent(p)) {
+            let table = tables[node].as_mut().unwrap();
+            table.mapv_inplace(|p| {
+                p * (1.0 + EM_PERTURBATION * (2.0 * rng.next_f64() as f32 - 1.0))
+            });
+            normalize(table);
+        }
+    }
+
+    let mut report = EmReport {
+        log_likelihoods: Vec::new(),
+        converged: false,
+        impossible_rows: 0,
+    };
+    let mut learned = Vec::new();
+    for _ in 0..EM_MAX_ITERATIONS {
+        let mut counts: Vec<ArrayD<f32>> = nodes
+            .iter()
+            .map(|&n| ArrayD::zeros(IxDyn(&dag.credencies_shape(n).unwrap())))
+            .collect();
+        let mut log_likelihood = 0.0;
+        report.impossible_rows = 0;
+        for (&row, &weight) in &unique {
+            let likelihood = elimination::joint(dag, &tables, row, &[]).total();
+            if likelihood <= 0.0 {
+                report.impossible_rows += weight as usize;
+                continue;
+            }
+            log_likelihood += weight * likelihood.ln();
+            for (&node, counts) in nodes.iter().zip(counts.iter_mut()) {
+                let family: Vec<usize> = std::iter::once(node)
+                    .chain(dag.get(node).unwrap().parents.iter().copied())
+                    .collect();
+                let joint = elimination::joint(dag, &tables, row, &family);
+                let mut idx: Vec<usize> = family.iter().map(|&n| row[n].unwrap_or(0)).collect();
+                for assignment in parent_configurations(&joint.cards) {
+                    for (&n, &v) in joint.nodes.iter().zip(&assignment) {
+                        idx[family.iter().position(|&m| m == n).unwrap()] = v;
+                    }
+                    let p = joint.value(&joint.nodes, &assignment) / likelihood;
+                    counts[IxDyn(&idx)] += (weight * p) as f32;
+                }
+            }
+        }
+
+        learned = nodes
+            .iter()
+            .zip(&counts)
+            .map(|(&node, counts)| LearnedTable {
+                node,
+                credencies: estimate(counts, estimator),
+                rows: row_totals(counts),
+            })
+            .collect();
+        for table in &learned {
+            tables[table.node] = Some(table.credencies.clone());
+        }
+
+        let previous = report.log_likelihoods.last().copied();
+        report.log_likelihoods.push(log_likelihood);
+        if let Some(previous) = previous {
+            if (log_likelihood - previous).abs() <= EM_TOLERANCE * previous.abs() {
+                report.converged = true;
+                break;
+            }
+        }
+    }
+    (learned, report)
+}
+
+fn normalize(table: &mut ArrayD<f32>) {
+    for mut row in table.lanes_mut(Axis(0)) {
+        let sum = row.sum();
+        if sum > 0.0 {
+            row.mapv_inplace(|v| v / sum);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+    use crate::formats::text;
+
+    fn load(model: &str, data: &str) -> (Dag, Cases) {
+        let (dag, _) = text::read(model).unwrap();
+        let cases = Dataset::parse(data).unwrap().match_nodes(&dag);
+        (dag, cases)
+    }
+
+    fn assert_close(found: &ArrayD<f32>, expected: &[f32]) {
+        assert_eq!(found.len(), expected.len());
+        for (f, e) in found.iter().zip(expected) {
+            assert!((f - e).abs() < 1e-6, "{} instead of {}", f, e);
+        }
+    }
+
+    #[test]
+    fn counts_and_estimates() {
+        let (dag, cases) = load(
+            "node a { values y, n }\nnode c { values t, f }\na -> c",
+            "a,c\ny,t\ny,t\ny,f\nn,\nn,f\n?,t\n",
+        );
+        // the rows missing the node or its parent are not counted
+        let counts = count(&dag, 1, &cases);
+        assert_eq!(counts.shape(), &[2, 2]);
+        assert_close(&counts, &[2.0, 0.0, 1.0, 1.0]);
+        assert_eq!(row_totals(&counts), vec![3.0, 1.0]);
+        assert_close(
+            &estimate(&counts, Estimator::MaximumLikelihood),
+            &[2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0],
+        );
+        assert_close(
+            &estimate(&counts, Estimator::Bayesian(1.0)),
+            &[3.0 / 5.0, 1.0 / 3.0, 2.0 / 5.0, 2.0 / 3.0],
+        );
+        let counts = count(&dag, 0, &cases);
+        assert_close(&counts, &[3.0, 2.0]);
+        assert_close(
+            &estimate(&counts, Estimator::MaximumLikelihood),
+            &[0.6, 0.4],
+        );
+    }
+
+    #[test]
+    fn rows_follow_parent_configurations() {
+        let a = ["y", "n"];
+        let b = ["p", "q", "r"];
+        // the k-th configuration of the parents appears k + 1 times
+        let mut data = String::from("a,b,c\n");
+        for (k, configuration) in parent_configurations(&[2, 3]).enumerate() {
+            for _ in 0..=k {
+                data += &format!("{},{},t\n", a[configuration[0]], b[configuration[1]]);
+            }
+        }
+        let (dag, cases) = load(
+            "node a { values y, n }\nnode b { values p, q, r }\nnode c { values t, f }\na, b -> c",
+            &data,
+        );
+        let counts = count(&dag, 2, &cases);
+        assert_eq!(row_totals(&counts), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        // a row never seen is uniform
+        let tables = learn_parameters(&dag, &cases, &[2], Estimator::MaximumLikelihood);
+        let credencies = &tables[0].credencies;
+        assert_eq!(credencies[IxDyn(&[0, 1, 2])], 1.0);
+        assert_eq!(credencies[IxDyn(&[1, 1, 2])], 0.0);
+        assert_eq!(tables[0].rows, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn em_on_complete_data() {
+        let (dag, cases) = load(
+            "node a { values y, n }\nnode b { values p, q, r }\nnode c { values t, f }\na -> c\nb -> c",
+            "a,b,c\ny,p,t\ny,q,f\nn,r,t\nn,p,t\ny,p,f\nn,q,f\ny,r,t\n",
+        );
+        let nodes = [0, 1, 2];
+        for &estimator in &[Estimator::MaximumLikelihood, Estimator::Bayesian(1.0)] {
+            let expected = learn_parameters(&dag, &cases, &nodes, estimator);
+            let (learned, report) = expectation_maximization(&dag, &cases, &nodes, estimator);
+            assert!(report.converged);
+            assert_eq!(report.impossible_rows, 0);
+            for (learned, expected) in learned.iter().zip(&expected) {
+                assert_eq!(learned.node, expected.node);
+                assert_close(
+                    &learned.credencies,
+                    &expected.credencies.iter().copied().collect::<Vec<_>>(),
+                );
+                assert_eq!(learned.rows, expected.rows);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_without_counts() {
+        let (dag, cases) = load(
+            "node a { values y, n }\nnode c { values t, f, u }\na -> c",
+            "a,c\ny,t\n",
+        );
+        let counts = count(&dag, 1, &cases);
+        let credencies = estimate(&counts, Estimator::MaximumLikelihood);
+        for v in 0..3 {
+            assert!((credencies[IxDyn(&[v, 1])] - 1.0 / 3.0).abs() < 1e-6);
+        }
+    }
+}
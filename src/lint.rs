@@ -0,0 +1,101 @@
+use ndarray::IxDyn;
+
+use crate::{graph::Dag, rules::parent_configurations};
+
+/*
+ * Health report of a model
+ *
+ * Lists the parts of the model that are likely mistakes or that make the
+ * inference brittle, so that they can be reviewed.
+ */
+
+/// Values with a probability lower than this make their row nearly deterministic
+pub const NEAR_DETERMINISTIC: f32 = 0.001;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LintKind {
+    NoValues,
+    UnsetCredencies,
+    ZeroRow(Vec<usize>),
+    NearDeterministic {
+        row: Vec<usize>,
+        value: usize,
+        probability: f32,
+    },
+    Orphan,
+    DuplicateLabel,
+    UnexplainedRows(Vec<Vec<usize>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub node: usize,
+    pub kind: LintKind,
+}
+
+pub fn lint(dag: &Dag) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let node_count = dag.iter_nodes().count();
+    for (id, node) in dag.iter_nodes() {
+        let mut push = |kind| lints.push(Lint { node: id, kind });
+        if dag
+            .iter_nodes()
+            .any(|(other, n)| other != id && n.label == node.label)
+        {
+            push(LintKind::DuplicateLabel);
+        }
+        if node_count > 1 && node.parents.is_empty() && node.children.is_empty() {
+            push(LintKind::Orphan);
+        }
+        if node.values.is_empty() {
+            push(LintKind::NoValues);
+            continue;
+        }
+        let credencies = match node.credencies {
+            Some(ref credencies) => credencies,
+            None => {
+                push(LintKind::UnsetCredencies);
+                continue;
+            }
+        };
+        let shape = dag.credencies_shape(id).unwrap();
+        let mut unexplained = Vec::new();
+        for (i, configuration) in parent_configurations(&shape[1..]).enumerate() {
+            let mut idx = vec![0];
+            idx.extend(configuration.iter().copied());
+            let row: Vec<f32> = (0..shape[0])
+                .map(|v| {
+                    idx[0] = v;
+                    credencies[IxDyn(&idx)].max(0.0)
+                })
+                .collect();
+            let sum: f32 = row.iter().sum();
+            if sum <= 0.0 {
+                push(LintKind::ZeroRow(configuration.clone()));
+            } else if row.len() > 1 {
+                for (value, v) in row.iter().enumerate() {
+                    let probability = v / sum;
+                    if probability < NEAR_DETERMINISTIC {
+                        push(LintKind::NearDeterministic {
+                            row: configuration.clone(),
+                            value,
+                            probability,
+                        });
+                    }
+                }
+            }
+            if node
+                .cred_description
+                .get(i)
+                .map(|d| d.trim().is_empty())
+                .unwrap_or(true)
+            {
+                unexplained.push(configuration);
+            }
+        }
+        if !unexplained.is_empty() {
+            push(LintKind::UnexplainedRows(unexplained));
+        }
+    }
+    lints
+}
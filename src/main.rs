@@ -1,15 +1,30 @@
 #![recursion_limit = "256"]
 
+mod batch;
+mod data;
+mod dataset;
 mod draw;
 mod editor;
+mod elimination;
+mod evaluation;
+mod formats;
 mod graph;
 mod i18n;
 mod js;
+mod learning;
+mod lint;
 mod markdown;
 mod model;
+mod random;
 mod render;
+mod reshape;
 mod results;
+mod rules;
+mod sampling;
+mod structure;
 mod ui;
+mod uncertainty;
+mod validation;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Page {
@@ -17,7 +32,12 @@ pub enum Page {
     NodeEdit(usize),
     ComputeBeliefs,
     MutualInformation(Option<usize>),
-    LoadJson,
+    Metadata,
+    Health,
+    Text,
+    Data,
+    Load,
+    Export,
     LoadExample,
     Help,
 }
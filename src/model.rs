@@ -8,9 +8,24 @@ use web_sys::HtmlSelectElement;
 use yew::{html, Component, Context, Html};
 
 use crate::{
-    graph::{Dag, DeserError},
+    batch,
+    dataset::{Cases, Dataset},
+    evaluation::{self, Evaluation},
+    formats::{
+        csv::{self, CellError},
+        text, uai, Format,
+    },
+    graph::{Dag, DeserError, Metadata},
     i18n::Lang,
-    lang, Page,
+    lang,
+    learning::{self, EmReport, Estimator, LearnedTable},
+    random::Rng,
+    rules::RuleTable,
+    sampling::{self, Sampling},
+    structure::{self, Classifier, Constraints, Score, Search, StructureReport},
+    uncertainty,
+    validation::ValidationReport,
+    Page,
 };
 
 #[derive(Clone, Debug)]
@@ -19,6 +34,7 @@ pub enum Msg {
     AddNode,
     DuplicateNode(usize),
     RemoveNode(usize),
+    AbsorbNode(usize),
     SetLabel {
         node: usize,
         label: String,
@@ -31,6 +47,16 @@ pub enum Msg {
         node: usize,
         value_id: usize,
     },
+    RenameValue {
+        node: usize,
+        value_id: usize,
+        name: String,
+    },
+    MoveValue {
+        node: usize,
+        value_id: usize,
+        to: usize,
+    },
     AddParent {
         node: usize,
         parent_id: usize,
@@ -39,6 +65,15 @@ pub enum Msg {
         node: usize,
         parent_id: usize,
     },
+    MoveParent {
+        node: usize,
+        parent_pos: usize,
+        to: usize,
+    },
+    ReverseEdge {
+        node: usize,
+        parent_id: usize,
+    },
     SetDesc {
         node: usize,
         desc: String,
@@ -51,15 +86,61 @@ pub enum Msg {
         node: usize,
         credencies: ArrayD<f32>,
         descriptions: Vec<String>,
+        sample_sizes: Vec<f32>,
     },
+    UpdateRules {
+        node: usize,
+        rules: Option<RuleTable>,
+    },
+    ImportTable {
+        node: usize,
+        text: String,
+    },
+    ExportTable(usize),
+    // the problems of a block pasted in a table, their line and column being the
+    // row and value of the table where the cell was pasted
+    PasteErrors {
+        node: usize,
+        errors: Vec<CellError>,
+    },
+    SetMetadata(Metadata),
     MoveToPage(Page),
     Reset,
-    LoadJson(String),
+    Load(Format, String),
+    LoadEvidence(String),
+    ConfirmLoad,
     LoadExample(String),
     ShowHelp(String),
     SetBeliefsDisplay(BeliefsDisplay),
     SetLang(String),
-    Export,
+    Export(Format),
+    SetSource(String),
+    LoadDataset(String),
+    SetEstimator(Estimator),
+    LearnParameters,
+    ToggleFrozen(usize),
+    RunEm,
+    SetScore(Score),
+    SetSearch(Search),
+    AddConstraint {
+        parent: usize,
+        child: usize,
+        required: bool,
+    },
+    RemoveConstraint {
+        parent: usize,
+        child: usize,
+    },
+    LearnStructure,
+    SetSampleCount(usize),
+    SetSampling(Sampling),
+    SampleCases,
+    ToggleTarget(usize),
+    DownloadPosteriors,
+    SetEvaluationTarget(Option<usize>),
+    Evaluate,
+    SetClassNode(Option<usize>),
+    BuildClassifier(Classifier),
 }
 
 impl From<Option<Msg>> for Msg {
@@ -93,16 +174,125 @@ pub struct BayesOMatic {
     pub(crate) dag: Dag,
     pub(crate) page: Page,
     pub(crate) load_error: Option<DeserError>,
+    pub(crate) pending_load: Option<(Dag, ValidationReport)>,
+    // the model as written in the text editor, which may not be valid
+    pub(crate) source: String,
+    pub(crate) source_error: Option<DeserError>,
+    pub(crate) source_report: ValidationReport,
+    // the problems of the last table imported in the node editor
+    pub(crate) table_errors: Option<(usize, Vec<CellError>)>,
+    // the problems of the last block pasted in a table, as in `Msg::PasteErrors`
+    pub(crate) paste_errors: Option<(usize, Vec<CellError>)>,
+    // the case data to learn from
+    pub(crate) dataset: Option<Dataset>,
+    pub(crate) dataset_empty: bool,
+    pub(crate) estimator: Estimator,
+    // the nodes whose table is kept when learning
+    pub(crate) frozen: Vec<usize>,
+    // the number of tables learned by the last run
+    pub(crate) learned: Option<usize>,
+    pub(crate) em_report: Option<EmReport>,
+    pub(crate) score: Score,
+    pub(crate) search: Search,
+    // constraints on the edges between the nodes of `Dataset::to_dag`
+    pub(crate) constraints: Constraints,
+    pub(crate) structure_report: Option<StructureReport>,
+    // the required edge that made a cycle
+    pub(crate) structure_error: Option<(usize, usize)>,
+    pub(crate) sample_count: usize,
+    pub(crate) sampling: Sampling,
+    // the number of cases kept and drawn by the last sampling
+    pub(crate) sample_report: Option<(usize, usize)>,
+    // the nodes whose posteriors are computed for each row of the dataset
+    pub(crate) targets: Vec<usize>,
+    // the number of impossible rows in the last posteriors computed
+    pub(crate) batch_report: Option<usize>,
+    pub(crate) evaluation_target: Option<usize>,
+    pub(crate) evaluation: Option<Evaluation>,
+    // the node of `Dataset::to_dag` to build a classifier of
+    pub(crate) class_node: Option<usize>,
     pub(crate) beliefs: Option<Vec<(LogProbVector, usize)>>,
+    // the interval around the belief of each value of each node, indexed like
+    // the nodes, when some credencies are uncertain
+    pub(crate) intervals: Option<Vec<Vec<(f64, f64)>>>,
     pub(crate) mutual_info: Option<Vec<(usize, f32)>>,
     pub(crate) beliefs_display: BeliefsDisplay,
     pub help_contents: Option<String>,
     pub(crate) lang: Lang,
 }
 
+/// The current date, as stored in the metadata of new models
+fn today() -> String {
+    let now = js_sys::Date::new_0();
+    format!(
+        "{:04}-{:02}-{:02}",
+        now.get_full_year(),
+        now.get_month() + 1,
+        now.get_date()
+    )
+}
+
+/// An empty model, with its creation date set
+fn new_dag() -> Dag {
+    let mut dag = Dag::new();
+    dag.set_metadata(Metadata {
+        created: today(),
+        ..Metadata::default()
+    });
+    dag
+}
+
 impl BayesOMatic {
+    /// Replace the tables of the nodes by the ones learned from data, each row
+    /// being worth the cases it was learned from
+    fn apply_learned(&mut self, tables: &[LearnedTable]) {
+        for table in tables {
+            let descriptions = table
+                .rows
+                .iter()
+                .map(|&count| lang!(self.lang, "learned-from", count = count.round() as usize))
+                .collect();
+            let values = table.credencies.shape()[0] as f32;
+            // the pseudo-counts are worth as many cases
+            let prior = match self.estimator {
+                Estimator::Bayesian(pseudo_count) => pseudo_count * values,
+                Estimator::MaximumLikelihood => 0.0,
+            };
+            let sample_sizes = table
+                .rows
+                .iter()
+                .map(|&count| {
+                    if count + prior > 0.0 {
+                        count + prior
+                    } else {
+                        // a row learned from nothing is worth one case per value,
+                        // all of its probabilities being equally likely
+                        values
+                    }
+                })
+                .collect();
+            self.dag
+                .set_credencies(table.node, table.credencies.clone())
+                .unwrap();
+            self.dag
+                .set_cred_descriptions(table.node, descriptions)
+                .unwrap();
+            self.dag.set_sample_sizes(table.node, sample_sizes).unwrap();
+        }
+        self.learned = Some(tables.len());
+    }
+
     fn compute_beliefs(&self) -> Option<Vec<(LogProbVector, usize)>> {
-        let (mut bayesnet, mapping) = match self.dag.make_bayesnet() {
+        self.compute_beliefs_with(&[])
+    }
+
+    /// The beliefs when the nodes having a table in `tables` use it instead of
+    /// their credencies
+    fn compute_beliefs_with(
+        &self,
+        tables: &[Option<ArrayD<f32>>],
+    ) -> Option<Vec<(LogProbVector, usize)>> {
+        let (mut bayesnet, mapping) = match self.dag.make_bayesnet_with(tables) {
             Ok(v) => v,
             Err(()) => {
                 // beliefs cannnot be computed,
@@ -122,6 +312,119 @@ impl BayesOMatic {
         Some(beliefs.into_iter().zip(mapping.into_iter()).collect())
     }
 
+    /// The interval around the belief of each value of each node, from the
+    /// beliefs computed with tables drawn according to the sample sizes
+    fn compute_intervals(&self) -> Option<Vec<Vec<(f64, f64)>>> {
+        if !uncertainty::is_uncertain(&self.dag) {
+            return None;
+        }
+        let mut rng = uncertainty::rng();
+        // the draws of the belief of each value of each node
+        let mut draws: Vec<Vec<Vec<f64>>> = vec![Vec::new(); self.dag.node_ids().len()];
+        for _ in 0..uncertainty::INTERVAL_SAMPLES {
+            let tables = uncertainty::sample_tables(&self.dag, &mut rng);
+            for (belief, id) in self.compute_beliefs_with(&tables)? {
+                let probabilities = belief.as_probabilities();
+                // a drawn table can make the observations impossible
+                if !probabilities.iter().all(|p| p.is_finite()) {
+                    continue;
+                }
+                draws[id].resize(probabilities.len(), Vec::new());
+                for (value, &p) in draws[id].iter_mut().zip(probabilities.iter()) {
+                    value.push(f64::from(p));
+                }
+            }
+        }
+        Some(
+            draws
+                .iter_mut()
+                .map(|values| {
+                    values
+                        .iter_mut()
+                        .map(|d| uncertainty::interval(d))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Replace the model, forgetting the choices made on the nodes of the old one
+    fn set_dag(&mut self, dag: Dag) {
+        self.dag = dag;
+        // the nodes of the new model are not the ones these refer to
+        self.frozen.clear();
+        self.targets.clear();
+        self.evaluation_target = None;
+        self.evaluation = None;
+    }
+
+    /// Replace the model by one edited as text. The choices made on the nodes
+    /// are kept as long as the text has the same nodes, in the same order.
+    fn set_dag_from_source(&mut self, dag: Dag) {
+        let ids = |dag: &Dag| {
+            dag.iter_nodes()
+                .map(|(i, node)| (i, node.id.clone()))
+                .collect::<Vec<_>>()
+        };
+        if ids(&dag) == ids(&self.dag) {
+            self.dag = dag;
+        } else {
+            self.set_dag(dag);
+        }
+    }
+
+    /// Replace the model by one built from the dataset, learning all its tables
+    fn replace_with_learned(&mut self, mut dag: Dag, cases: &Cases) {
+        let nodes: Vec<usize> = dag.iter_nodes().map(|(i, _)| i).collect();
+        let tables = learning::learn_parameters(&dag, cases, &nodes, self.estimator);
+        dag.set_metadata(Metadata {
+            created: today(),
+            ..Metadata::default()
+        });
+        self.set_dag(dag);
+        self.apply_learned(&tables);
+        self.em_report = None;
+        self.structure_report = None;
+        self.structure_error = None;
+    }
+
+    /// Predict the target in each row of the cases from the other cells of the
+    /// row, with the same inference as the beliefs page, and compare with the
+    /// value of the target in the row
+    fn evaluate_target(&mut self, cases: &Cases, target: usize) -> Evaluation {
+        let observations: Vec<(usize, Option<usize>)> = self
+            .dag
+            .iter_nodes()
+            .map(|(i, node)| (i, node.observation))
+            .collect();
+        let mut predictions = Vec::with_capacity(cases.rows.len());
+        for row in &cases.rows {
+            for &(i, _) in &observations {
+                self.dag
+                    .set_observation(i, if i == target { None } else { row[i] });
+            }
+            // the beliefs are not finite when the observations are impossible
+            let prediction = self.compute_beliefs().and_then(|beliefs| {
+                let (belief, _) = beliefs.into_iter().find(|&(_, id)| id == target)?;
+                let probabilities: Vec<f64> = belief
+                    .as_probabilities()
+                    .iter()
+                    .map(|&p| f64::from(p))
+                    .collect();
+                if probabilities.iter().all(|p| p.is_finite()) {
+                    Some(probabilities)
+                } else {
+                    None
+                }
+            });
+            predictions.push((prediction, row[target]));
+        }
+        for (i, observation) in observations {
+            self.dag.set_observation(i, observation);
+        }
+        evaluation::evaluate(self.dag.get(target).unwrap().values.len(), &predictions)
+    }
+
     fn compute_mutual_info(&mut self, id: usize) -> Option<Vec<(usize, f32)>> {
         if self.dag.get(id).unwrap().observation.is_some() {
             return None;
@@ -250,7 +553,7 @@ impl BayesOMatic {
             match response.text().await {
                 Ok(data) => {
                     weblog::console_log!(format!("Loading example {}.", name));
-                    Msg::LoadJson(data)
+                    Msg::Load(Format::Json, data)
                 }
                 Err(e) => {
                     weblog::console_log!(format!("Failed to load example {}: {}", name, e));
@@ -267,10 +570,36 @@ impl Component for BayesOMatic {
 
     fn create(_: &Context<Self>) -> Self {
         BayesOMatic {
-            dag: Dag::new(),
+            dag: new_dag(),
             page: Page::Idle,
             load_error: None,
+            pending_load: None,
+            source: String::new(),
+            source_error: None,
+            source_report: ValidationReport::new(),
+            table_errors: None,
+            paste_errors: None,
+            dataset: None,
+            dataset_empty: false,
+            estimator: Estimator::Bayesian(1.0),
+            frozen: Vec::new(),
+            learned: None,
+            em_report: None,
+            score: Score::Bic,
+            search: Search::Tabu,
+            constraints: Constraints::default(),
+            structure_report: None,
+            structure_error: None,
+            sample_count: 1000,
+            sampling: Sampling::Forward,
+            sample_report: None,
+            targets: Vec::new(),
+            batch_report: None,
+            evaluation_target: None,
+            evaluation: None,
+            class_node: None,
             beliefs: None,
+            intervals: None,
             mutual_info: None,
             beliefs_display: BeliefsDisplay::RawBeliefs,
             help_contents: None,
@@ -296,6 +625,10 @@ impl Component for BayesOMatic {
                 self.dag.remove_node(id);
                 self.page = Page::Idle;
             }
+            Msg::AbsorbNode(id) => {
+                self.dag.absorb_node(id);
+                self.page = Page::Idle;
+            }
             Msg::SetLabel { node, label } => {
                 self.dag.set_label(node, label);
             }
@@ -305,12 +638,34 @@ impl Component for BayesOMatic {
             Msg::DelValue { node, value_id } => {
                 self.dag.remove_value(node, value_id);
             }
+            Msg::RenameValue {
+                node,
+                value_id,
+                name,
+            } => {
+                if !name.is_empty() {
+                    self.dag.rename_value(node, value_id, name);
+                }
+            }
+            Msg::MoveValue { node, value_id, to } => {
+                self.dag.move_value(node, value_id, to);
+            }
             Msg::AddParent { node, parent_id } => {
                 self.dag.add_edge(node, parent_id).unwrap();
             }
             Msg::DelParent { node, parent_id } => {
                 self.dag.remove_edge(node, parent_id);
             }
+            Msg::MoveParent {
+                node,
+                parent_pos,
+                to,
+            } => {
+                self.dag.move_parent(node, parent_pos, to);
+            }
+            Msg::ReverseEdge { node, parent_id } => {
+                self.dag.reverse_edge(node, parent_id).unwrap();
+            }
             Msg::SetDesc { node, desc } => {
                 self.dag.set_description(node, desc);
                 redraw = false;
@@ -322,14 +677,54 @@ impl Component for BayesOMatic {
                 node,
                 credencies,
                 descriptions,
+                sample_sizes,
             } => {
                 self.dag.set_credencies(node, credencies).unwrap();
                 self.dag.set_cred_descriptions(node, descriptions).unwrap();
+                self.dag.set_sample_sizes(node, sample_sizes).unwrap();
+                // only redraw to hide the problems of an earlier import or paste
+                let import_errors = self.table_errors.take().is_some();
+                redraw = self.paste_errors.take().is_some() || import_errors;
+            }
+            Msg::UpdateRules { node, rules } => {
+                self.dag.set_rules(node, rules).unwrap();
+            }
+            Msg::ImportTable { node, text } => {
+                let explanation = lang!(self.lang, "explanation");
+                match csv::read_table(&self.dag, node, &text, &explanation) {
+                    Ok((credencies, descriptions)) => {
+                        self.dag.set_rules(node, None).unwrap();
+                        self.dag.set_credencies(node, credencies).unwrap();
+                        self.dag.set_cred_descriptions(node, descriptions).unwrap();
+                        self.table_errors = None;
+                    }
+                    Err(errors) => self.table_errors = Some((node, errors)),
+                }
+            }
+            Msg::PasteErrors { node, errors } => {
+                self.paste_errors = Some((node, errors));
+            }
+            Msg::ExportTable(node) => {
+                let label = &self.dag.get(node).unwrap().label;
+                let explanation = lang!(self.lang, "explanation");
+                crate::js::make_text_download(
+                    JsValue::from_str(&format!("{}.csv", label)),
+                    JsValue::from_str("text/csv"),
+                    JsValue::from_str(&csv::write_table(&self.dag, node, &explanation)),
+                );
                 redraw = false;
             }
+            Msg::SetMetadata(metadata) => {
+                self.dag.set_metadata(metadata);
+            }
             Msg::MoveToPage(page) => {
                 if page == Page::ComputeBeliefs {
                     self.beliefs = self.compute_beliefs();
+                    self.intervals = self.compute_intervals();
+                } else if page == Page::Text {
+                    self.source = text::write(&self.dag);
+                    self.source_error = None;
+                    self.source_report = ValidationReport::new();
                 } else if page == Page::Help {
                     if self.help_contents.is_none() {
                         ctx.link().send_future(self.load_help())
@@ -353,22 +748,74 @@ impl Component for BayesOMatic {
                 }
                 self.page = page;
                 self.load_error = None;
+                self.pending_load = None;
+                self.table_errors = None;
+                self.paste_errors = None;
+                self.learned = None;
+                self.em_report = None;
+                self.structure_report = None;
+                self.structure_error = None;
+                self.sample_report = None;
+                self.batch_report = None;
+                self.evaluation = None;
             }
             Msg::Reset => {
-                self.dag = Dag::new();
+                self.set_dag(new_dag());
                 self.load_error = None;
+                self.pending_load = None;
                 self.page = Page::Idle;
             }
-            Msg::LoadJson(json) => match Dag::from_json(&json) {
-                Ok(dag) => {
-                    self.dag = dag;
-                    self.page = Page::Idle;
-                    self.load_error = None;
+            Msg::Load(format, text) => {
+                self.load_error = None;
+                self.pending_load = None;
+                match format.read(&text) {
+                    Ok((dag, report)) if report.is_empty() => {
+                        self.set_dag(dag);
+                        self.page = Page::Idle;
+                    }
+                    Ok((dag, report)) => {
+                        // let the user review the problems before loading
+                        self.pending_load = Some((dag, report));
+                        self.page = Page::Load;
+                    }
+                    Err(e) => {
+                        self.load_error = Some(e);
+                        self.page = Page::Load;
+                    }
                 }
-                Err(e) => {
-                    self.load_error = Some(e);
+            }
+            Msg::LoadEvidence(text) => {
+                self.load_error = None;
+                self.pending_load = None;
+                match uai::read_evidence(&self.dag, &text) {
+                    Ok(observations) => {
+                        // the factors of a Markov network stay observed
+                        let nodes: Vec<usize> = self
+                            .dag
+                            .iter_nodes()
+                            .filter(|(_, node)| !node.factor)
+                            .map(|(i, _)| i)
+                            .collect();
+                        for node in nodes {
+                            self.dag.set_observation(node, None);
+                        }
+                        for (node, value) in observations {
+                            self.dag.set_observation(node, Some(value));
+                        }
+                        self.page = Page::Idle;
+                    }
+                    Err(e) => {
+                        self.load_error = Some(DeserError::Syntax(e));
+                        self.page = Page::Load;
+                    }
                 }
-            },
+            }
+            Msg::ConfirmLoad => {
+                if let Some((dag, _)) = self.pending_load.take() {
+                    self.set_dag(dag);
+                    self.page = Page::Idle;
+                }
+            }
             Msg::LoadExample(name) => {
                 ctx.link().send_future(self.load_example(name));
                 // only redraw when loading is finished
@@ -388,20 +835,239 @@ impl Component for BayesOMatic {
                     ctx.link().send_future(self.load_help())
                 }
             }
-            Msg::Export => {
+            Msg::Export(format) => {
+                // fix the identifiers of new nodes, so that later exports keep them
+                self.dag.assign_ids();
                 let now = js_sys::Date::new_0();
                 let filename = format!(
-                    "bayesomatic-export-{:04}-{:02}-{:02}-{:02}{:02}.json",
+                    "bayesomatic-export-{:04}-{:02}-{:02}-{:02}{:02}.{}",
                     now.get_full_year(),
                     now.get_month(),
                     now.get_date(),
                     now.get_hours(),
                     now.get_minutes(),
+                    format.extension(),
                 );
-                crate::js::make_json_download(
+                crate::js::make_text_download(
                     JsValue::from_str(&filename),
-                    JsValue::from_str(&self.dag.to_json()),
-                )
+                    JsValue::from_str(format.mime()),
+                    JsValue::from_str(&format.write(&self.dag)),
+                );
+                // the observations go in a separate file
+                if format == Format::Uai {
+                    if let Some(evidence) = uai::write_evidence(&self.dag) {
+                        crate::js::make_text_download(
+                            JsValue::from_str(&format!("{}.{}", filename, uai::EVIDENCE_EXTENSION)),
+                            JsValue::from_str(format.mime()),
+                            JsValue::from_str(&evidence),
+                        );
+                    }
+                }
+            }
+            Msg::SetSource(source) => {
+                self.source_error = None;
+                self.source_report = ValidationReport::new();
+                match text::read(&source) {
+                    Ok((dag, report)) if !report.has_errors() => {
+                        self.set_dag_from_source(dag);
+                        self.source_report = report;
+                    }
+                    Ok((_, report)) => self.source_error = Some(DeserError::Invalid(report)),
+                    Err(e) => self.source_error = Some(e),
+                }
+                self.source = source;
+            }
+            Msg::LoadDataset(text) => {
+                self.dataset = Dataset::parse(&text);
+                self.dataset_empty = self.dataset.is_none();
+                self.learned = None;
+                self.em_report = None;
+                // the nodes of the constraints are the columns of the dataset
+                self.constraints = Constraints::default();
+                self.class_node = None;
+                self.structure_report = None;
+                self.structure_error = None;
+                // the results were computed on the cases of the previous dataset
+                self.evaluation = None;
+                self.batch_report = None;
+                self.sample_report = None;
+            }
+            Msg::SetEstimator(estimator) => {
+                self.estimator = estimator;
+            }
+            Msg::LearnParameters => {
+                if let Some(ref dataset) = self.dataset {
+                    let cases = dataset.match_nodes(&self.dag);
+                    let nodes: Vec<usize> = learning::learnable_nodes(&self.dag, &cases)
+                        .into_iter()
+                        .filter(|n| !self.frozen.contains(n))
+                        .collect();
+                    let tables =
+                        learning::learn_parameters(&self.dag, &cases, &nodes, self.estimator);
+                    self.apply_learned(&tables);
+                    self.em_report = None;
+                }
+            }
+            Msg::ToggleFrozen(node) => {
+                if let Some(i) = self.frozen.iter().position(|&n| n == node) {
+                    self.frozen.remove(i);
+                } else {
+                    self.frozen.push(node);
+                }
+            }
+            Msg::RunEm => {
+                if let Some(ref dataset) = self.dataset {
+                    let cases = dataset.match_nodes(&self.dag);
+                    let nodes: Vec<usize> = self
+                        .dag
+                        .iter_nodes()
+                        .map(|(i, _)| i)
+                        .filter(|n| !self.frozen.contains(n))
+                        .collect();
+                    let (tables, report) = learning::expectation_maximization(
+                        &self.dag,
+                        &cases,
+                        &nodes,
+                        self.estimator,
+                    );
+                    self.apply_learned(&tables);
+                    self.em_report = Some(report);
+                }
+            }
+            Msg::SetScore(score) => {
+                self.score = score;
+            }
+            Msg::SetSearch(search) => {
+                self.search = search;
+            }
+            Msg::AddConstraint {
+                parent,
+                child,
+                required,
+            } => {
+                self.constraints
+                    .required
+                    .retain(|&edge| edge != (parent, child));
+                self.constraints
+                    .forbidden
+                    .retain(|&edge| edge != (parent, child));
+                if required {
+                    self.constraints.required.push((parent, child));
+                } else {
+                    self.constraints.forbidden.push((parent, child));
+                }
+                self.structure_error = None;
+            }
+            Msg::RemoveConstraint { parent, child } => {
+                self.constraints
+                    .required
+                    .retain(|&edge| edge != (parent, child));
+                self.constraints
+                    .forbidden
+                    .retain(|&edge| edge != (parent, child));
+                self.structure_error = None;
+            }
+            Msg::LearnStructure => {
+                if let Some(ref dataset) = self.dataset {
+                    let (mut dag, _) = dataset.to_dag();
+                    let cases = dataset.match_nodes(&dag);
+                    match structure::learn_structure(
+                        &mut dag,
+                        &cases,
+                        self.score,
+                        self.search,
+                        &self.constraints,
+                    ) {
+                        Ok(report) => {
+                            self.replace_with_learned(dag, &cases);
+                            self.structure_report = Some(report);
+                        }
+                        Err((edge, _)) => {
+                            self.structure_report = None;
+                            self.structure_error = Some(edge);
+                        }
+                    }
+                }
+            }
+            Msg::SetClassNode(class) => {
+                self.class_node = class;
+            }
+            Msg::BuildClassifier(classifier) => {
+                if let (Some(class), Some(dataset)) = (self.class_node, &self.dataset) {
+                    let (mut dag, _) = dataset.to_dag();
+                    let cases = dataset.match_nodes(&dag);
+                    structure::build_classifier(&mut dag, &cases, class, classifier);
+                    self.replace_with_learned(dag, &cases);
+                    self.evaluation_target = Some(class);
+                }
+            }
+            Msg::SetSampleCount(count) => {
+                self.sample_count = count;
+            }
+            Msg::SetSampling(sampling) => {
+                self.sampling = sampling;
+            }
+            Msg::SampleCases => {
+                let mut rng = Rng::new(js_sys::Date::now() as u64);
+                let samples =
+                    sampling::sample(&self.dag, self.sample_count, self.sampling, &mut rng);
+                crate::js::make_text_download(
+                    JsValue::from_str("cases.csv"),
+                    JsValue::from_str("text/csv"),
+                    JsValue::from_str(&sampling::write_samples(
+                        &self.dag,
+                        &samples,
+                        &lang!(self.lang, "sample-weight"),
+                    )),
+                );
+                self.sample_report = Some((samples.cases.len(), samples.drawn));
+            }
+            Msg::ToggleTarget(node) => {
+                if let Some(i) = self.targets.iter().position(|&n| n == node) {
+                    self.targets.remove(i);
+                } else {
+                    self.targets.push(node);
+                }
+                self.batch_report = None;
+            }
+            Msg::DownloadPosteriors => {
+                if let Some(ref dataset) = self.dataset {
+                    let cases = dataset.match_nodes(&self.dag);
+                    // keep the targets in the order of the nodes
+                    let targets: Vec<usize> = self
+                        .dag
+                        .iter_nodes()
+                        .map(|(i, _)| i)
+                        .filter(|i| self.targets.contains(i))
+                        .collect();
+                    let posteriors = batch::posteriors(&self.dag, &cases, &targets);
+                    crate::js::make_text_download(
+                        JsValue::from_str("posteriors.csv"),
+                        JsValue::from_str("text/csv"),
+                        JsValue::from_str(&batch::write_posteriors(
+                            &self.dag,
+                            dataset,
+                            &targets,
+                            &posteriors,
+                        )),
+                    );
+                    self.batch_report = Some(
+                        posteriors
+                            .iter()
+                            .filter(|row| row.iter().any(Option::is_none))
+                            .count(),
+                    );
+                }
+            }
+            Msg::SetEvaluationTarget(target) => {
+                self.evaluation_target = target;
+                self.evaluation = None;
+            }
+            Msg::Evaluate => {
+                if let (Some(target), Some(dataset)) = (self.evaluation_target, &self.dataset) {
+                    let cases = dataset.match_nodes(&self.dag);
+                    self.evaluation = Some(self.evaluate_target(&cases, target));
+                }
             }
         }
 
@@ -441,6 +1107,26 @@ impl Component for BayesOMatic {
                 Some(id) => select.set_value(&id.to_string()),
                 None => select.set_value("none"),
             }
+            if let Some(ref rules) = node.rules {
+                for (i, rule) in rules.rules.iter().enumerate() {
+                    for (k, value) in rule.context.iter().enumerate() {
+                        let query = format!("select[name=\"rule_{}_parent_{}\"]", i, k);
+                        let select: HtmlSelectElement = web_sys::window()
+                            .unwrap()
+                            .document()
+                            .unwrap()
+                            .query_selector(&query)
+                            .unwrap()
+                            .unwrap()
+                            .dyn_into()
+                            .unwrap();
+                        match value {
+                            Some(v) => select.set_value(&v.to_string()),
+                            None => select.set_value("any"),
+                        }
+                    }
+                }
+            }
         }
     }
 }
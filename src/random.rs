@@ -0,0 +1,159 @@
+/*
+ * A small pseudo-random generator
+ *
+ * The xorshift64* generator is good enough for breaking ties and sampling from
+ * models, and a given seed always gives the same sequence, which makes the
+ * results reproducible.
+ *
+ * Gamma variates come from the method of Marsaglia and Tsang, and Dirichlet ones
+ * from normalizing independent gamma variates.
+ */
+
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state must never be zero
+        Rng((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number uniformly drawn in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// An index drawn with the given weights, which need not sum to one
+    pub fn choose(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut target = self.next_f64() * total;
+        for (i, &w) in weights.iter().enumerate() {
+            if target < w {
+                return i;
+            }
+            target -= w;
+        }
+        // rounding errors may leave us past the end
+        weights.iter().rposition(|&w| w > 0.0).unwrap_or(0)
+    }
+
+    /// A number drawn from the standard normal distribution, by the Box-Muller
+    /// transform
+    pub fn normal(&mut self) -> f64 {
+        // 1 - u is in (0, 1], whose logarithm is finite
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// A number drawn from the gamma distribution of the given shape and of scale 1
+    pub fn gamma(&mut self, shape: f64) -> f64 {
+        if shape <= 0.0 {
+            return 0.0;
+        }
+        if shape < 1.0 {
+            // boost the shape, then scale back down
+            let u = 1.0 - self.next_f64();
+            return self.gamma(shape + 1.0) * u.powf(1.0 / shape);
+        }
+        let d = shape - 1.0 / 3.0;
+        let c = 1.0 / (9.0 * d).sqrt();
+        loop {
+            let x = self.normal();
+            let v = 1.0 + c * x;
+            if v <= 0.0 {
+                continue;
+            }
+            let v = v * v * v;
+            let u = 1.0 - self.next_f64();
+            if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+                return d * v;
+            }
+        }
+    }
+
+    /// Probabilities drawn from the Dirichlet distribution of the given
+    /// parameters, the values whose parameter is zero getting none
+    pub fn dirichlet(&mut self, alphas: &[f64]) -> Vec<f64> {
+        let draws: Vec<f64> = alphas.iter().map(|&a| self.gamma(a)).collect();
+        let total: f64 = draws.iter().sum();
+        if total > 0.0 {
+            draws.iter().map(|&g| g / total).collect()
+        } else {
+            // all the parameters are tiny, and so are the draws
+            let uniform = 1.0 / alphas.len() as f64;
+            alphas.iter().map(|_| uniform).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DRAWS: usize = 20000;
+
+    fn mean(draws: &[f64]) -> f64 {
+        draws.iter().sum::<f64>() / draws.len() as f64
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        assert!((0..100).all(|_| a.next_u64() == b.next_u64()));
+    }
+
+    #[test]
+    fn gamma_moments() {
+        let mut rng = Rng::new(1);
+        // the boosted small shapes, and the direct method
+        for &shape in &[0.3, 1.0, 2.5, 20.0] {
+            let draws: Vec<f64> = (0..DRAWS).map(|_| rng.gamma(shape)).collect();
+            assert!(draws.iter().all(|&g| g >= 0.0 && g.is_finite()));
+            let mean = mean(&draws);
+            let variance =
+                draws.iter().map(|&g| (g - mean) * (g - mean)).sum::<f64>() / (DRAWS - 1) as f64;
+            // the mean and the variance are both the shape
+            assert!((mean - shape).abs() < 0.05 * shape, "{} {}", shape, mean);
+            assert!(
+                (variance - shape).abs() < 0.1 * shape,
+                "{} {}",
+                shape,
+                variance
+            );
+        }
+        assert_eq!(rng.gamma(0.0), 0.0);
+        assert_eq!(rng.gamma(-1.0), 0.0);
+    }
+
+    #[test]
+    fn dirichlet_means() {
+        let mut rng = Rng::new(2);
+        let alphas = [1.0, 3.0, 0.0, 6.0];
+        let mut sums = vec![0.0; alphas.len()];
+        for _ in 0..DRAWS {
+            let draw = rng.dirichlet(&alphas);
+            assert!((draw.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert_eq!(draw[2], 0.0);
+            for (s, p) in sums.iter_mut().zip(draw) {
+                *s += p;
+            }
+        }
+        for (s, a) in sums.iter().zip(&alphas) {
+            assert!((s / DRAWS as f64 - a / 10.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn dirichlet_without_weight_is_uniform() {
+        let mut rng = Rng::new(3);
+        assert_eq!(rng.dirichlet(&[0.0, 0.0]), vec![0.5, 0.5]);
+    }
+}
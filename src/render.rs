@@ -4,10 +4,12 @@ use yew::{html, Html};
 use web_sys::{Event, HtmlInputElement, HtmlSelectElement};
 
 use crate::draw::DotCanvas;
+use crate::formats::{uai, Expected, Format, ParseError};
 use crate::graph::{DeserError, EdgeError};
 use crate::lang;
 use crate::model::{BayesOMatic, Msg};
 use crate::ui::PushButton;
+use crate::validation::{Issue, Problem, Severity, ValidationReport};
 use crate::Page;
 
 impl BayesOMatic {
@@ -18,10 +20,13 @@ impl BayesOMatic {
             <div class="column is-narrow">
                 <ul class="blocky">
                     <li><PushButton text={ lang!(self.lang, "reset") } onclick={ link.callback(|_| Msg::Reset) } /></li>
-                    <li><PushButton text={ lang!(self.lang, "export-json") }onclick={ link.callback(|_| Msg::Export) } /></li>
-                    <li><PushButton text={ lang!(self.lang, "load-json") }
-                            onclick={ link.callback(|_| Msg::MoveToPage(Page::LoadJson)) }
-                            selected={ self.page == Page::LoadJson }
+                    <li><PushButton text={ lang!(self.lang, "export") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Export)) }
+                            selected={ self.page == Page::Export }
+                        /></li>
+                    <li><PushButton text={ lang!(self.lang, "load-file") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Load)) }
+                            selected={ self.page == Page::Load }
                         /></li>
                     <li><PushButton text={ lang!(self.lang, "load-example") }
                             onclick={ link.callback(|_| Msg::MoveToPage(Page::LoadExample)) }
@@ -35,13 +40,29 @@ impl BayesOMatic {
                             onclick={ link.callback(|_| Msg::MoveToPage(Page::MutualInformation(None))) }
                             selected={ matches!(&self.page, &Page::MutualInformation(_)) }
                         /></li>
+                    <li><PushButton text={ lang!(self.lang, "health") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Health)) }
+                            selected={ self.page == Page::Health }
+                        /></li>
+                    <li><PushButton text={ lang!(self.lang, "text-editor") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Text)) }
+                            selected={ self.page == Page::Text }
+                        /></li>
+                    <li><PushButton text={ lang!(self.lang, "learn-from-data") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Data)) }
+                            selected={ self.page == Page::Data }
+                        /></li>
+                    <li><PushButton text={ lang!(self.lang, "model-info") }
+                            onclick={ link.callback(|_| Msg::MoveToPage(Page::Metadata)) }
+                            selected={ self.page == Page::Metadata }
+                        /></li>
                     <li><PushButton text={ lang!(self.lang, "help") }
                             onclick={ link.callback(|_| Msg::MoveToPage(Page::Help)) }
                             selected={ self.page == Page::Help }
                         /></li>
                 </ul>
             </div>
-            <div class="column"></div>
+            <div class="column">{ self.model_title() }</div>
             <div class="column is-narrow">
                 <ul class="blocky">
                     <li class="field">
@@ -68,6 +89,16 @@ impl BayesOMatic {
         }
     }
 
+    fn model_title(&self) -> Html {
+        let metadata = self.dag.metadata();
+        html! {
+            <div>
+                <p><strong>{ &metadata.title }</strong></p>
+                <p><em>{ &metadata.question }</em></p>
+            </div>
+        }
+    }
+
     fn editorbar(&self, link: &Scope<Self>) -> Html {
         html! {
             <div class="box">
@@ -84,16 +115,152 @@ impl BayesOMatic {
         }
     }
 
-    fn print_error(&self) -> Html {
+    fn describe_problem(&self, problem: &Problem) -> String {
+        match *problem {
+            Problem::DuplicateId(ref id) => lang!(self.lang, "val-duplicate-id", id = &id[..]),
+            Problem::UnknownParent(ref parent) | Problem::Edge(EdgeError::BadNode, ref parent) => {
+                lang!(self.lang, "val-unknown-parent", parent = &parent[..])
+            }
+            Problem::Edge(EdgeError::WouldCycle, ref parent) => {
+                lang!(self.lang, "val-cycle", parent = &parent[..])
+            }
+            Problem::Edge(EdgeError::AlreadyExisting, ref parent) => {
+                lang!(self.lang, "val-duplicate-edge", parent = &parent[..])
+            }
+            Problem::NoValues => lang!(self.lang, "val-no-values"),
+            Problem::DuplicateValue(ref value) => {
+                lang!(self.lang, "val-duplicate-value", value = &value[..])
+            }
+            Problem::ObservationOutOfRange {
+                observation,
+                values,
+            } => lang!(
+                self.lang,
+                "val-observation",
+                observation = observation,
+                values = values
+            ),
+            Problem::CredenciesLength { expected, found } => lang!(
+                self.lang,
+                "val-credencies-length",
+                expected = expected,
+                found = found
+            ),
+            Problem::NegativeCredency(value) => {
+                lang!(
+                    self.lang,
+                    "val-negative-credency",
+                    value = value.to_string()
+                )
+            }
+            Problem::DescriptionsCount { expected, found } => lang!(
+                self.lang,
+                "val-descriptions-count",
+                expected = expected,
+                found = found
+            ),
+            Problem::SampleSizesCount { expected, found } => lang!(
+                self.lang,
+                "val-sample-sizes-count",
+                expected = expected,
+                found = found
+            ),
+            Problem::InvalidRules => lang!(self.lang, "val-invalid-rules"),
+            Problem::Unsupported(_, ref construct) => {
+                lang!(self.lang, "val-unsupported", construct = &construct[..])
+            }
+            Problem::UnknownName(_, ref name) => {
+                lang!(self.lang, "val-unknown-name", name = &name[..])
+            }
+            Problem::MissingCredency(ref row) => {
+                lang!(self.lang, "val-missing-credency", row = &row[..])
+            }
+        }
+    }
+
+    fn print_issue(&self, issue: &Issue) -> Html {
+        let (class, severity) = match issue.problem.severity() {
+            Severity::Error => ("error", lang!(self.lang, "val-error")),
+            Severity::Warning => ("warning", lang!(self.lang, "val-warning")),
+        };
+        html! {
+            <li class={ class }>{ lang!(self.lang, "val-issue",
+                severity = severity,
+                node = &issue.node[..],
+                field = issue.problem.field(),
+                problem = self.describe_problem(&issue.problem)
+            ) }</li>
+        }
+    }
+
+    pub(crate) fn print_report(&self, report: &ValidationReport) -> Html {
+        html! {
+            <ul>
+                { for report.issues.iter().map(|issue| self.print_issue(issue)) }
+            </ul>
+        }
+    }
+
+    fn describe_parse_error(&self, error: &ParseError) -> String {
+        let expected = match error.expected {
+            Expected::Punct(c) => format!("\"{}\"", c),
+            Expected::Keyword(k) => format!("\"{}\"", k),
+            Expected::Name => lang!(self.lang, "syntax-name"),
+            Expected::Number => lang!(self.lang, "syntax-number"),
+            Expected::Variable => lang!(self.lang, "syntax-variable"),
+            Expected::ValueOf(ref node) => lang!(self.lang, "syntax-value-of", node = &node[..]),
+        };
+        let found = match error.found {
+            Some(ref found) => format!("\"{}\"", found),
+            None => lang!(self.lang, "syntax-end"),
+        };
+        lang!(
+            self.lang,
+            "err-syntax",
+            line = error.line,
+            expected = expected,
+            found = found
+        )
+    }
+
+    pub(crate) fn print_deser_error(&self, error: &DeserError) -> Html {
+        match error {
+            DeserError::Json(ref e) => html! {
+                <p class="error">{ format!("{}: {}", lang!(self.lang, "invalid-json"), e) }</p>
+            },
+            DeserError::Xml(ref e) => html! {
+                <p class="error">{ format!("{}: {}", lang!(self.lang, "invalid-xml"), e) }</p>
+            },
+            DeserError::Yaml(ref e) => html! {
+                <p class="error">{ format!("{}: {}", lang!(self.lang, "invalid-yaml"), e) }</p>
+            },
+            DeserError::Syntax(ref e) => html! {
+                <p class="error">{ self.describe_parse_error(e) }</p>
+            },
+            DeserError::Version(v) => html! {
+                <p class="error">{ lang!(self.lang, "err-version", version = *v) }</p>
+            },
+            DeserError::Invalid(ref report) => html! {
+                <div class="block">
+                    <p class="error">{ lang!(self.lang, "err-invalid") }</p>
+                    { self.print_report(report) }
+                </div>
+            },
+        }
+    }
+
+    fn print_error(&self, link: &Scope<Self>) -> Html {
         if let Some(ref error) = self.load_error {
-            let text: String = match error {
-                DeserError::Json(ref e) => format!("{}: {}", lang!(self.lang, "invalid-json"), e),
-                DeserError::Graph(EdgeError::WouldCycle) => lang!(self.lang, "err-cycle"),
-                DeserError::Graph(EdgeError::BadNode) => lang!(self.lang, "err-nodenotfound"),
-                DeserError::Graph(EdgeError::AlreadyExisting) => lang!(self.lang, "err-edges"),
-            };
+            self.print_deser_error(error)
+        } else if let Some((_, ref report)) = self.pending_load {
             html! {
-                <p class="error">{ text }</p>
+                <div class="block">
+                    <p>{ lang!(self.lang, "load-report") }</p>
+                    { self.print_report(report) }
+                    <a href="#" class="button" onclick={ link.callback(|_| Msg::ConfirmLoad) }>
+                        { if report.has_errors() { lang!(self.lang, "load-anyway") } else { lang!(self.lang, "load") } }
+                    </a>
+                </div>
             }
         } else {
             html! {}
@@ -102,18 +269,23 @@ impl BayesOMatic {
 
     pub fn content(&self, link: &Scope<Self>) -> Html {
         match self.page {
-            Page::LoadJson => {
+            Page::Load => {
                 html! {
                     <div class="columns is-centered">
                     <div class="column is-three-fifths box content">
-                        { self.print_error() }
+                        { self.print_error(link) }
                         <div class="file block is-boxed is-large">
                         <label class="file-label">
-                        <input type="file" class="file-input" id="load-json" accept="application/json" onchange={ link.callback_future(|evt: Event| async move {
+                        <input type="file" class="file-input" id="load-file" accept={ Format::accept() } onchange={ link.callback_future(|evt: Event| async move {
                             let fileinput = evt.target_dyn_into::<HtmlInputElement>().unwrap();
                             let file = fileinput.files()?.get(0)?;
-                            let text = wasm_bindgen_futures::JsFuture::from(file.text()).await.ok()?;
-                            Some(Msg::LoadJson(text.as_string()?))
+                            let name = file.name();
+                            let text = wasm_bindgen_futures::JsFuture::from(file.text()).await.ok()?.as_string()?;
+                            if name.ends_with(&format!(".{}", uai::EVIDENCE_EXTENSION)) {
+                                return Some(Msg::LoadEvidence(text));
+                            }
+                            let format = Format::from_filename(&name).unwrap_or(Format::Json);
+                            Some(Msg::Load(format, text))
                         })} />
                         <span class="file-cta">
                             <span class="file-icon">
@@ -133,6 +305,23 @@ impl BayesOMatic {
                     </div>
                 }
             }
+            Page::Export => {
+                html! {
+                    <div class="columns is-centered">
+                    <div class="column is-three-fifths box content">
+                        <p>{ lang!(self.lang, "export-format") }</p>
+                        <ul>
+                        { for Format::ALL.iter().copied().map(|format| {
+                            html! { <li><p><a href="#" onclick={ link.callback(move |_| Msg::Export(format)) }>
+                                { format!("{} (.{})", format.name(), format.extension()) }
+                            </a></p></li> }
+                        })}
+                        </ul>
+                        <a href="#" class="button" onclick={ link.callback(|_| Msg::MoveToPage(Page::Idle)) }>{ lang!(self.lang, "close") }</a>
+                    </div>
+                    </div>
+                }
+            }
             Page::LoadExample => {
                 html! {
                     <div class="columns is-centered">
@@ -209,6 +398,58 @@ impl BayesOMatic {
                     </div>
                 }
             }
+            Page::Health => {
+                html! {
+                    <div class="columns">
+                        <div class="column">
+                        <DotCanvas dot={ crate::draw::graph_to_dot(&self.dag) } />
+                        </div>
+                        <div class="column">
+                            { self.editorbar(link) }
+                            { self.make_health_tab(link) }
+                        </div>
+                    </div>
+                }
+            }
+            Page::Text => {
+                html! {
+                    <div class="columns">
+                        <div class="column">
+                        <DotCanvas dot={ crate::draw::graph_to_dot(&self.dag) } />
+                        </div>
+                        <div class="column">
+                            { self.editorbar(link) }
+                            { self.make_text_tab(link) }
+                        </div>
+                    </div>
+                }
+            }
+            Page::Data => {
+                html! {
+                    <div class="columns">
+                        <div class="column">
+                        <DotCanvas dot={ crate::draw::graph_to_dot(&self.dag) } />
+                        </div>
+                        <div class="column">
+                            { self.editorbar(link) }
+                            { self.make_data_tab(link) }
+                        </div>
+                    </div>
+                }
+            }
+            Page::Metadata => {
+                html! {
+                    <div class="columns">
+                        <div class="column">
+                        <DotCanvas dot={ crate::draw::graph_to_dot(&self.dag) } />
+                        </div>
+                        <div class="column">
+                            { self.editorbar(link) }
+                            { self.make_metadata_tab(link) }
+                        </div>
+                    </div>
+                }
+            }
             Page::MutualInformation(_) => {
                 html! {
                     <div class="columns">
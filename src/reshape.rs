@@ -0,0 +1,197 @@
+use ndarray::{ArrayD, Axis, IxDyn};
+
+use crate::rules::parent_configurations;
+
+/*
+ * Helpers to reshape credency tables when the structure of the graph changes
+ *
+ * Axis 0 of a credency array runs over the values of the node, and axis `k + 1`
+ * over the values of its `k`-th parent. The row descriptions are indexed by
+ * configurations of parent values, in the order of `parent_configurations`.
+ *
+ * Mappings are given as a list over the new indices of an axis, `Some(i)`
+ * meaning the entry comes from the old index `i` and `None` that it is new.
+ */
+
+fn row_index(configuration: &[usize], parent_shape: &[usize]) -> usize {
+    configuration
+        .iter()
+        .zip(parent_shape.iter())
+        .fold(0, |acc, (&c, &n)| acc * n + c)
+}
+
+/// Rebuild an array along one axis, filling new entries with `fill`
+pub fn remap_axis(
+    array: &ArrayD<f32>,
+    axis: usize,
+    mapping: &[Option<usize>],
+    fill: f32,
+) -> ArrayD<f32> {
+    let mut shape = array.shape().to_vec();
+    shape[axis] = mapping.len();
+    let mut result = ArrayD::from_elem(IxDyn(&shape), fill);
+    for (new, old) in mapping.iter().enumerate() {
+        if let Some(old) = *old {
+            result
+                .index_axis_mut(Axis(axis), new)
+                .assign(&array.index_axis(Axis(axis), old));
+        }
+    }
+    result
+}
+
+/// Rebuild the row descriptions, or any other value given for each row, after
+/// remapping the axis of the given parent, new rows getting an empty description
+pub fn remap_rows<T: Clone + Default>(
+    descriptions: &[T],
+    parent_shape: &[usize],
+    parent: usize,
+    mapping: &[Option<usize>],
+) -> Vec<T> {
+    if descriptions.is_empty() {
+        return Vec::new();
+    }
+    let mut new_shape = parent_shape.to_vec();
+    new_shape[parent] = mapping.len();
+    parent_configurations(&new_shape)
+        .map(|mut configuration| match mapping[configuration[parent]] {
+            Some(old) => {
+                configuration[parent] = old;
+                descriptions
+                    .get(row_index(&configuration, parent_shape))
+                    .cloned()
+                    .unwrap_or_default()
+            }
+            None => T::default(),
+        })
+        .collect()
+}
+
+/// Add a new last axis, the node not depending on it yet: every slice is a copy
+/// of the previous table
+pub fn push_axis(array: &ArrayD<f32>, len: usize) -> ArrayD<f32> {
+    let mut shape = array.shape().to_vec();
+    shape.push(len);
+    let new_axis = shape.len() - 1;
+    let mut result = ArrayD::from_elem(IxDyn(&shape), 0.0);
+    for i in 0..len {
+        result.index_axis_mut(Axis(new_axis), i).assign(array);
+    }
+    result
+}
+
+/// Descriptions matching `push_axis`: each row is repeated for every value of
+/// the new parent
+pub fn push_rows<T: Clone>(descriptions: &[T], len: usize) -> Vec<T> {
+    descriptions
+        .iter()
+        .flat_map(|d| std::iter::repeat(d.clone()).take(len))
+        .collect()
+}
+
+/// Remove an axis. If the node did not actually depend on it, the table is kept
+/// as is, otherwise the normalized rows are averaged over it.
+pub fn remove_axis(array: &ArrayD<f32>, axis: usize) -> ArrayD<f32> {
+    if array.len_of(Axis(axis)) == 0 {
+        // nothing to keep, the parent had no value
+        let mut shape = array.shape().to_vec();
+        shape.remove(axis);
+        return ArrayD::from_elem(IxDyn(&shape), 1.0);
+    }
+    let first = array.index_axis(Axis(axis), 0);
+    if array.axis_iter(Axis(axis)).all(|slice| slice == first) {
+        return first.to_owned();
+    }
+    let sums = array.sum_axis(Axis(0)).insert_axis(Axis(0));
+    let normalized = array / &sums;
+    // rows of only zeroes contribute nothing rather than NaNs
+    let normalized = normalized.mapv(|v| if v.is_nan() { 0.0 } else { v });
+    normalized.mean_axis(Axis(axis)).unwrap()
+}
+
+/// Descriptions matching `remove_axis`: the distinct descriptions of the merged
+/// rows are joined together
+pub fn remove_rows(descriptions: &[String], parent_shape: &[usize], parent: usize) -> Vec<String> {
+    if descriptions.is_empty() {
+        return Vec::new();
+    }
+    let mut new_shape = parent_shape.to_vec();
+    new_shape.remove(parent);
+    parent_configurations(&new_shape)
+        .map(|configuration| {
+            let mut merged: Vec<String> = Vec::new();
+            for v in 0..parent_shape[parent] {
+                let mut old = configuration.clone();
+                old.insert(parent, v);
+                if let Some(d) = descriptions.get(row_index(&old, parent_shape)) {
+                    if !d.is_empty() && !merged.contains(d) {
+                        merged.push(d.clone());
+                    }
+                }
+            }
+            merged.join(" / ")
+        })
+        .collect()
+}
+
+/// Sample sizes matching `remove_axis`: the merged rows having been averaged, so
+/// are their sample sizes
+pub fn remove_sample_sizes(sizes: &[f32], parent_shape: &[usize], parent: usize) -> Vec<f32> {
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+    let mut new_shape = parent_shape.to_vec();
+    new_shape.remove(parent);
+    let merged = parent_shape[parent].max(1) as f32;
+    parent_configurations(&new_shape)
+        .map(|configuration| {
+            let total: f32 = (0..parent_shape[parent])
+                .map(|v| {
+                    let mut old = configuration.clone();
+                    old.insert(parent, v);
+                    sizes
+                        .get(row_index(&old, parent_shape))
+                        .copied()
+                        .unwrap_or(0.0)
+                })
+                .sum();
+            total / merged
+        })
+        .collect()
+}
+
+/// Reorder the parent axes: the new `i`-th parent is the old `order[i]`-th one
+pub fn permute_parents(array: &ArrayD<f32>, order: &[usize]) -> ArrayD<f32> {
+    let mut axes = vec![0];
+    axes.extend(order.iter().map(|&p| p + 1));
+    array
+        .clone()
+        .permuted_axes(IxDyn(&axes))
+        .as_standard_layout()
+        .into_owned()
+}
+
+/// Descriptions, or any other value given for each row, matching
+/// `permute_parents`
+pub fn permute_rows<T: Clone + Default>(
+    descriptions: &[T],
+    parent_shape: &[usize],
+    order: &[usize],
+) -> Vec<T> {
+    if descriptions.is_empty() {
+        return Vec::new();
+    }
+    let new_shape: Vec<usize> = order.iter().map(|&p| parent_shape[p]).collect();
+    parent_configurations(&new_shape)
+        .map(|configuration| {
+            let mut old = vec![0; configuration.len()];
+            for (i, &p) in order.iter().enumerate() {
+                old[p] = configuration[i];
+            }
+            descriptions
+                .get(row_index(&old, parent_shape))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect()
+}
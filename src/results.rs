@@ -10,6 +10,7 @@ use web_sys::{Event, HtmlSelectElement};
 
 use crate::{
     lang,
+    lint::{lint, Lint, LintKind},
     model::{BayesOMatic, BeliefsDisplay, Msg},
     Page,
 };
@@ -62,7 +63,11 @@ impl BayesOMatic {
                     </div>
                 }
             } else {
-                let raw_iter = node.values.iter().zip(log_beliefs.iter());
+                let raw_iter = node.values.iter().zip(log_beliefs.iter()).enumerate();
+                let intervals = self
+                    .intervals
+                    .as_ref()
+                    .and_then(|intervals| intervals.get(nodeid));
                 let min_log_belief = log_beliefs
                     .iter()
                     .copied()
@@ -72,11 +77,24 @@ impl BayesOMatic {
                     <div class="block">
                         <h3>{ lang!(self.lang, "node", name=&node.label[..]) }</h3>
                         <ul class="vlist blocky">
-                            { for raw_iter.map(|(name, belief)| {
+                            { for raw_iter.map(|(i, (name, belief))| {
                                 if self.beliefs_display == BeliefsDisplay::Probabilities {
+                                    let interval = intervals.and_then(|intervals| intervals.get(i));
                                     html! {
                                         <li>
                                             { format!("{}: {:.1}%", name, belief.exp()*100.0) }
+                                            { if let Some(&(low, high)) = interval {
+                                                html! {
+                                                    <span>
+                                                        { " " }
+                                                        { lang!(self.lang, "belief-interval",
+                                                                low = format!("{:.1}", low * 100.0),
+                                                                high = format!("{:.1}", high * 100.0)) }
+                                                    </span>
+                                                }
+                                            } else {
+                                                html! {}
+                                            }}
                                         </li>
                                     }
                                 } else {
@@ -168,4 +186,87 @@ impl BayesOMatic {
             }
         }
     }
+
+    /// Describe a configuration of the parents of a node, as used for the rows
+    /// of its credency table
+    fn describe_row(&self, nodeid: usize, row: &[usize]) -> String {
+        let node = self.dag.get(nodeid).unwrap();
+        if row.is_empty() {
+            return lang!(self.lang, "prior");
+        }
+        node.parents
+            .iter()
+            .zip(row.iter())
+            .map(|(&p, &v)| {
+                let parent = self.dag.get(p).unwrap();
+                format!("{} = {}", parent.label, parent.values[v])
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn describe_lint(&self, lint: &Lint) -> String {
+        let node = self.dag.get(lint.node).unwrap();
+        match lint.kind {
+            LintKind::NoValues => lang!(self.lang, "lint-no-values"),
+            LintKind::UnsetCredencies => lang!(self.lang, "lint-unset-credencies"),
+            LintKind::ZeroRow(ref row) => lang!(
+                self.lang,
+                "lint-zero-row",
+                row = self.describe_row(lint.node, row)
+            ),
+            LintKind::NearDeterministic {
+                ref row,
+                value,
+                probability,
+            } => lang!(
+                self.lang,
+                "lint-near-deterministic",
+                value = &node.values[value][..],
+                probability = format!("{:.2}%", probability * 100.0),
+                row = self.describe_row(lint.node, row)
+            ),
+            LintKind::Orphan => lang!(self.lang, "lint-orphan"),
+            LintKind::DuplicateLabel => lang!(self.lang, "lint-duplicate-label"),
+            LintKind::UnexplainedRows(ref rows) => lang!(
+                self.lang,
+                "lint-unexplained-rows",
+                count = rows.len(),
+                rows = rows
+                    .iter()
+                    .map(|row| format!("({})", self.describe_row(lint.node, row)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    pub fn make_health_tab(&self, link: &Scope<Self>) -> Html {
+        let lints = lint(&self.dag);
+        if lints.is_empty() {
+            return html! {
+                <div id="node-editor" class="box content">
+                    <p>{ lang!(self.lang, "lint-nothing") }</p>
+                </div>
+            };
+        }
+        html! {
+            <div id="node-editor" class="box content">
+                <h2>{ lang!(self.lang, "health-results") }</h2>
+                <ul>
+                { for lints.iter().map(|l| {
+                    let nodeid = l.node;
+                    html! {
+                        <li>
+                            <a href="#" onclick={ link.callback(move |_| Msg::MoveToPage(Page::NodeEdit(nodeid))) }>
+                                { &self.dag.get(nodeid).unwrap().label }
+                            </a>
+                            { format!(" : {}", self.describe_lint(l)) }
+                        </li>
+                    }
+                })}
+                </ul>
+            </div>
+        }
+    }
 }
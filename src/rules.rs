@@ -0,0 +1,287 @@
+use itertools::Itertools;
+use ndarray::{ArrayD, IxDyn};
+
+use crate::reshape;
+
+/*
+ * Context-specific credency tables
+ *
+ * Rather than listing every combination of parent values, a node can be
+ * described by a list of rules. Each rule applies to all parent configurations
+ * matching its context, where `None` is a wildcard matching any value of the
+ * corresponding parent. Rules are tried in order and the first match wins, the
+ * default row being used when no rule matches.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CptRule {
+    pub context: Vec<Option<usize>>,
+    pub credencies: Vec<f32>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleTable {
+    pub rules: Vec<CptRule>,
+    pub default: Vec<f32>,
+    pub default_description: String,
+}
+
+/// Iterates over all the configurations of parent values, in the order used
+/// for the rows of the credency tables (first parent varying the slowest).
+pub fn parent_configurations(parent_shape: &[usize]) -> Box<dyn Iterator<Item = Vec<usize>>> {
+    if parent_shape.is_empty() {
+        Box::new(std::iter::once(Vec::new()))
+    } else {
+        Box::new(
+            parent_shape
+                .iter()
+                .map(|&n| 0..n)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .multi_cartesian_product(),
+        )
+    }
+}
+
+impl CptRule {
+    pub fn wildcard(parent_count: usize, credencies: Vec<f32>) -> CptRule {
+        CptRule {
+            context: vec![None; parent_count],
+            credencies,
+            description: String::new(),
+        }
+    }
+
+    pub fn matches(&self, configuration: &[usize]) -> bool {
+        self.context
+            .iter()
+            .zip(configuration.iter())
+            .all(|(c, v)| c.map(|c| c == *v).unwrap_or(true))
+    }
+}
+
+impl RuleTable {
+    /// A table containing only a uniform default row
+    pub fn uniform(value_count: usize) -> RuleTable {
+        RuleTable {
+            rules: Vec::new(),
+            default: vec![1.0; value_count],
+            default_description: String::new(),
+        }
+    }
+
+    /// Build a table equivalent to a full credency array: the most common row
+    /// becomes the default and every other row gets its own rule.
+    pub fn from_credencies(credencies: &ArrayD<f32>, descriptions: &[String]) -> RuleTable {
+        let shape = credencies.shape();
+        let mut rows = Vec::new();
+        for (i, configuration) in parent_configurations(&shape[1..]).enumerate() {
+            let mut idx = vec![0];
+            idx.extend(configuration.iter().copied());
+            let row = (0..shape[0])
+                .map(|v| {
+                    idx[0] = v;
+                    credencies[IxDyn(&idx)]
+                })
+                .collect::<Vec<f32>>();
+            let desc = descriptions.get(i).cloned().unwrap_or_default();
+            rows.push((configuration, row, desc));
+        }
+        // pick the most frequent row as default, the first one in case of tie
+        let default = (0..rows.len())
+            .max_by_key(|&i| {
+                let count = rows
+                    .iter()
+                    .filter(|r| r.1 == rows[i].1 && r.2 == rows[i].2)
+                    .count();
+                (count, std::cmp::Reverse(i))
+            })
+            .map(|i| (rows[i].1.clone(), rows[i].2.clone()))
+            .unwrap_or_else(|| (vec![1.0; shape[0]], String::new()));
+        RuleTable {
+            rules: rows
+                .into_iter()
+                .filter(|r| r.1 != default.0 || r.2 != default.1)
+                .map(|(configuration, credencies, description)| CptRule {
+                    context: configuration.into_iter().map(Some).collect(),
+                    credencies,
+                    description,
+                })
+                .collect(),
+            default: default.0,
+            default_description: default.1,
+        }
+    }
+
+    /// Reorder, add or remove values of the node, new values getting a credency of 1
+    pub fn remap_values(&mut self, mapping: &[Option<usize>]) {
+        let remap = |row: &Vec<f32>| -> Vec<f32> {
+            mapping
+                .iter()
+                .map(|m| m.map(|i| row[i]).unwrap_or(1.0))
+                .collect()
+        };
+        for rule in &mut self.rules {
+            rule.credencies = remap(&rule.credencies);
+        }
+        self.default = remap(&self.default);
+    }
+
+    /// Reorder, add or remove values of a parent. Rules about a value that was
+    /// removed are dropped.
+    pub fn remap_parent(&mut self, parent: usize, mapping: &[Option<usize>]) {
+        self.rules = std::mem::take(&mut self.rules)
+            .into_iter()
+            .filter_map(|mut rule| {
+                if let Some(old) = rule.context[parent] {
+                    let new = mapping.iter().position(|&m| m == Some(old))?;
+                    rule.context[parent] = Some(new);
+                }
+                Some(rule)
+            })
+            .collect();
+    }
+
+    /// Reorder the parents, the new `i`-th parent being the old `order[i]`-th one
+    pub fn permute_parents(&mut self, order: &[usize]) {
+        for rule in &mut self.rules {
+            rule.context = order.iter().map(|&p| rule.context[p]).collect();
+        }
+    }
+
+    pub fn push_parent(&mut self) {
+        for rule in &mut self.rules {
+            rule.context.push(None);
+        }
+    }
+
+    /// Remove a parent, given the shape of its parents before the removal. If
+    /// no rule depends on it, only the rule contexts change. Otherwise the rows
+    /// are averaged over it as `reshape::remove_axis` does, and the rules are
+    /// rebuilt from the result.
+    pub fn remove_parent(&mut self, parent: usize, parent_shape: &[usize]) {
+        if self.rules.iter().all(|rule| rule.context[parent].is_none()) {
+            for rule in &mut self.rules {
+                rule.context.remove(parent);
+            }
+            return;
+        }
+        let mut shape = vec![self.default.len()];
+        shape.extend_from_slice(parent_shape);
+        let (credencies, descriptions) = self.expand(&shape);
+        *self = RuleTable::from_credencies(
+            &reshape::remove_axis(&credencies, parent + 1),
+            &reshape::remove_rows(&descriptions, parent_shape, parent),
+        );
+    }
+
+    /// Check that this table is consistent with a node having the given shape,
+    /// as would be returned by `Dag::credencies_shape`.
+    pub fn check_shape(&self, shape: &[usize]) -> bool {
+        self.default.len() == shape[0]
+            && self.rules.iter().all(|rule| {
+                rule.credencies.len() == shape[0]
+                    && rule.context.len() == shape.len() - 1
+                    && rule
+                        .context
+                        .iter()
+                        .zip(shape[1..].iter())
+                        .all(|(c, &n)| c.map(|c| c < n).unwrap_or(true))
+            })
+    }
+
+    /// Find the rule that applies to this configuration of parent values, `None`
+    /// meaning the default row.
+    pub fn matching_rule(&self, configuration: &[usize]) -> Option<usize> {
+        self.rules.iter().position(|r| r.matches(configuration))
+    }
+
+    /// Expand the rules into the full credency array and its row descriptions.
+    ///
+    /// The shape must have been validated with `check_shape` beforehand.
+    pub fn expand(&self, shape: &[usize]) -> (ArrayD<f32>, Vec<String>) {
+        let mut credencies = ArrayD::from_elem(IxDyn(shape), 0.0);
+        let mut descriptions = Vec::new();
+        for configuration in parent_configurations(&shape[1..]) {
+            let (row, desc) = match self.matching_rule(&configuration) {
+                Some(i) => (&self.rules[i].credencies, &self.rules[i].description),
+                None => (&self.default, &self.default_description),
+            };
+            let mut idx = vec![0];
+            idx.extend(configuration.iter().copied());
+            for (i, &v) in row.iter().enumerate() {
+                idx[0] = i;
+                credencies[IxDyn(&idx)] = v;
+            }
+            descriptions.push(desc.clone());
+        }
+        (credencies, descriptions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(context: Vec<Option<usize>>, credencies: Vec<f32>) -> CptRule {
+        CptRule {
+            context,
+            credencies,
+            description: String::new(),
+        }
+    }
+
+    // two binary parents, a specific rule for a=0 and b=1 and a wildcard on b
+    fn table() -> RuleTable {
+        RuleTable {
+            rules: vec![
+                rule(vec![Some(0), Some(1)], vec![0.9, 0.1]),
+                rule(vec![None, Some(1)], vec![0.2, 0.8]),
+            ],
+            default: vec![0.5, 0.5],
+            default_description: String::new(),
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = table();
+        assert_eq!(rules.matching_rule(&[0, 1]), Some(0));
+        assert_eq!(rules.matching_rule(&[1, 1]), Some(1));
+        assert_eq!(rules.matching_rule(&[0, 0]), None);
+    }
+
+    #[test]
+    fn expand_round_trips_through_credencies() {
+        let (credencies, descriptions) = table().expand(&[2, 2, 2]);
+        assert_eq!(credencies[IxDyn(&[0, 0, 1])], 0.9);
+        assert_eq!(credencies[IxDyn(&[1, 1, 1])], 0.8);
+        assert_eq!(credencies[IxDyn(&[0, 1, 0])], 0.5);
+        let rules = RuleTable::from_credencies(&credencies, &descriptions);
+        assert_eq!(rules.default, vec![0.5, 0.5]);
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.expand(&[2, 2, 2]).0, credencies);
+    }
+
+    #[test]
+    fn removing_a_parent_averages_its_rows() {
+        let mut rules = table();
+        let (credencies, _) = rules.expand(&[2, 2, 2]);
+        rules.remove_parent(1, &[2, 2]);
+        assert!(rules.check_shape(&[2, 2]));
+        let (removed, _) = rules.expand(&[2, 2]);
+        assert_eq!(removed, reshape::remove_axis(&credencies, 2));
+        assert!((removed[IxDyn(&[0, 0])] - 0.7).abs() < 1e-6);
+        assert!((removed[IxDyn(&[0, 1])] - 0.35).abs() < 1e-6);
+    }
+
+    #[test]
+    fn removing_an_unused_parent_keeps_the_rules() {
+        let mut rules = table();
+        rules.rules.remove(0);
+        rules.remove_parent(0, &[2, 2]);
+        assert_eq!(rules.rules, vec![rule(vec![Some(1)], vec![0.2, 0.8])]);
+        assert_eq!(rules.default, vec![0.5, 0.5]);
+    }
+}
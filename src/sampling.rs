@@ -0,0 +1,212 @@
+use ndarray::IxDyn;
+
+use crate::elimination;
+use crate::formats::csv::write_record;
+use crate::graph::Dag;
+use crate::random::Rng;
+
+/*
+ * Drawing cases from a model
+ *
+ * Forward sampling draws the value of each node from its table, given the values
+ * already drawn for its parents, going through the nodes in topological order.
+ *
+ * To take the observations into account, rejection sampling throws away the
+ * cases that disagree with them, which wastes many draws when the observations
+ * are unlikely. Likelihood weighting instead sets the observed nodes to their
+ * observed value, and weights each case by the probability of the observations
+ * given the values drawn for their parents.
+ */
+
+/// The largest number of cases that can be drawn at once
+pub const MAX_CASES: usize = 100_000;
+// rejection sampling gives up after this many draws, as they run while the page
+// waits
+const MAX_DRAWS: usize = 1_000_000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sampling {
+    // ignoring the observations
+    Forward,
+    Rejection,
+    LikelihoodWeighting,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Samples {
+    // the value of each node in each case, indexed like the nodes of the model
+    pub cases: Vec<Vec<usize>>,
+    // the weight of each case, with likelihood weighting
+    pub weights: Option<Vec<f64>>,
+    // the number of cases drawn, including the rejected ones
+    pub drawn: usize,
+}
+
+/// Draw cases from a model whose nodes all have values
+pub fn sample(dag: &Dag, count: usize, sampling: Sampling, rng: &mut Rng) -> Samples {
+    let tables = elimination::tables(dag);
+    let order = dag.topological_order();
+    let mut samples = Samples {
+        cases: Vec::with_capacity(count),
+        weights: match sampling {
+            Sampling::LikelihoodWeighting => Some(Vec::with_capacity(count)),
+            _ => None,
+        },
+        drawn: 0,
+    };
+    let mut values = vec![0; tables.len()];
+    'cases: while samples.cases.len() < count && samples.drawn < MAX_DRAWS {
+        samples.drawn += 1;
+        let mut weight = 1.0;
+        for &n in &order {
+            let node = dag.get(n).unwrap();
+            let table = tables[n].as_ref().unwrap();
+            let mut idx = vec![0];
+            idx.extend(node.parents.iter().map(|&p| values[p]));
+            let probability = |v: usize| {
+                let mut idx = idx.clone();
+                idx[0] = v;
+                f64::from(table[IxDyn(&idx)])
+            };
+            match (sampling, node.observation) {
+                (Sampling::LikelihoodWeighting, Some(observed)) => {
+                    values[n] = observed;
+                    weight *= probability(observed);
+                }
+                _ => {
+                    let weights: Vec<f64> = (0..node.values.len()).map(probability).collect();
+                    values[n] = rng.choose(&weights);
+                }
+            }
+            if sampling == Sampling::Rejection
+                && matches!(node.observation, Some(observed) if observed != values[n])
+            {
+                continue 'cases;
+            }
+        }
+        samples.cases.push(values.clone());
+        if let Some(ref mut weights) = samples.weights {
+            weights.push(weight);
+        }
+    }
+    samples
+}
+
+/// The cases as CSV, with the labels of the nodes as header, and a last column
+/// for the weights if there are any
+pub fn write_samples(dag: &Dag, samples: &Samples, weight: &str) -> String {
+    let mut header: Vec<&str> = dag.iter_nodes().map(|(_, node)| &node.label[..]).collect();
+    if samples.weights.is_some() {
+        header.push(weight);
+    }
+    let mut out = write_record(&header, ',');
+    for (i, case) in samples.cases.iter().enumerate() {
+        let case_weight = samples
+            .weights
+            .as_ref()
+            .map(|weights| weights[i].to_string());
+        let mut record: Vec<&str> = dag
+            .iter_nodes()
+            .map(|(n, node)| &node.values[case[n]][..])
+            .collect();
+        if let Some(ref case_weight) = case_weight {
+            record.push(case_weight);
+        }
+        out.push_str(&write_record(&record, ','));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    const DRAWS: usize = 20000;
+
+    // P(b = t) = 0.3 * 0.9 + 0.7 * 0.2 = 0.41, P(a = t | b = t) = 0.27 / 0.41
+    fn model(observed: &str) -> Dag {
+        text::read(&format!(
+            "node a {{\n    values t, f\n}}\n\
+             node b {{\n    values t, f\n    {}\n}}\n\
+             a -> b\n\
+             table a {{ 0.3, 0.7 }}\n\
+             table b {{\n    (t): 0.9, 0.1\n    (f): 0.2, 0.8\n}}\n",
+            observed
+        ))
+        .unwrap()
+        .0
+    }
+
+    /// The weighted frequency of the first value of a node
+    fn frequency(samples: &Samples, node: usize) -> f64 {
+        let weight = |i: usize| samples.weights.as_ref().map(|w| w[i]).unwrap_or(1.0);
+        let total: f64 = (0..samples.cases.len()).map(weight).sum();
+        let hits: f64 = (0..samples.cases.len())
+            .filter(|&i| samples.cases[i][node] == 0)
+            .map(weight)
+            .sum();
+        hits / total
+    }
+
+    #[test]
+    fn forward_sampling_reproduces_the_marginals() {
+        // the observation is ignored
+        let dag = model("observed t");
+        let samples = sample(&dag, DRAWS, Sampling::Forward, &mut Rng::new(1));
+        assert_eq!(samples.cases.len(), DRAWS);
+        assert_eq!(samples.drawn, DRAWS);
+        assert!(samples.weights.is_none());
+        assert!((frequency(&samples, 0) - 0.3).abs() < 0.015);
+        assert!((frequency(&samples, 1) - 0.41).abs() < 0.015);
+    }
+
+    #[test]
+    fn rejection_sampling_keeps_the_observed_cases() {
+        let dag = model("observed t");
+        let samples = sample(&dag, DRAWS, Sampling::Rejection, &mut Rng::new(2));
+        assert_eq!(samples.cases.len(), DRAWS);
+        assert!(samples.drawn > DRAWS);
+        assert!(samples.cases.iter().all(|case| case[1] == 0));
+        assert!((frequency(&samples, 0) - 0.27 / 0.41).abs() < 0.015);
+    }
+
+    #[test]
+    fn rejection_sampling_gives_up() {
+        let mut dag = model("observed t");
+        // a is never drawn as observed
+        dag.set_observation(0, Some(1));
+        dag.set_credencies(0, ndarray::arr1(&[1.0f32, 0.0]).into_dyn())
+            .unwrap();
+        let samples = sample(&dag, 10, Sampling::Rejection, &mut Rng::new(3));
+        assert!(samples.cases.is_empty());
+        assert_eq!(samples.drawn, MAX_DRAWS);
+    }
+
+    #[test]
+    fn likelihood_weighting() {
+        let dag = model("observed t");
+        let samples = sample(&dag, DRAWS, Sampling::LikelihoodWeighting, &mut Rng::new(4));
+        assert_eq!(samples.drawn, DRAWS);
+        let weights = samples.weights.as_ref().unwrap();
+        for (case, &weight) in samples.cases.iter().zip(weights) {
+            assert_eq!(case[1], 0);
+            assert_eq!(weight, f64::from(if case[0] == 0 { 0.9f32 } else { 0.2 }));
+        }
+        assert!((frequency(&samples, 0) - 0.27 / 0.41).abs() < 0.015);
+    }
+
+    #[test]
+    fn writes_the_weights() {
+        let dag = model("");
+        let samples = Samples {
+            cases: vec![vec![0, 1], vec![1, 0]],
+            weights: Some(vec![0.5, 2.0]),
+            drawn: 2,
+        };
+        assert_eq!(
+            write_samples(&dag, &samples, "w"),
+            "a,b,w\nt,f,0.5\nf,t,2\n"
+        );
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+
+use crate::dataset::Cases;
+use crate::graph::{Dag, EdgeError};
+
+/*
+ * Learning the structure of a model from case data
+ *
+ * The search starts from the required edges and repeatedly adds, removes or
+ * reverses the edge that improves the score of the model the most. The score is
+ * a sum over the nodes of a local score of each node given its parents, so that
+ * a move only needs the local scores of the nodes whose parents change.
+ *
+ * Hill-climbing stops as soon as no move improves the score, while tabu search
+ * keeps going with the best move that does not undo a recent one, escaping
+ * local maxima, and keeps the best structure it went through.
+ *
+ * Rows where a node or one of its parents is missing are left out of the local
+ * score of that node.
+ */
+
+/// Nodes never get more parents than this, which keeps their tables small
+pub const MAX_PARENTS: usize = 3;
+// the number of recent moves that cannot be undone during tabu search
+const TABU_LENGTH: usize = 5;
+// tabu search stops after this many moves without finding a better structure
+const TABU_PATIENCE: usize = 20;
+const MAX_MOVES: usize = 1000;
+// score improvements smaller than this are rounding errors
+const EPSILON: f64 = 1e-9;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Score {
+    // the Bayesian information criterion
+    Bic,
+    // the Bayesian Dirichlet equivalent uniform score, with its equivalent sample
+    // size
+    Bdeu(f64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Search {
+    HillClimbing,
+    Tabu,
+}
+
+/// Edges from a parent to a child that the structure must have, or must not have
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Constraints {
+    pub required: Vec<(usize, usize)>,
+    pub forbidden: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructureReport {
+    pub score: f64,
+    pub moves: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Move {
+    Add(usize, usize),
+    Remove(usize, usize),
+    Reverse(usize, usize),
+}
+
+impl Move {
+    /// The move going back to the structure before this one
+    fn undo(self) -> Move {
+        match self {
+            Move::Add(p, c) => Move::Remove(p, c),
+            Move::Remove(p, c) => Move::Add(p, c),
+            Move::Reverse(p, c) => Move::Reverse(c, p),
+        }
+    }
+}
+
+/// The logarithm of the gamma function, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        std::f64::consts::PI.ln() - (std::f64::consts::PI * x).sin().ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let sum = COEFFICIENTS[1..]
+            .iter()
+            .enumerate()
+            .fold(COEFFICIENTS[0], |sum, (i, &c)| {
+                sum + c / (x + i as f64 + 1.0)
+            });
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+    }
+}
+
+/// Local scores of the nodes given a set of parents, computed once
+struct Scorer<'a> {
+    cases: &'a Cases,
+    cards: Vec<usize>,
+    score: Score,
+    cache: HashMap<(usize, Vec<usize>), f64>,
+}
+
+impl<'a> Scorer<'a> {
+    fn local(&mut self, node: usize, parents: &[usize]) -> f64 {
+        let mut parents = parents.to_vec();
+        parents.sort_unstable();
+        let key = (node, parents);
+        if let Some(&score) = self.cache.get(&key) {
+            return score;
+        }
+        let parents = &key.1;
+        let r = self.cards[node];
+        let q: usize = parents.iter().map(|&p| self.cards[p]).product();
+        let mut counts = vec![0.0; q * r];
+        let mut total = 0.0;
+        'rows: for row in &self.cases.rows {
+            let mut j = 0;
+            for &p in parents {
+                match row[p] {
+                    Some(v) => j = j * self.cards[p] + v,
+                    None => continue 'rows,
+                }
+            }
+            if let Some(v) = row[node] {
+                counts[j * r + v] += 1.0;
+                total += 1.0;
+            }
+        }
+        let score = match self.score {
+            Score::Bic => {
+                let mut log_likelihood = 0.0;
+                for row in counts.chunks(r) {
+                    let n: f64 = row.iter().sum();
+                    for &c in row.iter().filter(|&&c| c > 0.0) {
+                        log_likelihood += c * (c / n).ln();
+                    }
+                }
+                let parameters = (q * (r - 1)) as f64;
+                log_likelihood - 0.5 * f64::ln(f64::max(total, 1.0)) * parameters
+            }
+            Score::Bdeu(ess) => {
+                let a_row = ess / q as f64;
+                let a_cell = ess / (q * r) as f64;
+                counts
+                    .chunks(r)
+                    .map(|row| {
+                        let n: f64 = row.iter().sum();
+                        ln_gamma(a_row) - ln_gamma(a_row + n)
+                            + row
+                                .iter()
+                                .map(|&c| ln_gamma(a_cell + c) - ln_gamma(a_cell))
+                                .sum::<f64>()
+                    })
+                    .sum()
+            }
+        };
+        self.cache.insert(key, score);
+        score
+    }
+
+    /// The change of the score of the model if the move was made
+    fn delta(&mut self, dag: &Dag, m: Move) -> f64 {
+        let parents = |n: usize| dag.get(n).unwrap().parents.clone();
+        let without = |n: usize, p: usize| -> Vec<usize> {
+            parents(n).into_iter().filter(|&q| q != p).collect()
+        };
+        let with = |n: usize, p: usize| -> Vec<usize> {
+            let mut parents = parents(n);
+            parents.push(p);
+            parents
+        };
+        match m {
+            Move::Add(p, c) => self.local(c, &with(c, p)) - self.local(c, &parents(c)),
+            Move::Remove(p, c) => self.local(c, &without(c, p)) - self.local(c, &parents(c)),
+            Move::Reverse(p, c) => {
+                self.local(c, &without(c, p)) - self.local(c, &parents(c))
+                    + self.local(p, &with(p, c))
+                    - self.local(p, &parents(p))
+            }
+        }
+    }
+
+    fn total(&mut self, dag: &Dag) -> f64 {
+        dag.iter_nodes()
+            .map(|(i, node)| (i, node.parents.clone()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(i, parents)| self.local(i, &parents))
+            .sum()
+    }
+}
+
+/// The moves that keep the model acyclic and respect the constraints
+fn moves(dag: &Dag, constraints: &Constraints) -> Vec<Move> {
+    let nodes: Vec<usize> = dag.iter_nodes().map(|(i, _)| i).collect();
+    let parent_count = |n: usize| dag.get(n).unwrap().parents.len();
+    let mut moves = Vec::new();
+    for &p in &nodes {
+        for &c in &nodes {
+            if p == c {
+                continue;
+            }
+            if dag.get(c).unwrap().parents.contains(&p) {
+                if constraints.required.contains(&(p, c)) {
+                    continue;
+                }
+                moves.push(Move::Remove(p, c));
+                if !constraints.forbidden.contains(&(c, p))
+                    && parent_count(p) < MAX_PARENTS
+                    && dag.check_edge_reversal(c, p).is_ok()
+                {
+                    moves.push(Move::Reverse(p, c));
+                }
+            } else if !constraints.forbidden.contains(&(p, c))
+                && parent_count(c) < MAX_PARENTS
+                && dag.check_edge_addition(c, p).is_ok()
+            {
+                moves.push(Move::Add(p, c));
+            }
+        }
+    }
+    moves
+}
+
+fn apply(dag: &mut Dag, m: Move) {
+    match m {
+        Move::Add(p, c) => dag.add_edge(c, p).unwrap(),
+        Move::Remove(p, c) => dag.remove_edge(c, p),
+        Move::Reverse(p, c) => {
+            dag.remove_edge(c, p);
+            dag.add_edge(p, c).unwrap();
+        }
+    }
+}
+
+/// Add edges to a model whose nodes all have values, choosing them to fit the
+/// data. Fails if the required edges make a cycle, giving the offending edge.
+pub fn learn_structure(
+    dag: &mut Dag,
+    cases: &Cases,
+    score: Score,
+    search: Search,
+    constraints: &Constraints,
+) -> Result<StructureReport, ((usize, usize), EdgeError)> {
+    for &(p, c) in &constraints.required {
+        match dag.add_edge(c, p) {
+            Ok(()) | Err(EdgeError::AlreadyExisting) => {}
+            Err(e) => return Err(((p, c), e)),
+        }
+    }
+
+    let mut scorer = Scorer {
+        cases,
+        cards: (0..cases.columns.len())
+            .map(|i| dag.get(i).map_or(0, |n| n.values.len()))
+            .collect(),
+        score,
+        cache: HashMap::new(),
+    };
+    let mut current = scorer.total(dag);
+    let mut best = (current, Vec::new());
+    let mut history: Vec<Move> = Vec::new();
+    let mut since_best = 0;
+    while history.len() < MAX_MOVES {
+        let tabu: Vec<Move> = history
+            .iter()
+            .rev()
+            .take(TABU_LENGTH)
+            .map(|m| m.undo())
+            .collect();
+        let candidate = moves(dag, constraints)
+            .into_iter()
+            .filter(|m| search == Search::HillClimbing || !tabu.contains(m))
+            .map(|m| (scorer.delta(dag, m), m))
+            .fold(None, |best: Option<(f64, Move)>, (delta, m)| match best {
+                Some((d, _)) if d >= delta => best,
+                _ => Some((delta, m)),
+            });
+        let (delta, m) = match candidate {
+            Some((delta, m)) if delta > EPSILON || search == Search::Tabu => (delta, m),
+            _ => break,
+        };
+        apply(dag, m);
+        history.push(m);
+        current += delta;
+        if current > best.0 + EPSILON {
+            best = (current, history.clone());
+            since_best = 0;
+        } else {
+            since_best += 1;
+            if since_best >= TABU_PATIENCE {
+                break;
+            }
+        }
+    }
+
+    // go back to the best structure found
+    while history.len() > best.1.len() {
+        apply(dag, history.pop().unwrap().undo());
+    }
+    Ok(StructureReport {
+        score: best.0,
+        moves: history.len(),
+    })
+}
+
+/*
+ * Classifiers
+ *
+ * A naive Bayes model has an edge from the class to every other node, the other
+ * nodes being independent given the class. A tree-augmented naive Bayes model
+ * also links the other nodes by a tree, each having at most one parent besides
+ * the class. The tree maximizes the sum of the mutual information of the linked
+ * nodes given the class, which makes it the tree that best fits the data.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Classifier {
+    NaiveBayes,
+    TreeAugmented,
+}
+
+/// The mutual information of two nodes given a third, from the rows where all
+/// three are known
+fn conditional_mutual_information(
+    cases: &Cases,
+    cards: &[usize],
+    a: usize,
+    b: usize,
+    given: usize,
+) -> f64 {
+    let (ca, cb, cg) = (cards[a], cards[b], cards[given]);
+    let mut counts = vec![0.0; cg * ca * cb];
+    let mut total = 0.0;
+    for row in &cases.rows {
+        if let (Some(va), Some(vb), Some(vg)) = (row[a], row[b], row[given]) {
+            counts[(vg * ca + va) * cb + vb] += 1.0;
+            total += 1.0;
+        }
+    }
+    let mut information = 0.0;
+    for g in 0..cg {
+        let block = &counts[g * ca * cb..(g + 1) * ca * cb];
+        let n: f64 = block.iter().sum();
+        for va in 0..ca {
+            let na: f64 = block[va * cb..(va + 1) * cb].iter().sum();
+            for vb in 0..cb {
+                let nab = block[va * cb + vb];
+                if nab > 0.0 {
+                    let nb: f64 = (0..ca).map(|v| block[v * cb + vb]).sum();
+                    information += nab / total * (nab * n / (na * nb)).ln();
+                }
+            }
+        }
+    }
+    information
+}
+
+/// Add the edges of a classifier of the `class` node to a model without edges
+/// whose nodes all have values
+pub fn build_classifier(dag: &mut Dag, cases: &Cases, class: usize, classifier: Classifier) {
+    let features: Vec<usize> = dag
+        .iter_nodes()
+        .map(|(i, _)| i)
+        .filter(|&i| i != class)
+        .collect();
+    for &f in &features {
+        dag.add_edge(f, class).unwrap();
+    }
+    if classifier == Classifier::TreeAugmented && !features.is_empty() {
+        let cards: Vec<usize> = (0..cases.columns.len())
+            .map(|i| dag.get(i).map_or(0, |n| n.values.len()))
+            .collect();
+        // Prim's algorithm for the maximum spanning tree, rooted at the first node
+        let mut in_tree = vec![features[0]];
+        let mut best: Vec<(f64, usize)> = features
+            .iter()
+            .map(|&f| {
+                let weight = conditional_mutual_information(cases, &cards, f, features[0], class);
+                (weight, features[0])
+            })
+            .collect();
+        while in_tree.len() < features.len() {
+            let k = (0..features.len())
+                .filter(|&k| !in_tree.contains(&features[k]))
+                .fold(None, |chosen: Option<usize>, k| match chosen {
+                    Some(c) if best[c].0 >= best[k].0 => chosen,
+                    _ => Some(k),
+                })
+                .unwrap();
+            let node = features[k];
+            dag.add_edge(node, best[k].1).unwrap();
+            in_tree.push(node);
+            for (j, &f) in features.iter().enumerate() {
+                if !in_tree.contains(&f) {
+                    let weight = conditional_mutual_information(cases, &cards, f, node, class);
+                    if weight > best[j].0 {
+                        best[j] = (weight, node);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Dataset;
+    use crate::random::Rng;
+
+    /// A dataset whose columns are drawn in order, each from a function of the
+    /// values already drawn
+    fn dataset(header: &str, rows: usize, draw: impl Fn(&mut Rng, &[usize]) -> usize) -> Dataset {
+        let columns = header.split(',').count();
+        let mut rng = Rng::new(3);
+        let mut text = format!("{}\n", header);
+        for _ in 0..rows {
+            let mut values = Vec::with_capacity(columns);
+            for _ in 0..columns {
+                let value = draw(&mut rng, &values);
+                values.push(value);
+            }
+            let cells: Vec<String> = values.iter().map(|v| format!("v{}", v)).collect();
+            text += &cells.join(",");
+            text.push('\n');
+        }
+        Dataset::parse(&text).unwrap()
+    }
+
+    /// A value equal to `value` with the given probability, otherwise random
+    fn noisy(rng: &mut Rng, value: usize, keep: f64) -> usize {
+        if rng.next_f64() < keep {
+            value
+        } else {
+            rng.choose(&[1.0, 1.0])
+        }
+    }
+
+    fn linked(dag: &Dag, a: usize, b: usize) -> bool {
+        dag.get(a).unwrap().parents.contains(&b) || dag.get(b).unwrap().parents.contains(&a)
+    }
+
+    #[test]
+    fn gamma() {
+        let close = |x: f64, expected: f64| {
+            assert!((ln_gamma(x) - expected).abs() < 1e-10, "ln_gamma({})", x)
+        };
+        close(1.0, 0.0);
+        close(2.0, 0.0);
+        close(5.0, 24f64.ln());
+        close(0.5, std::f64::consts::PI.sqrt().ln());
+        close(0.1, 9.513_507_698_668_732f64.ln());
+        close(10.5, 1_133_278.388_948_785_4f64.ln());
+    }
+
+    #[test]
+    fn dependent_columns() {
+        // b copies a most of the time, c is independent
+        let data = dataset("a,b,c", 500, |rng, values| match values.len() {
+            1 => noisy(rng, values[0], 0.8),
+            _ => rng.choose(&[1.0, 1.0]),
+        });
+        for &score in &[Score::Bic, Score::Bdeu(1.0)] {
+            for &search in &[Search::HillClimbing, Search::Tabu] {
+                let (mut dag, _) = data.to_dag();
+                let cases = data.match_nodes(&dag);
+                let report =
+                    learn_structure(&mut dag, &cases, score, search, &Constraints::default())
+                        .unwrap();
+                assert!(linked(&dag, 0, 1));
+                assert!(!linked(&dag, 0, 2) && !linked(&dag, 1, 2));
+                // the structure kept is the one whose score is reported
+                let mut scorer = Scorer {
+                    cases: &cases,
+                    cards: vec![2; 3],
+                    score,
+                    cache: HashMap::new(),
+                };
+                assert!((scorer.total(&dag) - report.score).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn constraints() {
+        let data = dataset("a,b,c", 200, |rng, values| match values.len() {
+            1 => noisy(rng, values[0], 0.8),
+            _ => rng.choose(&[1.0, 1.0]),
+        });
+        let (mut dag, _) = data.to_dag();
+        let cases = data.match_nodes(&dag);
+        let constraints = Constraints {
+            required: vec![(0, 2)],
+            forbidden: vec![(0, 1), (1, 0)],
+        };
+        learn_structure(&mut dag, &cases, Score::Bic, Search::Tabu, &constraints).unwrap();
+        assert_eq!(dag.get(2).unwrap().parents, vec![0]);
+        assert!(!linked(&dag, 0, 1));
+
+        // required edges making a cycle
+        let (mut dag, _) = data.to_dag();
+        let constraints = Constraints {
+            required: vec![(0, 1), (1, 2), (2, 0)],
+            forbidden: Vec::new(),
+        };
+        let result = learn_structure(&mut dag, &cases, Score::Bic, Search::Tabu, &constraints);
+        assert!(matches!(result, Err(((2, 0), _))));
+    }
+
+    #[test]
+    fn classifiers() {
+        // x1 depends on the class c, x2 on x1, x3 on x2, and x4 on nothing
+        let data = dataset("c,x1,x2,x3,x4", 1000, |rng, values| match values.len() {
+            0 | 4 => rng.choose(&[1.0, 1.0]),
+            n => noisy(rng, values[n - 1], 0.7),
+        });
+        let (mut dag, _) = data.to_dag();
+        let cases = data.match_nodes(&dag);
+        build_classifier(&mut dag, &cases, 0, Classifier::NaiveBayes);
+        for f in 1..5 {
+            assert_eq!(dag.get(f).unwrap().parents, vec![0]);
+        }
+
+        let (mut dag, _) = data.to_dag();
+        build_classifier(&mut dag, &cases, 0, Classifier::TreeAugmented);
+        assert!(dag.get(0).unwrap().parents.is_empty());
+        let mut extra = 0;
+        for f in 1..5 {
+            let parents = &dag.get(f).unwrap().parents;
+            assert_eq!(parents[0], 0);
+            assert!(parents.len() <= 2);
+            extra += parents.len() - 1;
+        }
+        // a tree over the four features
+        assert_eq!(extra, 3);
+        assert!(linked(&dag, 1, 2) && linked(&dag, 2, 3));
+
+        let cards = vec![2; 5];
+        assert!(conditional_mutual_information(&cases, &cards, 1, 2, 0) > 0.05);
+        assert!(conditional_mutual_information(&cases, &cards, 1, 4, 0) < 0.01);
+    }
+}
@@ -0,0 +1,139 @@
+use ndarray::{ArrayD, Axis};
+
+use crate::graph::Dag;
+use crate::random::Rng;
+
+/*
+ * Uncertain credencies
+ *
+ * A row of a table can be given a sample size, the number of cases it is worth.
+ * Its credencies are then the parameters of a Dirichlet distribution: the
+ * normalized row is the expected probability of each value, and the sample size
+ * how concentrated the probabilities are around it, as if the row had been
+ * learned from that many cases.
+ *
+ * The beliefs are computed again with many tables drawn from these
+ * distributions, and the spread of the results gives an interval around each
+ * belief.
+ */
+
+/// The number of sets of tables drawn to compute the intervals
+pub const INTERVAL_SAMPLES: usize = 100;
+/// The probability that a belief lies in its interval
+pub const INTERVAL_LEVEL: f64 = 0.9;
+// the intervals are the same each time the beliefs are computed
+const SEED: u64 = 0x5eed_d1c1_e7a1;
+
+/// Whether any row of the model has a sample size
+pub fn is_uncertain(dag: &Dag) -> bool {
+    dag.iter_nodes()
+        .any(|(_, node)| !node.sample_sizes.is_empty())
+}
+
+/// A new generator for drawing tables
+pub fn rng() -> Rng {
+    Rng::new(SEED)
+}
+
+/// Draw a table for each node having sample sizes, indexed like the nodes. The
+/// rows without a sample size keep their normalized credencies.
+pub fn sample_tables(dag: &Dag, rng: &mut Rng) -> Vec<Option<ArrayD<f32>>> {
+    (0..dag.node_ids().len())
+        .map(|i| {
+            let node = dag.get(i)?;
+            if node.sample_sizes.is_empty() {
+                return None;
+            }
+            let mut table = dag.normalized_credencies(i)?;
+            for (mut row, &size) in table.lanes_mut(Axis(0)).into_iter().zip(&node.sample_sizes) {
+                if size <= 0.0 {
+                    continue;
+                }
+                let alphas: Vec<f64> = row.iter().map(|&p| f64::from(p * size)).collect();
+                for (v, p) in row.iter_mut().zip(rng.dirichlet(&alphas)) {
+                    *v = p as f32;
+                }
+            }
+            Some(table)
+        })
+        .collect()
+}
+
+/// The interval holding `INTERVAL_LEVEL` of the draws, leaving out as many of
+/// the lowest as of the highest
+pub fn interval(draws: &mut [f64]) -> (f64, f64) {
+    if draws.is_empty() {
+        return (0.0, 1.0);
+    }
+    draws.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    // without the margin, rounding errors would leave one draw less out
+    let tail = ((1.0 - INTERVAL_LEVEL) / 2.0 * draws.len() as f64 + 1e-9).floor() as usize;
+    (draws[tail], draws[draws.len() - 1 - tail])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::text;
+
+    fn model(size: f32) -> Dag {
+        text::read(&format!(
+            "node a {{\n    values x, y\n    sample_sizes {}\n}}\n\
+             node b {{\n    values u, v\n    sample_sizes 0, 40\n}}\n\
+             node c {{\n    values t, f\n}}\n\
+             a -> b\n\
+             table a {{ 3, 7 }}\n\
+             table b {{\n    (x): 0.9, 0.1\n    (y): 0.2, 0.8\n}}\n",
+            size
+        ))
+        .unwrap()
+        .0
+    }
+
+    /// The interval of P(a = x) over the drawn tables
+    fn prior_interval(dag: &Dag) -> (f64, f64) {
+        let mut rng = rng();
+        let mut draws: Vec<f64> = (0..INTERVAL_SAMPLES)
+            .map(|_| f64::from(sample_tables(dag, &mut rng)[0].as_ref().unwrap()[[0]]))
+            .collect();
+        interval(&mut draws)
+    }
+
+    #[test]
+    fn interval_leaves_out_both_tails() {
+        let mut draws: Vec<f64> = (0..100).rev().map(f64::from).collect();
+        // 5 draws are left out on each side
+        assert_eq!(interval(&mut draws), (5.0, 94.0));
+        let mut draws: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(interval(&mut draws), (0.0, 9.0));
+        assert_eq!(interval(&mut []), (0.0, 1.0));
+    }
+
+    #[test]
+    fn tables_are_drawn_for_uncertain_nodes() {
+        let dag = model(10.0);
+        assert!(is_uncertain(&dag));
+        let tables = sample_tables(&dag, &mut rng());
+        assert_eq!(tables.len(), 3);
+        assert!(tables[2].is_none());
+        let a = tables[0].as_ref().unwrap();
+        assert!((a[[0]] + a[[1]] - 1.0).abs() < 1e-5);
+        // a row without sample size keeps its normalized credencies
+        let b = tables[1].as_ref().unwrap();
+        assert_eq!((b[[0, 0]], b[[1, 0]]), (0.9, 0.1));
+        assert!(b[[0, 1]] != 0.2);
+        assert!(!is_uncertain(
+            &text::read("node a {\n    values x\n}\n").unwrap().0
+        ));
+    }
+
+    #[test]
+    fn larger_samples_narrow_the_interval() {
+        let (low, high) = prior_interval(&model(10.0));
+        assert!(low < 0.3 && 0.3 < high);
+        assert!(high - low > 0.2);
+        let (low, high) = prior_interval(&model(100_000.0));
+        assert!(low < 0.3 && 0.3 < high);
+        assert!(high - low < 0.02);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::graph::EdgeError;
+
+/*
+ * Validation of the models loaded from files
+ *
+ * Rather than silently dropping the invalid parts of a file, loading it
+ * produces a report of all the problems found, each attached to a node and a
+ * field of the file.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Severity {
+    // the offending data could not be loaded
+    Error,
+    // the data was loaded, but is likely not what was intended
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    DuplicateId(String),
+    UnknownParent(String),
+    Edge(EdgeError, String),
+    NoValues,
+    DuplicateValue(String),
+    ObservationOutOfRange { observation: usize, values: usize },
+    CredenciesLength { expected: usize, found: usize },
+    NegativeCredency(f32),
+    DescriptionsCount { expected: usize, found: usize },
+    SampleSizesCount { expected: usize, found: usize },
+    InvalidRules,
+    // the field it was found in, and a construct of the file format that the
+    // app cannot represent
+    Unsupported(&'static str, String),
+    // the field it was found in, and a name that is neither a value of the
+    // node or of its parents, nor a parent
+    UnknownName(&'static str, String),
+    // the values of the parents and of the node whose credency is missing
+    MissingCredency(String),
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Problem::NoValues
+            | Problem::DuplicateValue(_)
+            | Problem::NegativeCredency(_)
+            | Problem::SampleSizesCount { .. }
+            | Problem::Unsupported(_, _) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The field of the node in the file this problem is about
+    pub fn field(&self) -> &'static str {
+        match *self {
+            Problem::DuplicateId(_) => "id",
+            Problem::UnknownParent(_) | Problem::Edge(_, _) => "parents",
+            Problem::NoValues | Problem::DuplicateValue(_) => "values",
+            Problem::ObservationOutOfRange { .. } => "observation",
+            Problem::CredenciesLength { .. } | Problem::NegativeCredency(_) => "credencies",
+            Problem::DescriptionsCount { .. } => "cred_description",
+            Problem::SampleSizesCount { .. } => "sample_sizes",
+            Problem::InvalidRules => "rules",
+            Problem::Unsupported(field, _) | Problem::UnknownName(field, _) => field,
+            Problem::MissingCredency(_) => "credencies",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    // the label of the node, or its position in the file if it has none
+    pub node: String,
+    pub problem: Problem,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn new() -> ValidationReport {
+        ValidationReport { issues: Vec::new() }
+    }
+
+    pub fn push(&mut self, node: String, problem: Problem) {
+        self.issues.push(Issue { node, problem });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|i| i.problem.severity() == Severity::Error)
+    }
+}